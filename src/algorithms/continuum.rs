@@ -0,0 +1,149 @@
+use ndarray::{Array2, ArrayBase, Data, Ix2};
+use num_traits::{Float, FromPrimitive};
+
+use crate::error::{VanadiumError, VanadiumResult};
+
+/// Continuum removal over a range of bands, with an optional absorption feature to measure.
+///
+/// The continuum of each spectrum is the upper convex hull of its points across wavelength, and
+/// is divided out band by band.
+pub struct ContinuumRemoval {
+    /// Bands within the window, in order of wavelength.
+    bands: Vec<usize>,
+    wavelengths: Vec<f64>,
+    /// Range of positions in `bands` covered by the absorption feature.
+    feature: Option<(usize, usize)>,
+}
+
+impl ContinuumRemoval {
+    /// `window` and `feature` are inclusive wavelength ranges, in the same units as `wavelengths`.
+    pub fn new(
+        wavelengths: &[f64],
+        window: Option<(f64, f64)>,
+        feature: Option<(f64, f64)>,
+    ) -> VanadiumResult<Self> {
+        let (lo, hi) = window.unwrap_or((f64::NEG_INFINITY, f64::INFINITY));
+
+        let mut bands: Vec<usize> = (0..wavelengths.len())
+            .filter(|i| wavelengths[*i] >= lo && wavelengths[*i] <= hi)
+            .collect();
+
+        bands.sort_by(|a, b| wavelengths[*a].total_cmp(&wavelengths[*b]));
+
+        if bands.len() < 2 {
+            return Err(VanadiumError::InvalidArgs("Window must contain at least two bands".to_owned()));
+        }
+
+        let wavelengths: Vec<f64> = bands.iter().map(|i| wavelengths[*i]).collect();
+
+        let feature = match feature {
+            Some((start, end)) => {
+                let first = wavelengths.iter().position(|w| *w >= start);
+                let last = wavelengths.iter().rposition(|w| *w <= end);
+
+                match (first, last) {
+                    (Some(first), Some(last)) if first <= last => Some((first, last)),
+                    _ => return Err(VanadiumError::InvalidArgs(
+                        "Absorption feature contains no bands within the window".to_owned()
+                    )),
+                }
+            }
+            None => None,
+        };
+
+        Ok(Self { bands, wavelengths, feature })
+    }
+
+    /// Source bands of the continuum removed outputs, in output order.
+    pub fn bands(&self) -> &[usize] {
+        &self.bands
+    }
+
+    /// One output per band in the window, followed by band depth and band center if an
+    /// absorption feature was given.
+    pub fn n_outputs(&self) -> usize {
+        self.bands.len() + if self.feature.is_some() { 2 } else { 0 }
+    }
+
+    /// Writes the continuum removed spectrum of each pixel to `out`, followed by the depth and
+    /// center wavelength of the absorption feature.
+    ///
+    /// Bands where the continuum is not positive, such as in empty pixels, are left at 1, so that
+    /// they show no absorption. Bands which are NaN stay NaN, and are never the feature's center.
+    pub fn remove<S, T>(&self, pixels: &ArrayBase<S, Ix2>, out: &mut Array2<T>)
+        where S: Data<Elem=T>,
+              T: Float + FromPrimitive
+    {
+        let n = self.bands.len();
+
+        let mut spectrum = vec![0.0; n];
+        let mut removed = vec![0.0; n];
+        let mut hull = Vec::with_capacity(n);
+
+        for (pixel, mut o) in pixels.outer_iter().zip(out.outer_iter_mut()) {
+            for (s, b) in spectrum.iter_mut().zip(&self.bands) {
+                *s = pixel[*b].to_f64().unwrap();
+            }
+
+            upper_hull(&self.wavelengths, &spectrum, &mut hull);
+
+            // bands outside the hull, which only happens where the ends are NaN, are unknown
+            removed.fill(f64::NAN);
+
+            for segment in hull.windows(2) {
+                let (a, b) = (segment[0], segment[1]);
+                let slope = (spectrum[b] - spectrum[a]) / (self.wavelengths[b] - self.wavelengths[a]);
+
+                for k in a..=b {
+                    let continuum = spectrum[a] + slope * (self.wavelengths[k] - self.wavelengths[a]);
+                    removed[k] = if continuum > 0.0 { spectrum[k] / continuum } else { 1.0 };
+                }
+            }
+
+            for (x, r) in o.iter_mut().zip(&removed) {
+                *x = T::from_f64(*r).unwrap();
+            }
+
+            if let Some((first, last)) = self.feature {
+                let deepest = (first..=last)
+                    .filter(|k| !removed[*k].is_nan())
+                    .min_by(|a, b| removed[*a].total_cmp(&removed[*b]));
+
+                let (depth, center) = match deepest {
+                    Some(k) => (1.0 - removed[k], self.wavelengths[k]),
+                    None => (f64::NAN, f64::NAN),
+                };
+
+                o[n] = T::from_f64(depth).unwrap();
+                o[n + 1] = T::from_f64(center).unwrap();
+            }
+        }
+    }
+}
+
+/// Indices of the points on the upper convex hull, for points sorted by `x`, skipping NaN.
+fn upper_hull(x: &[f64], y: &[f64], hull: &mut Vec<usize>) {
+    hull.clear();
+
+    for i in 0..x.len() {
+        if y[i].is_nan() {
+            continue;
+        }
+
+        while hull.len() >= 2 {
+            let o = hull[hull.len() - 2];
+            let a = hull[hull.len() - 1];
+
+            // drop the last point if it lies on or below the line from its predecessor to this one
+            let cross = (x[a] - x[o]) * (y[i] - y[o]) - (y[a] - y[o]) * (x[i] - x[o]);
+
+            if cross >= 0.0 {
+                hull.pop();
+            } else {
+                break;
+            }
+        }
+
+        hull.push(i);
+    }
+}
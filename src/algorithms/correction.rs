@@ -0,0 +1,80 @@
+use ndarray::{Array1, Array2, Axis, Zip};
+use num_traits::{Float, FromPrimitive};
+
+/// Means and standard deviations of every band of every column, taken over the lines.
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct ColumnStatistics<T> {
+    /// One row per column, one column per band.
+    pub means: Array2<T>,
+    pub std_devs: Array2<T>,
+}
+
+impl<T> ColumnStatistics<T> where T: Float + FromPrimitive + 'static {
+    /// Band means and standard deviations of the whole image.
+    ///
+    /// Every column covers the same number of lines, so the image variance is the average
+    /// within-column variance plus the variance of the column means.
+    pub fn global(&self) -> (Array1<T>, Array1<T>) {
+        let n = T::from_usize(self.means.nrows()).unwrap();
+
+        let means = self.means.sum_axis(Axis(0)).mapv(|x| x / n);
+
+        let mut variances = self.std_devs.mapv(|x| x * x).sum_axis(Axis(0));
+
+        for row in self.means.outer_iter() {
+            Zip::from(&mut variances).and(&row).and(&means).for_each(|v, x, m| {
+                *v = *v + (*x - *m).powi(2)
+            });
+        }
+
+        (means, variances.mapv(|v| (v / n).sqrt()))
+    }
+
+    /// Gains and offsets which give every column the global mean and standard deviation of each
+    /// band.
+    ///
+    /// Columns with no variation in a band are only shifted.
+    pub fn destripe_coefficients(&self) -> (Array2<T>, Array2<T>) {
+        let (means, std_devs) = self.global();
+
+        let mut gains = Array2::ones(self.means.raw_dim());
+        let mut offsets = Array2::zeros(self.means.raw_dim());
+
+        for (((mut g, mut o), m), s) in gains.outer_iter_mut()
+            .zip(offsets.outer_iter_mut())
+            .zip(self.means.outer_iter())
+            .zip(self.std_devs.outer_iter())
+        {
+            Zip::from(&mut g).and(&s).and(&std_devs).for_each(|g, s, global| {
+                if *s > T::zero() {
+                    *g = *global / *s;
+                }
+            });
+
+            Zip::from(&mut o).and(&g).and(&m).and(&means).for_each(|o, g, m, global| {
+                *o = *global - *m * *g
+            });
+        }
+
+        (gains, offsets)
+    }
+}
+
+/// Per-column, per-band gain of a flat-field reference, relative to the band's average gain.
+///
+/// `dark` and `flat` are column means, one row per column, and the dark level is removed from the
+/// flat before the gain is taken.
+/// Normalizing by the average keeps corrected values in the units of the input.
+/// Columns with no response above the dark level, such as dead ones, get a gain of one, so that
+/// only their dark level is removed.
+pub fn flat_field_gain<T>(dark: &Array2<T>, flat: &Array2<T>) -> Array2<T>
+    where T: Float + FromPrimitive + 'static
+{
+    let response = flat - dark;
+
+    let n = T::from_usize(response.nrows()).unwrap();
+    let average = response.sum_axis(Axis(0)).mapv(|x| x / n);
+
+    (response / &average).mapv(|g| if g.is_finite() && g > T::zero() { g } else { T::one() })
+}
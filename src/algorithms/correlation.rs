@@ -0,0 +1,96 @@
+use ndarray::{Array2, ArrayBase, Data, Ix2};
+use num_traits::Float;
+
+/// Normalizes a covariance matrix into a correlation matrix.
+///
+/// Bands with no variance have no defined correlation, and are reported as uncorrelated with
+/// every other band.
+pub fn correlation_matrix<T, S>(covariances: &ArrayBase<S, Ix2>) -> Array2<T>
+    where T: Float,
+          S: Data<Elem=T>
+{
+    let scales = covariances.diag().mapv(|v| v.max(T::zero()).sqrt());
+
+    Array2::from_shape_fn(covariances.raw_dim(), |(i, j)| {
+        if i == j {
+            T::one()
+        } else if scales[i] > T::zero() && scales[j] > T::zero() {
+            // Clamped, as rounding can carry near-identical bands slightly past one
+            (covariances[[i, j]] / (scales[i] * scales[j])).max(-T::one()).min(T::one())
+        } else {
+            T::zero()
+        }
+    })
+}
+
+/// Two bands and the correlation between them.
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct BandPair {
+    pub bands: (usize, usize),
+    pub correlation: f64,
+}
+
+/// The most redundant band pairs of an image, and a subset of bands with the redundancy removed.
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct CorrelationReport {
+    /// Band pairs ordered from the strongest absolute correlation down.
+    pub pairs: Vec<BandPair>,
+    pub threshold: f64,
+    /// Bands in which no pair is correlated more strongly than the threshold.
+    pub subset: Vec<usize>,
+    /// Bands left out of the subset, in the order they were dropped.
+    pub dropped: Vec<usize>,
+}
+
+impl CorrelationReport {
+    /// Reports the `top` most correlated pairs, and chooses a decorrelated subset.
+    ///
+    /// While the strongest remaining absolute correlation exceeds `threshold`, one band of that
+    /// pair is dropped: whichever has the higher mean absolute correlation with the other
+    /// remaining bands, as it carries the least information of its own. Ties keep the lower band.
+    pub fn new<T, S>(correlations: &ArrayBase<S, Ix2>, top: usize, threshold: f64) -> Self
+        where T: Float,
+              S: Data<Elem=T>
+    {
+        let bands = correlations.nrows();
+        let r = |i: usize, j: usize| correlations[[i, j]].to_f64().unwrap();
+
+        let mut pairs: Vec<BandPair> = (0..bands)
+            .flat_map(|i| ((i + 1)..bands).map(move |j| (i, j)))
+            .map(|(i, j)| BandPair { bands: (i, j), correlation: r(i, j) })
+            .collect();
+
+        pairs.sort_by(|a, b| b.correlation.abs().partial_cmp(&a.correlation.abs()).unwrap());
+
+        let mut subset: Vec<usize> = (0..bands).collect();
+        let mut dropped = Vec::new();
+
+        let mean_redundancy = |band: usize, subset: &[usize]| -> f64 {
+            let others = subset.iter().filter(|b| **b != band);
+            others.clone().map(|b| r(band, *b).abs()).sum::<f64>() / others.count().max(1) as f64
+        };
+
+        for pair in &pairs {
+            if pair.correlation.abs() <= threshold {
+                break;
+            }
+
+            let (a, b) = pair.bands;
+
+            if !subset.contains(&a) || !subset.contains(&b) {
+                continue;
+            }
+
+            let drop = if mean_redundancy(b, &subset) >= mean_redundancy(a, &subset) { b } else { a };
+
+            subset.retain(|band| *band != drop);
+            dropped.push(drop);
+        }
+
+        pairs.truncate(top);
+
+        Self { pairs, threshold, subset, dropped }
+    }
+}
@@ -0,0 +1,427 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use ndarray::{Array1, Array2, ArrayBase, Axis, Data, Ix2};
+use ndarray_linalg::{Inverse, Lapack, Scalar};
+use num_traits::{Float, FromPrimitive};
+
+use crate::algorithms::pca::Projection;
+use crate::error::{VanadiumError, VanadiumResult};
+
+/// A set of named spectra, along with the pixels they were taken from.
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct SpectralLibrary<T> {
+    pub names: Vec<String>,
+    /// `(line, pixel)` of each spectrum in the source image.
+    pub coordinates: Vec<(usize, usize)>,
+    /// One spectrum per row.
+    pub spectra: Array2<T>,
+    /// Wavelength of each band in nanometres, if the source image declared them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wavelengths: Option<Vec<f64>>,
+}
+
+impl<T> SpectralLibrary<T> where T: Display {
+    pub fn from_pixels(indices: &[usize], spectra: Array2<T>, pixels_per_line: usize) -> Self {
+        Self {
+            names: (0..indices.len()).map(|i| format!("endmember_{}", i)).collect(),
+            coordinates: indices.iter().map(|i| (i / pixels_per_line, i % pixels_per_line)).collect(),
+            spectra,
+            wavelengths: None,
+        }
+    }
+
+    pub fn with_wavelengths(self, wavelengths: Option<Vec<f64>>) -> Self {
+        Self { wavelengths, ..self }
+    }
+
+    /// Writes one spectrum per row, preceded by its name and coordinates.
+    pub fn write_csv<W>(&self, mut writer: W) -> io::Result<()> where W: Write {
+        write!(writer, "name,line,pixel")?;
+
+        match &self.wavelengths {
+            Some(wavelengths) => for w in wavelengths {
+                write!(writer, ",{}", w)?;
+            },
+            None => for band in 0..self.spectra.ncols() {
+                write!(writer, ",band_{}", band)?;
+            },
+        }
+
+        writeln!(writer)?;
+
+        for ((name, (line, pixel)), spectrum) in self.names.iter()
+            .zip(self.coordinates.iter())
+            .zip(self.spectra.outer_iter())
+        {
+            write!(writer, "{},{},{}", name, line, pixel)?;
+
+            for x in spectrum.iter() {
+                write!(writer, ",{}", x)?;
+            }
+
+            writeln!(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A pixel coordinate in a coordinate list, either as `[line, pixel]` or `{"line", "pixel"}`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Coordinate {
+    Pair(usize, usize),
+    Named { line: usize, pixel: usize },
+}
+
+/// Parses a list of `(line, pixel)` coordinates, given either as a JSON array or as CSV.
+///
+/// CSV input has one `line,pixel` pair per row, and may start with a header row.
+pub fn parse_coordinates(s: &str) -> VanadiumResult<Vec<(usize, usize)>> {
+    if s.trim_start().starts_with('[') {
+        let coordinates: Vec<Coordinate> = serde_json::from_str(s)
+            .map_err(|e| VanadiumError::InvalidArgs(format!("Invalid coordinates: {}", e)))?;
+
+        return Ok(coordinates.into_iter()
+            .map(|c| match c {
+                Coordinate::Pair(line, pixel) | Coordinate::Named { line, pixel } => (line, pixel),
+            })
+            .collect());
+    }
+
+    let mut coordinates = Vec::new();
+
+    for (i, row) in s.lines().enumerate().filter(|(_, row)| !row.trim().is_empty()) {
+        let fields: Vec<_> = row.split(',').map(str::trim).collect();
+
+        let parsed = match fields.as_slice() {
+            [line, pixel, ..] => line.parse().and_then(|l| Ok((l, pixel.parse()?))),
+            _ => return Err(VanadiumError::InvalidArgs(format!("Row {} is not line,pixel", i + 1))),
+        };
+
+        match parsed {
+            Ok(c) => coordinates.push(c),
+            Err(_) if i == 0 => continue,
+            Err(_) => return Err(VanadiumError::InvalidArgs(
+                format!("Row {} is not line,pixel", i + 1)
+            )),
+        }
+    }
+
+    Ok(coordinates)
+}
+
+/// A pixel which is extreme along some direction, kept with its full spectrum.
+#[derive(Clone)]
+struct Extreme<T> {
+    score: T,
+    index: usize,
+    spectrum: Array1<T>,
+}
+
+/// Pixel purity index accumulator.
+///
+/// Tracks the pixels at either end of a set of random skewers through the reduced space.
+/// The pixels which land on the ends of the most skewers are the purest.
+pub struct PpiAccumulator<T> {
+    skewers: Array2<T>,
+    minima: Vec<Option<Extreme<T>>>,
+    maxima: Vec<Option<Extreme<T>>>,
+    offset: usize,
+}
+
+impl<T> PpiAccumulator<T> where T: Float + 'static {
+    /// `skewers` holds one unit direction per row, in the reduced space.
+    pub fn new(skewers: Array2<T>) -> Self {
+        let n = skewers.nrows();
+
+        Self {
+            skewers,
+            minima: vec![None; n],
+            maxima: vec![None; n],
+            offset: 0,
+        }
+    }
+
+    pub fn add<S>(&mut self, pixels: &ArrayBase<S, Ix2>, projection: &Projection<T>)
+        where S: Data<Elem=T>
+    {
+        if pixels.nrows() == 0 {
+            return;
+        }
+
+        let scores = projection.project(pixels).dot(&self.skewers.t());
+
+        for (j, column) in scores.axis_iter(Axis(1)).enumerate() {
+            let mut lo = 0;
+            let mut hi = 0;
+
+            for (i, x) in column.iter().enumerate() {
+                if *x < column[lo] {
+                    lo = i;
+                }
+
+                if *x > column[hi] {
+                    hi = i;
+                }
+            }
+
+            let lower = match &self.minima[j] {
+                Some(e) => column[lo] < e.score,
+                None => true,
+            };
+
+            let higher = match &self.maxima[j] {
+                Some(e) => column[hi] > e.score,
+                None => true,
+            };
+
+            if lower {
+                self.minima[j] = Some(Extreme {
+                    score: column[lo],
+                    index: self.offset + lo,
+                    spectrum: pixels.row(lo).to_owned(),
+                });
+            }
+
+            if higher {
+                self.maxima[j] = Some(Extreme {
+                    score: column[hi],
+                    index: self.offset + hi,
+                    spectrum: pixels.row(hi).to_owned(),
+                });
+            }
+        }
+
+        self.offset += pixels.nrows();
+    }
+
+    /// The `n` pixels that were extreme for the most skewers, purest first.
+    pub fn into_library(self, n: usize, pixels_per_line: usize) -> SpectralLibrary<T>
+        where T: Display
+    {
+        let mut counts: HashMap<usize, (usize, Array1<T>)> = HashMap::new();
+
+        for extreme in self.minima.into_iter().chain(self.maxima).flatten() {
+            counts.entry(extreme.index).or_insert((0, extreme.spectrum)).0 += 1;
+        }
+
+        let mut counts: Vec<(usize, (usize, Array1<T>))> = counts.into_iter().collect();
+        counts.sort_by(|a, b| (b.1).0.cmp(&(a.1).0).then(a.0.cmp(&b.0)));
+        counts.truncate(n);
+
+        let indices: Vec<usize> = counts.iter().map(|(i, _)| *i).collect();
+        let spectra = stack_rows(counts.iter().map(|(_, (_, s))| s));
+
+        SpectralLibrary::from_pixels(&indices, spectra, pixels_per_line)
+    }
+}
+
+/// Finds the pixel furthest along a direction in the reduced space, as used by each pass of VCA.
+pub struct ExtremeAccumulator<T> {
+    direction: Array1<T>,
+    best: Option<Extreme<T>>,
+    reduced: Option<Array1<T>>,
+    offset: usize,
+}
+
+impl<T> ExtremeAccumulator<T> where T: Float + 'static {
+    pub fn new(direction: Array1<T>) -> Self {
+        Self {
+            direction,
+            best: None,
+            reduced: None,
+            offset: 0,
+        }
+    }
+
+    pub fn add<S>(&mut self, pixels: &ArrayBase<S, Ix2>, projection: &Projection<T>)
+        where S: Data<Elem=T>
+    {
+        if pixels.nrows() == 0 {
+            return;
+        }
+
+        let reduced = projection.project(pixels);
+        let scores = reduced.dot(&self.direction);
+
+        let mut hi = 0;
+
+        for (i, x) in scores.iter().enumerate() {
+            if x.abs() > scores[hi].abs() {
+                hi = i;
+            }
+        }
+
+        let better = match &self.best {
+            Some(e) => scores[hi].abs() > e.score,
+            None => true,
+        };
+
+        if better {
+            self.best = Some(Extreme {
+                score: scores[hi].abs(),
+                index: self.offset + hi,
+                spectrum: pixels.row(hi).to_owned(),
+            });
+            self.reduced = Some(reduced.row(hi).to_owned());
+        }
+
+        self.offset += pixels.nrows();
+    }
+
+    /// The pixel index, spectrum and reduced coordinates of the most extreme pixel.
+    pub fn into_extreme(self) -> Option<(usize, Array1<T>, Array1<T>)> {
+        let reduced = self.reduced?;
+        self.best.map(|e| (e.index, e.spectrum, reduced))
+    }
+}
+
+/// Removes the components of `v` lying in the span of `basis`, then normalizes it.
+///
+/// This is the `(I - A A^+) w` step of VCA, using modified Gram-Schmidt rather than a
+/// pseudo-inverse.
+pub fn orthogonal_direction<T>(v: Array1<T>, basis: &[Array1<T>]) -> Array1<T>
+    where T: Float + 'static
+{
+    let mut orthonormal: Vec<Array1<T>> = Vec::with_capacity(basis.len());
+
+    for b in basis {
+        let mut u = b.clone();
+
+        for q in &orthonormal {
+            let d = q.dot(&u);
+            u.zip_mut_with(q, |x, y| *x = *x - d * *y);
+        }
+
+        let norm = u.dot(&u).sqrt();
+
+        if norm > T::epsilon() {
+            orthonormal.push(u.mapv(|x| x / norm));
+        }
+    }
+
+    let mut f = v;
+
+    for q in &orthonormal {
+        let d = q.dot(&f);
+        f.zip_mut_with(q, |x, y| *x = *x - d * *y);
+    }
+
+    let norm = f.dot(&f).sqrt();
+
+    f.mapv(|x| x / norm)
+}
+
+/// N-FINDR state: the current simplex and the inverse of its augmented vertex matrix.
+///
+/// With `M = [1; E^T]`, replacing vertex `j` by `y` scales the simplex volume by
+/// `(M^-1 [1; y])_j`, so each candidate pixel costs one small matrix-vector product.
+pub struct NfindrAccumulator<T> {
+    reduced: Array2<T>,
+    inverse: Array2<T>,
+    indices: Vec<usize>,
+    spectra: Array2<T>,
+    offset: usize,
+    replacements: usize,
+}
+
+impl<T> NfindrAccumulator<T> where T: Float + FromPrimitive + Lapack + Scalar + 'static {
+    /// `reduced` holds one vertex per row, in a space with one fewer dimension than vertices.
+    pub fn new(reduced: Array2<T>, indices: Vec<usize>, spectra: Array2<T>) -> VanadiumResult<Self> {
+        let inverse = Self::invert(&reduced).ok_or(VanadiumError::Unknown)?;
+
+        Ok(Self {
+            reduced,
+            inverse,
+            indices,
+            spectra,
+            offset: 0,
+            replacements: 0,
+        })
+    }
+
+    fn invert(reduced: &Array2<T>) -> Option<Array2<T>> {
+        let p = reduced.nrows();
+        let mut m = Array2::ones((p, p));
+
+        m.slice_mut(s![1.., ..]).assign(&reduced.t());
+
+        m.inv().ok()
+    }
+
+    /// Resets the per-pass state before another sweep over the image.
+    pub fn start_pass(&mut self) {
+        self.offset = 0;
+        self.replacements = 0;
+    }
+
+    pub fn replacements(&self) -> usize {
+        self.replacements
+    }
+
+    pub fn add<S>(&mut self, pixels: &ArrayBase<S, Ix2>, projection: &Projection<T>)
+        where S: Data<Elem=T>
+    {
+        let threshold = T::from_f64(1.0 + 1e-5).unwrap();
+
+        let reduced = projection.project(pixels);
+
+        let mut augmented = Array1::ones(self.reduced.nrows());
+
+        for (i, y) in reduced.outer_iter().enumerate() {
+            augmented.slice_mut(s![1..]).assign(&y);
+
+            let w = self.inverse.dot(&augmented);
+
+            let mut best = 0;
+
+            for (j, x) in w.iter().enumerate() {
+                if Float::abs(*x) > Float::abs(w[best]) {
+                    best = j;
+                }
+            }
+
+            if Float::abs(w[best]) > threshold {
+                let previous = self.reduced.row(best).to_owned();
+                self.reduced.row_mut(best).assign(&y);
+
+                match Self::invert(&self.reduced) {
+                    Some(inverse) => {
+                        self.inverse = inverse;
+                        self.indices[best] = self.offset + i;
+                        self.spectra.row_mut(best).assign(&pixels.row(i));
+                        self.replacements += 1;
+                    }
+                    None => self.reduced.row_mut(best).assign(&previous),
+                }
+            }
+        }
+
+        self.offset += pixels.nrows();
+    }
+
+    pub fn into_library(self, pixels_per_line: usize) -> SpectralLibrary<T> where T: Display {
+        SpectralLibrary::from_pixels(&self.indices, self.spectra, pixels_per_line)
+    }
+}
+
+pub(crate) fn stack_rows<'a, T, I>(rows: I) -> Array2<T>
+    where T: Clone + 'a,
+          I: Iterator<Item=&'a Array1<T>>
+{
+    let mut flat = Vec::new();
+    let mut n = 0;
+    let mut len = 0;
+
+    for row in rows {
+        len = row.len();
+        flat.extend(row.iter().cloned());
+        n += 1;
+    }
+
+    Array2::from_shape_vec((n, len), flat).unwrap()
+}
@@ -0,0 +1,567 @@
+use ndarray::{Array1, ArrayBase, Data, Ix2};
+use num_traits::{Float, FromPrimitive};
+
+use crate::error::{VanadiumError, VanadiumResult};
+
+/// A reference to a band, either directly or by the nearest wavelength in the header.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BandRef {
+    Index(usize),
+    /// Wavelength in nanometres.
+    Wavelength(f64),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Function {
+    /// `if(condition, then, else)`, where any non-zero condition is true.
+    If,
+    Min,
+    Max,
+    Abs,
+    /// Natural logarithm.
+    Log,
+    Log10,
+    Exp,
+    Sqrt,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "if" => Function::If,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            "abs" => Function::Abs,
+            "log" => Function::Log,
+            "log10" => Function::Log10,
+            "exp" => Function::Exp,
+            "sqrt" => Function::Sqrt,
+            _ => return None,
+        })
+    }
+
+    fn check_arity(&self, n: usize) -> bool {
+        match self {
+            Function::If => n == 3,
+            Function::Min | Function::Max => n >= 1,
+            _ => n == 1,
+        }
+    }
+}
+
+/// A per-band image statistic, which must be calculated before the expression is evaluated.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Statistic {
+    Mean,
+    StdDev,
+}
+
+/// Everything in the header and cached statistics needed to resolve an expression.
+#[derive(Default)]
+pub struct ExprContext<'a> {
+    pub channels: usize,
+    pub wavelengths: Option<&'a [f64]>,
+    pub means: Option<&'a [f64]>,
+    pub std_devs: Option<&'a [f64]>,
+}
+
+/// Band math expression, evaluated per pixel.
+///
+/// Band and statistic references must be resolved with [`Expr::resolve`] before evaluation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Constant(f64),
+    Band(BandRef),
+    Negate(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+    Stat(Statistic, BandRef),
+}
+
+impl Expr {
+    /// Parses an expression over bands.
+    ///
+    /// Bands are written as `b12` or `b[12]` by index, and as `b[800nm]` or `b[0.8um]` by
+    /// wavelength.
+    /// Image statistics are written as `mean[b3]` or `std[b3]`.
+    /// Comparisons and logical operators yield one or zero.
+    pub fn parse(s: &str) -> VanadiumResult<Self> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens: &tokens, pos: 0 };
+
+        let expr = parser.expr()?;
+
+        if parser.pos != tokens.len() {
+            return Err(invalid(format!("unexpected {:?}", tokens[parser.pos])));
+        }
+
+        Ok(expr)
+    }
+
+    /// Parses an expression of the form `name=expression`.
+    pub fn parse_named(s: &str) -> VanadiumResult<(String, Self)> {
+        let eq = s.find('=')
+            .filter(|i| !s[i + 1..].starts_with('='))
+            .ok_or_else(|| invalid(format!("expected name=expression, got {}", s)))?;
+
+        let name = s[..eq].trim();
+
+        let is_ident = matches!(name.chars().next(), Some(c) if c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+
+        if !is_ident {
+            return Err(invalid(format!("invalid output name '{}'", name)));
+        }
+
+        Ok((name.to_owned(), Self::parse(&s[eq + 1..])?))
+    }
+
+    /// Replaces wavelength references with the index of the nearest band and statistics with
+    /// their values, and checks that every band exists.
+    pub fn resolve(self, ctx: &ExprContext) -> VanadiumResult<Self> {
+        Ok(match self {
+            Expr::Band(band) => Expr::Band(BandRef::Index(resolve_band(band, ctx)?)),
+            Expr::Stat(stat, band) => {
+                let band = resolve_band(band, ctx)?;
+
+                let (values, name) = match stat {
+                    Statistic::Mean => (ctx.means, "means"),
+                    Statistic::StdDev => (ctx.std_devs, "standard deviations"),
+                };
+
+                let values = values.ok_or_else(|| invalid(format!("no {} available", name)))?;
+
+                Expr::Constant(values[band])
+            }
+            Expr::Negate(e) => Expr::Negate(Box::new(e.resolve(ctx)?)),
+            Expr::Binary(op, a, b) => Expr::Binary(
+                op,
+                Box::new(a.resolve(ctx)?),
+                Box::new(b.resolve(ctx)?),
+            ),
+            Expr::Call(f, args) => Expr::Call(
+                f,
+                args.into_iter().map(|a| a.resolve(ctx)).collect::<VanadiumResult<_>>()?,
+            ),
+            e => e,
+        })
+    }
+
+    /// Whether the expression refers to the given statistic anywhere.
+    pub fn uses(&self, stat: Statistic) -> bool {
+        match self {
+            Expr::Stat(s, _) => *s == stat,
+            Expr::Negate(e) => e.uses(stat),
+            Expr::Binary(_, a, b) => a.uses(stat) || b.uses(stat),
+            Expr::Call(_, args) => args.iter().any(|a| a.uses(stat)),
+            _ => false,
+        }
+    }
+
+    /// Evaluates the expression for a batch of pixels, one pixel per row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the expression still holds wavelength references.
+    pub fn eval<T, S>(&self, pixels: &ArrayBase<S, Ix2>) -> Array1<T>
+        where T: Float + FromPrimitive,
+              S: Data<Elem=T>
+    {
+        match self {
+            Expr::Constant(c) => Array1::from_elem(pixels.nrows(), T::from_f64(*c).unwrap()),
+            Expr::Band(BandRef::Index(i)) => pixels.column(*i).to_owned(),
+            Expr::Band(BandRef::Wavelength(_)) => panic!("unresolved wavelength reference"),
+            Expr::Stat(_, _) => panic!("unresolved statistic reference"),
+            Expr::Negate(e) => e.eval(pixels).mapv(|x| -x),
+            Expr::Binary(op, a, b) => {
+                let mut a = a.eval(pixels);
+                let b = b.eval(pixels);
+
+                let f: fn(T, T) -> T = match op {
+                    BinaryOp::Add => T::add,
+                    BinaryOp::Sub => T::sub,
+                    BinaryOp::Mul => T::mul,
+                    BinaryOp::Div => T::div,
+                    BinaryOp::Pow => T::powf,
+                    BinaryOp::Lt => |x, y| truth(x < y),
+                    BinaryOp::Le => |x, y| truth(x <= y),
+                    BinaryOp::Gt => |x, y| truth(x > y),
+                    BinaryOp::Ge => |x, y| truth(x >= y),
+                    BinaryOp::Eq => |x, y| truth(x == y),
+                    BinaryOp::Ne => |x, y| truth(x != y),
+                    BinaryOp::And => |x, y| truth(!x.is_zero() && !y.is_zero()),
+                    BinaryOp::Or => |x, y| truth(!x.is_zero() || !y.is_zero()),
+                };
+
+                a.zip_mut_with(&b, |x, y| *x = f(*x, *y));
+                a
+            }
+            Expr::Call(f, args) => {
+                let mut values = args.iter().map(|a| a.eval(pixels));
+                let mut first = values.next().unwrap();
+
+                match f {
+                    Function::If => {
+                        let a = values.next().unwrap();
+                        let b = values.next().unwrap();
+
+                        ndarray::Zip::from(&mut first).and(&a).and(&b).for_each(|c, a, b| {
+                            *c = if c.is_zero() { *b } else { *a }
+                        });
+                    }
+                    Function::Min => for v in values {
+                        first.zip_mut_with(&v, |x, y| *x = x.min(*y));
+                    },
+                    Function::Max => for v in values {
+                        first.zip_mut_with(&v, |x, y| *x = x.max(*y));
+                    },
+                    Function::Abs => first.mapv_inplace(T::abs),
+                    Function::Log => first.mapv_inplace(T::ln),
+                    Function::Log10 => first.mapv_inplace(T::log10),
+                    Function::Exp => first.mapv_inplace(T::exp),
+                    Function::Sqrt => first.mapv_inplace(T::sqrt),
+                }
+
+                first
+            }
+        }
+    }
+}
+
+fn truth<T>(b: bool) -> T where T: Float {
+    if b { T::one() } else { T::zero() }
+}
+
+fn resolve_band(band: BandRef, ctx: &ExprContext) -> VanadiumResult<usize> {
+    match band {
+        BandRef::Index(i) if i < ctx.channels => Ok(i),
+        BandRef::Index(i) => Err(invalid(format!("band {} out of range", i))),
+        BandRef::Wavelength(w) => {
+            let wavelengths = ctx.wavelengths.ok_or_else(|| {
+                invalid("header has no wavelengths to select bands by".to_owned())
+            })?;
+
+            Ok(nearest_band(wavelengths, w))
+        }
+    }
+}
+
+pub(crate) fn nearest_band(wavelengths: &[f64], target: f64) -> usize {
+    wavelengths.iter()
+        .enumerate()
+        .min_by(|a, b| (a.1 - target).abs().partial_cmp(&(b.1 - target).abs()).unwrap())
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+fn invalid(message: String) -> VanadiumError {
+    VanadiumError::InvalidExpression(message)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Symbol(&'static str),
+}
+
+/// Symbols, with the longer ones first so that they take precedence.
+const SYMBOLS: [&str; 19] = [
+    "<=", ">=", "==", "!=", "&&", "||",
+    "+", "-", "*", "/", "^", "(", ")", "[", "]", ",", "<", ">", "!",
+];
+
+fn tokenize(s: &str) -> VanadiumResult<Vec<Token>> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+
+            // exponent, taking care not to swallow a unit such as "nm"
+            if i + 1 < chars.len() && (chars[i] == 'e' || chars[i] == 'E')
+                && (chars[i + 1].is_ascii_digit() || chars[i + 1] == '-' || chars[i + 1] == '+')
+            {
+                i += 2;
+
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+
+            let text: String = chars[start..i].iter().collect();
+            let value = text.parse().map_err(|_| invalid(format!("bad number {}", text)))?;
+
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            let rest: String = chars[i..].iter().take(2).collect();
+
+            let symbol = SYMBOLS.iter()
+                .find(|sym| rest.starts_with(*sym))
+                .ok_or_else(|| invalid(format!("unexpected character '{}'", c)))?;
+
+            tokens.push(Token::Symbol(symbol));
+            i += symbol.len();
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: &str) -> VanadiumResult<()> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(invalid(format!("expected '{}'", symbol)))
+        }
+    }
+
+    fn expr(&mut self) -> VanadiumResult<Expr> {
+        let mut lhs = self.and()?;
+
+        while self.eat("||") {
+            lhs = Expr::Binary(BinaryOp::Or, Box::new(lhs), Box::new(self.and()?));
+        }
+
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> VanadiumResult<Expr> {
+        let mut lhs = self.comparison()?;
+
+        while self.eat("&&") {
+            lhs = Expr::Binary(BinaryOp::And, Box::new(lhs), Box::new(self.comparison()?));
+        }
+
+        Ok(lhs)
+    }
+
+    fn comparison(&mut self) -> VanadiumResult<Expr> {
+        let lhs = self.additive()?;
+
+        let ops = [
+            ("<=", BinaryOp::Le),
+            (">=", BinaryOp::Ge),
+            ("==", BinaryOp::Eq),
+            ("!=", BinaryOp::Ne),
+            ("<", BinaryOp::Lt),
+            (">", BinaryOp::Gt),
+        ];
+
+        for (symbol, op) in ops.iter() {
+            if self.eat(symbol) {
+                return Ok(Expr::Binary(*op, Box::new(lhs), Box::new(self.additive()?)));
+            }
+        }
+
+        Ok(lhs)
+    }
+
+    fn additive(&mut self) -> VanadiumResult<Expr> {
+        let mut lhs = self.term()?;
+
+        loop {
+            let op = if self.eat("+") {
+                BinaryOp::Add
+            } else if self.eat("-") {
+                BinaryOp::Sub
+            } else {
+                return Ok(lhs);
+            };
+
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> VanadiumResult<Expr> {
+        let mut lhs = self.unary()?;
+
+        loop {
+            let op = if self.eat("*") {
+                BinaryOp::Mul
+            } else if self.eat("/") {
+                BinaryOp::Div
+            } else {
+                return Ok(lhs);
+            };
+
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> VanadiumResult<Expr> {
+        if self.eat("-") {
+            Ok(Expr::Negate(Box::new(self.unary()?)))
+        } else if self.eat("!") {
+            let operand = self.unary()?;
+            Ok(Expr::Binary(BinaryOp::Eq, Box::new(operand), Box::new(Expr::Constant(0.0))))
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> VanadiumResult<Expr> {
+        let base = self.atom()?;
+
+        if self.eat("^") {
+            // right associative, and binds tighter than a leading minus on the exponent
+            Ok(Expr::Binary(BinaryOp::Pow, Box::new(base), Box::new(self.unary()?)))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom(&mut self) -> VanadiumResult<Expr> {
+        match self.peek().cloned() {
+            Some(Token::Number(x)) => {
+                self.pos += 1;
+                Ok(Expr::Constant(x))
+            }
+            Some(Token::Symbol("(")) => {
+                self.pos += 1;
+                let e = self.expr()?;
+                self.expect(")")?;
+                Ok(e)
+            }
+            Some(Token::Ident(name)) => {
+                self.pos += 1;
+                self.ident(&name)
+            }
+            Some(t) => Err(invalid(format!("unexpected {:?}", t))),
+            None => Err(invalid("unexpected end of expression".to_owned())),
+        }
+    }
+
+    fn ident(&mut self, name: &str) -> VanadiumResult<Expr> {
+        if name == "b" {
+            self.expect("[")?;
+            let band = self.band_ref()?;
+            self.expect("]")?;
+
+            Ok(Expr::Band(band))
+        } else if let Some(index) = name.strip_prefix('b').and_then(|i| i.parse().ok()) {
+            Ok(Expr::Band(BandRef::Index(index)))
+        } else if name == "mean" || name == "std" {
+            let stat = if name == "mean" { Statistic::Mean } else { Statistic::StdDev };
+
+            self.expect("[")?;
+
+            let band = match self.peek().cloned() {
+                Some(Token::Ident(b)) => {
+                    self.pos += 1;
+
+                    match self.ident(&b)? {
+                        Expr::Band(band) => band,
+                        _ => return Err(invalid(format!("expected a band in {}[]", name))),
+                    }
+                }
+                _ => self.band_ref()?,
+            };
+
+            self.expect("]")?;
+
+            Ok(Expr::Stat(stat, band))
+        } else if let Some(f) = Function::from_name(name) {
+            self.expect("(")?;
+
+            let mut args = vec![self.expr()?];
+
+            while self.eat(",") {
+                args.push(self.expr()?);
+            }
+
+            self.expect(")")?;
+
+            if !f.check_arity(args.len()) {
+                return Err(invalid(format!("wrong number of arguments to {}", name)));
+            }
+
+            Ok(Expr::Call(f, args))
+        } else {
+            Err(invalid(format!("unknown name {}", name)))
+        }
+    }
+
+    fn band_ref(&mut self) -> VanadiumResult<BandRef> {
+        let value = match self.peek() {
+            Some(Token::Number(x)) => *x,
+            _ => return Err(invalid("expected a band number or wavelength".to_owned())),
+        };
+
+        self.pos += 1;
+
+        let scale = match self.peek() {
+            Some(Token::Ident(unit)) => {
+                let scale = match unit.as_str() {
+                    "nm" => 1.0,
+                    "um" => 1000.0,
+                    _ => return Err(invalid(format!("unknown wavelength unit {}", unit))),
+                };
+
+                self.pos += 1;
+                Some(scale)
+            }
+            _ => None,
+        };
+
+        match scale {
+            Some(scale) => Ok(BandRef::Wavelength(value * scale)),
+            None if value.fract() == 0.0 && value >= 0.0 => Ok(BandRef::Index(value as usize)),
+            None => Err(invalid(format!("band index {} is not a whole number", value))),
+        }
+    }
+}
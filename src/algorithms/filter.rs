@@ -0,0 +1,70 @@
+use ndarray::Array2;
+
+use crate::algorithms::unmixing::solve;
+use crate::error::{VanadiumError, VanadiumResult};
+
+/// Builds the matrix of a Savitzky-Golay filter along the channel axis.
+///
+/// Each output band is the `derivative`-th derivative, at that band, of a polynomial of degree
+/// `order` fitted by least squares to the `window` nearest bands.
+/// Near the ends of the spectrum the window is shifted inwards rather than padded, so every fit
+/// uses real data.
+/// `positions` gives the location of each band, such as its wavelength, which need not be evenly
+/// spaced; derivatives are per unit of position.
+pub fn savitzky_golay(
+    positions: &[f64],
+    window: usize,
+    order: usize,
+    derivative: usize,
+) -> VanadiumResult<Array2<f64>> {
+    let n = positions.len();
+
+    if window > n || window <= order || derivative > order {
+        return Err(VanadiumError::InvalidArgs(
+            "Window must fit the spectrum and exceed the polynomial order, which must be at \
+            least the derivative order".to_owned()
+        ));
+    }
+
+    let factorial: f64 = (1..=derivative).map(|x| x as f64).product();
+
+    let mut matrix = Array2::zeros((n, n));
+
+    for i in 0..n {
+        let start = i.saturating_sub(window / 2).min(n - window);
+        let bands = start..(start + window);
+
+        // fit in a scaled coordinate centered on the band, to keep the system well conditioned
+        let scale = bands.clone()
+            .map(|j| (positions[j] - positions[i]).abs())
+            .fold(0.0, f64::max)
+            .max(f64::MIN_POSITIVE);
+
+        let design: Vec<Vec<f64>> = bands.clone()
+            .map(|j| {
+                let t = (positions[j] - positions[i]) / scale;
+                (0..=order).map(|k| t.powi(k as i32)).collect()
+            })
+            .collect();
+
+        let gram = (0..=order)
+            .map(|a| (0..=order).map(|b| design.iter().map(|r| r[a] * r[b]).sum()).collect())
+            .collect();
+
+        let mut unit = vec![0.0; order + 1];
+        unit[derivative] = 1.0;
+
+        // row i picks out coefficient d of the fit: e_d^T (A^T A)^-1 A^T
+        let z = solve(gram, unit).ok_or_else(|| {
+            VanadiumError::InvalidArgs("Band positions within a window must be distinct".to_owned())
+        })?;
+
+        let weight = factorial / scale.powi(derivative as i32);
+
+        for (j, row) in bands.zip(design.iter()) {
+            matrix[[i, j]] = weight * row.iter().zip(&z).map(|(a, b)| a * b).sum::<f64>();
+        }
+    }
+
+    Ok(matrix)
+}
@@ -0,0 +1,267 @@
+use ndarray::{ArrayBase, Data, Ix2};
+use num_traits::Float;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+/// A fixed number of equal width bins, whose range grows to cover every value added.
+///
+/// The range starts at the spread of the first distinct values, and doubles whenever a value falls
+/// outside it by merging neighbouring bins, so a single pass needs no prior knowledge of the data.
+/// The final range covers the data, but may reach up to twice as far as its extremes.
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct Histogram {
+    /// Lower edge of the first bin.
+    pub start: f64,
+    /// Width of every bin, zero until two distinct values have been added.
+    pub bin_width: f64,
+    pub counts: Vec<u64>,
+}
+
+impl Histogram {
+    pub fn new(bins: usize) -> Self {
+        assert!(bins > 0);
+
+        Self {
+            start: f64::NAN,
+            bin_width: 0.0,
+            counts: vec![0; bins],
+        }
+    }
+
+    /// Upper edge of the last bin, which is included in it.
+    pub fn end(&self) -> f64 {
+        self.start + self.bin_width * self.counts.len() as f64
+    }
+
+    pub fn add(&mut self, x: f64) {
+        if self.start.is_nan() {
+            self.start = x;
+        }
+
+        if self.bin_width == 0.0 && x != self.start {
+            // Every value so far equals start, and sits in the first bin
+            let total = self.counts[0];
+            let bins = self.counts.len() as f64;
+
+            self.counts[0] = 0;
+            self.bin_width = (x - self.start).abs() / bins;
+
+            if x < self.start {
+                let previous = self.start;
+                self.start = x;
+                let i = self.index(previous);
+                self.counts[i] = total;
+            } else {
+                self.counts[0] = total;
+            }
+        }
+
+        // Leaves room for rounding in the edges, so values on them never double the range
+        let tolerance = self.bin_width * 1e-9;
+
+        while x < self.start - tolerance {
+            self.grow(true);
+        }
+
+        while x > self.end() + tolerance {
+            self.grow(false);
+        }
+
+        let i = self.index(x);
+        self.counts[i] += 1;
+    }
+
+    fn index(&self, x: f64) -> usize {
+        if self.bin_width == 0.0 {
+            return 0;
+        }
+
+        (((x - self.start) / self.bin_width) as usize).min(self.counts.len() - 1)
+    }
+
+    /// Doubles the bin width, keeping either the upper or the lower edge in place.
+    fn grow(&mut self, downwards: bool) {
+        let bins = self.counts.len();
+        let shift = if downwards { bins } else { 0 };
+
+        let mut counts = vec![0; bins];
+
+        for (i, count) in self.counts.iter().enumerate() {
+            counts[(i + shift) / 2] += count;
+        }
+
+        if downwards {
+            self.start -= self.bin_width * bins as f64;
+        }
+
+        self.bin_width *= 2.0;
+        self.counts = counts;
+    }
+}
+
+/// A KLL streaming quantile sketch.
+///
+/// Values are kept in a stack of compactors, where each item at level `h` stands for `2^h` of the
+/// values added. When a level fills, it is sorted and every other item, starting from a random
+/// offset, is promoted to the level above. Lower levels get geometrically smaller capacities, so
+/// memory stays around `3k` items while the rank error shrinks as `k` grows.
+#[derive(Clone, Debug)]
+pub struct KllSketch {
+    k: usize,
+    levels: Vec<Vec<f64>>,
+    rng: StdRng,
+}
+
+impl KllSketch {
+    pub fn new(k: usize, seed: u64) -> Self {
+        Self {
+            k: k.max(2),
+            levels: vec![Vec::new()],
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Number of values added.
+    pub fn count(&self) -> u64 {
+        self.levels.iter().enumerate().map(|(h, level)| (level.len() as u64) << h).sum()
+    }
+
+    pub fn add(&mut self, x: f64) {
+        self.levels[0].push(x);
+
+        if self.levels[0].len() >= self.capacity(0) {
+            self.compress();
+        }
+    }
+
+    fn capacity(&self, level: usize) -> usize {
+        let depth = (self.levels.len() - 1 - level) as i32;
+
+        ((self.k as f64 * (2.0 / 3.0).powi(depth)) as usize).max(2)
+    }
+
+    fn compress(&mut self) {
+        for h in 0..self.levels.len() {
+            if self.levels[h].len() < self.capacity(h) {
+                continue;
+            }
+
+            if h + 1 == self.levels.len() {
+                self.levels.push(Vec::new());
+            }
+
+            let mut level = std::mem::take(&mut self.levels[h]);
+            level.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+            // An odd item out stays behind, so the promoted items keep their total weight
+            if level.len() % 2 == 1 {
+                self.levels[h].push(level.pop().unwrap());
+            }
+
+            let offset = self.rng.gen_range(0..2);
+            self.levels[h + 1].extend(level.into_iter().skip(offset).step_by(2));
+        }
+    }
+
+    /// Approximate value at quantile `q`, or `None` if nothing has been added.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let mut items: Vec<(f64, u64)> = self.levels.iter()
+            .enumerate()
+            .flat_map(|(h, level)| level.iter().map(move |x| (*x, 1u64 << h)))
+            .collect();
+
+        items.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        let target = q.clamp(0.0, 1.0) * self.count() as f64;
+        let mut rank = 0;
+
+        for (x, weight) in &items {
+            rank += weight;
+
+            if rank as f64 >= target {
+                return Some(*x);
+            }
+        }
+
+        items.last().map(|(x, _)| *x)
+    }
+}
+
+/// Extremes, histogram and quantile sketch of the values of one band.
+///
+/// NaN and infinite values are skipped.
+#[derive(Clone, Debug)]
+pub struct BandDistribution {
+    pub min: f64,
+    pub max: f64,
+    pub histogram: Histogram,
+    pub sketch: KllSketch,
+}
+
+impl BandDistribution {
+    pub fn new(bins: usize, sketch_size: usize, seed: u64) -> Self {
+        Self {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            histogram: Histogram::new(bins),
+            sketch: KllSketch::new(sketch_size, seed),
+        }
+    }
+
+    pub fn add(&mut self, x: f64) {
+        if !x.is_finite() {
+            return;
+        }
+
+        self.min = self.min.min(x);
+        self.max = self.max.max(x);
+        self.histogram.add(x);
+        self.sketch.add(x);
+    }
+
+    pub fn summary(&self, quantiles: &[f64]) -> BandSummary {
+        let count = self.sketch.count();
+
+        BandSummary {
+            count,
+            min: if count > 0 { Some(self.min) } else { None },
+            max: if count > 0 { Some(self.max) } else { None },
+            histogram: self.histogram.clone(),
+            quantiles: quantiles.iter().map(|q| self.sketch.quantile(*q)).collect(),
+        }
+    }
+}
+
+/// Folds a batch of pixels into one distribution per band.
+pub fn accumulate_distributions<T, S>(pixels: &ArrayBase<S, Ix2>, acc: &mut [BandDistribution])
+    where T: Float,
+          S: Data<Elem=T>
+{
+    for (band, distribution) in pixels.columns().into_iter().zip(acc.iter_mut()) {
+        for x in band.iter() {
+            distribution.add(x.to_f64().unwrap());
+        }
+    }
+}
+
+/// Serializable statistics of one band.
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct BandSummary {
+    /// Number of values, excluding NaN and infinite values.
+    pub count: u64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub histogram: Histogram,
+    /// Approximate values at each of the requested quantiles.
+    pub quantiles: Vec<Option<f64>>,
+}
+
+/// Serializable statistics of every band, along with the quantiles estimated for each.
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct BandStatistics {
+    pub quantiles: Vec<f64>,
+    pub bands: Vec<BandSummary>,
+}
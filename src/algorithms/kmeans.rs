@@ -0,0 +1,294 @@
+use ndarray::{Array1, Array2, ArrayBase, Axis, Data, Ix2};
+use num_traits::{Float, FromPrimitive};
+use rand::Rng;
+
+use crate::algorithms::pca::Projection;
+
+/// Parameters of a k-means clustering run.
+pub struct KMeansParams<T> {
+    /// Number of clusters to seed.
+    pub clusters: usize,
+    pub max_iterations: usize,
+    /// Iteration stops once no centroid moves further than this.
+    pub tolerance: T,
+    /// Number of pixels sampled for k-means++ seeding.
+    pub sample_size: usize,
+    pub seed: u64,
+    /// If present, pixels are assigned to clusters in this reduced space.
+    pub projection: Option<Projection<T>>,
+    pub isodata: IsodataParams<T>,
+}
+
+/// ISODATA split/merge rules, applied between k-means iterations.
+///
+/// Each rule is disabled when its threshold is absent.
+pub struct IsodataParams<T> {
+    /// Clusters with fewer pixels than this are discarded.
+    pub min_cluster_size: Option<usize>,
+    /// Clusters with a standard deviation above this along any channel are split in two.
+    pub split_std_dev: Option<T>,
+    /// Pairs of clusters whose centroids are closer than this are merged.
+    pub merge_distance: Option<T>,
+    /// Discarding and merging stop at this many clusters, and never leave fewer than one.
+    pub min_clusters: usize,
+    pub max_clusters: usize,
+}
+
+impl<T> IsodataParams<T> {
+    pub fn is_enabled(&self) -> bool {
+        self.min_cluster_size.is_some() || self.split_std_dev.is_some()
+            || self.merge_distance.is_some()
+    }
+}
+
+/// Result of a clustering run.
+#[derive(Serialize, Deserialize)]
+pub struct Clustering<T> {
+    /// Cluster centroids, one per row, in the original channel space.
+    pub centroids: Array2<T>,
+    /// Number of pixels assigned to each cluster on the final iteration.
+    pub counts: Vec<usize>,
+    pub iterations: usize,
+}
+
+/// Per-cluster sums, used to compute centroids and spreads in a single pass.
+pub struct ClusterAccumulator<T> {
+    pub sums: Array2<T>,
+    pub squares: Array2<T>,
+    pub counts: Vec<usize>,
+}
+
+impl<T> ClusterAccumulator<T> where T: Float + FromPrimitive + 'static {
+    pub fn new(clusters: usize, channels: usize) -> Self {
+        Self {
+            sums: Array2::zeros((clusters, channels)),
+            squares: Array2::zeros((clusters, channels)),
+            counts: vec![0; clusters],
+        }
+    }
+
+    pub fn add<S>(&mut self, pixels: &ArrayBase<S, Ix2>, labels: &[usize]) where S: Data<Elem=T> {
+        for (pixel, &label) in pixels.outer_iter().zip(labels) {
+            let mut sum = self.sums.row_mut(label);
+            sum.zip_mut_with(&pixel, |s, x| *s = *s + *x);
+
+            let mut square = self.squares.row_mut(label);
+            square.zip_mut_with(&pixel, |s, x| *s = *s + *x * *x);
+
+            self.counts[label] += 1;
+        }
+    }
+
+    /// New centroids; empty clusters keep their previous position.
+    pub fn centroids(&self, previous: &Array2<T>) -> Array2<T> {
+        let mut centroids = previous.clone();
+
+        for (i, mut row) in centroids.outer_iter_mut().enumerate() {
+            if self.counts[i] > 0 {
+                let n = T::from_usize(self.counts[i]).unwrap();
+                row.assign(&self.sums.row(i).mapv(|x| x / n));
+            }
+        }
+
+        centroids
+    }
+
+    /// Per-channel standard deviations of each cluster about its mean.
+    pub fn std_devs(&self) -> Array2<T> {
+        let mut std_devs = Array2::zeros(self.sums.raw_dim());
+
+        for (i, mut row) in std_devs.outer_iter_mut().enumerate() {
+            if self.counts[i] > 0 {
+                let n = T::from_usize(self.counts[i]).unwrap();
+
+                row.zip_mut_with(&self.squares.row(i), |s, sq| *s = *sq / n);
+                row.zip_mut_with(&self.sums.row(i), |s, x| {
+                    let mean = *x / n;
+                    *s = (*s - mean * mean).max(T::zero()).sqrt()
+                });
+            }
+        }
+
+        std_devs
+    }
+}
+
+/// Index of the nearest centroid for each pixel, by squared euclidean distance.
+pub fn nearest_clusters<S, T>(pixels: &ArrayBase<S, Ix2>, centroids: &Array2<T>) -> Vec<usize>
+    where S: Data<Elem=T>,
+          T: Float + 'static
+{
+    let centroid_norms = centroids.map_axis(Axis(1), |c| c.dot(&c));
+
+    // |p - c|^2 = |p|^2 - 2 p.c + |c|^2, and |p|^2 does not change the ordering
+    let cross = pixels.dot(&centroids.t());
+
+    cross.outer_iter()
+        .map(|row| {
+            let mut best = 0;
+            let mut best_dist = T::infinity();
+
+            for (j, (x, n)) in row.iter().zip(centroid_norms.iter()).enumerate() {
+                let dist = *n - (*x + *x);
+
+                if dist < best_dist {
+                    best = j;
+                    best_dist = dist;
+                }
+            }
+
+            best
+        })
+        .collect()
+}
+
+/// Chooses `k` seed rows from `sample` using k-means++.
+///
+/// Each seed after the first is drawn with probability proportional to its squared distance from
+/// the nearest seed chosen so far.
+pub fn kmeans_plus_plus<T, R>(sample: &Array2<T>, k: usize, rng: &mut R) -> Vec<usize>
+    where T: Float + 'static,
+          R: Rng
+{
+    let n = sample.nrows();
+    let mut seeds = Vec::with_capacity(k);
+
+    if n == 0 {
+        return seeds;
+    }
+
+    seeds.push(rng.gen_range(0..n));
+
+    let mut dists: Vec<f64> = vec![f64::INFINITY; n];
+
+    while seeds.len() < k.min(n) {
+        let last = sample.row(*seeds.last().unwrap());
+
+        for (d, row) in dists.iter_mut().zip(sample.outer_iter()) {
+            let diff = &row - &last;
+            *d = d.min(diff.dot(&diff).to_f64().unwrap());
+        }
+
+        let total: f64 = dists.iter().sum();
+
+        let next = if total > 0.0 {
+            let mut target = rng.gen::<f64>() * total;
+
+            dists.iter()
+                .position(|d| {
+                    target -= d;
+                    target <= 0.0
+                })
+                .unwrap_or(n - 1)
+        } else {
+            // every remaining sample coincides with a seed
+            rng.gen_range(0..n)
+        };
+
+        seeds.push(next);
+    }
+
+    seeds
+}
+
+/// Applies the ISODATA discard, split and merge rules to the centroids of an iteration.
+///
+/// Returns the new centroids, or `None` if no rule changed anything.
+pub fn isodata_step<T>(
+    centroids: &Array2<T>,
+    acc: &ClusterAccumulator<T>,
+    params: &IsodataParams<T>,
+) -> Option<Array2<T>>
+    where T: Float + FromPrimitive + 'static
+{
+    let std_devs = acc.std_devs();
+
+    let mut clusters: Vec<(Array1<T>, usize, Array1<T>)> = centroids.outer_iter()
+        .zip(acc.counts.iter())
+        .zip(std_devs.outer_iter())
+        .map(|((c, n), s)| (c.to_owned(), *n, s.to_owned()))
+        .collect();
+
+    let mut changed = false;
+
+    if let Some(min_size) = params.min_cluster_size {
+        let mut i = 0;
+
+        while i < clusters.len() && clusters.len() > params.min_clusters.max(1) {
+            if clusters[i].1 < min_size {
+                clusters.remove(i);
+                changed = true;
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    if let Some(max_std) = params.split_std_dev {
+        for i in 0..clusters.len() {
+            if clusters.len() >= params.max_clusters {
+                break;
+            }
+
+            let (channel, std_dev) = clusters[i].2.iter()
+                .cloned()
+                .enumerate()
+                .fold((0, T::zero()), |best, (j, s)| if s > best.1 { (j, s) } else { best });
+
+            if std_dev > max_std && clusters[i].1 >= 2 {
+                let half = clusters[i].1 / 2;
+                let mut other = clusters[i].clone();
+
+                clusters[i].0[channel] = clusters[i].0[channel] + std_dev;
+                other.0[channel] = other.0[channel] - std_dev;
+
+                clusters[i].1 -= half;
+                other.1 = half;
+
+                clusters.push(other);
+                changed = true;
+            }
+        }
+    }
+
+    if let Some(min_dist) = params.merge_distance {
+        let min_dist = min_dist * min_dist;
+
+        let mut i = 0;
+
+        while i < clusters.len() && clusters.len() > params.min_clusters.max(1) {
+            let closest = ((i + 1)..clusters.len())
+                .map(|j| {
+                    let diff = &clusters[i].0 - &clusters[j].0;
+                    (j, diff.dot(&diff))
+                })
+                .filter(|(_, d)| *d < min_dist)
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+            if let Some((j, _)) = closest {
+                let (c_j, n_j, _) = clusters.remove(j);
+                let (c_i, n_i, _) = &mut clusters[i];
+
+                let total = (*n_i + n_j).max(1);
+                let w_i = T::from_usize(*n_i).unwrap() / T::from_usize(total).unwrap();
+                let w_j = T::from_usize(n_j).unwrap() / T::from_usize(total).unwrap();
+
+                *c_i = c_i.mapv(|x| x * w_i) + c_j.mapv(|x| x * w_j);
+                *n_i = total;
+
+                changed = true;
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    if !changed {
+        return None;
+    }
+
+    let channels = centroids.ncols();
+    let flat: Vec<T> = clusters.into_iter().flat_map(|(c, _, _)| c.to_vec()).collect();
+
+    Some(Array2::from_shape_vec((flat.len() / channels, channels), flat).unwrap())
+}
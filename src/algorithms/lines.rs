@@ -0,0 +1,165 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::mem;
+use std::path::Path;
+
+use ndarray::{Array2, ArrayBase, Data, Ix2};
+
+use crate::headers::ImageFormat;
+use crate::util::make_raw;
+
+/// Reassembles batches of pixels, which need not line up with lines, into whole lines.
+pub struct LineAssembler<T> {
+    pixels: usize,
+    channels: usize,
+    partial: Vec<T>,
+}
+
+impl<T> LineAssembler<T> where T: Copy {
+    pub fn new(pixels: usize, channels: usize) -> Self {
+        Self {
+            pixels,
+            channels,
+            partial: Vec::with_capacity(pixels * channels),
+        }
+    }
+
+    /// Adds a batch of pixels, calling `f` with each line it completes.
+    pub fn add<S, F>(&mut self, pixels: &ArrayBase<S, Ix2>, mut f: F)
+        where S: Data<Elem=T>,
+              F: FnMut(Array2<T>)
+    {
+        let line_length = self.pixels * self.channels;
+
+        for pixel in pixels.outer_iter() {
+            self.partial.extend(pixel.iter());
+
+            if self.partial.len() == line_length {
+                let line = mem::replace(&mut self.partial, Vec::with_capacity(line_length));
+                f(Array2::from_shape_vec((self.pixels, self.channels), line).unwrap());
+            }
+        }
+    }
+}
+
+/// Destination for an image written one line at a time, with one row per pixel.
+pub trait LineSink<T> {
+    fn write_line(&mut self, line: &Array2<T>) -> io::Result<()>;
+    fn flush(&mut self) -> io::Result<()>;
+}
+
+/// Writes lines in band interleaved by pixel order.
+pub struct BipWriter<W> {
+    writer: W,
+}
+
+impl<W> BipWriter<W> where W: Write {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    #[cfg(test)]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<T, W> LineSink<T> for BipWriter<W> where T: Clone, W: Write {
+    fn write_line(&mut self, line: &Array2<T>) -> io::Result<()> {
+        let line = line.as_standard_layout();
+        let raw = unsafe { make_raw(line.as_slice().unwrap()) };
+
+        self.writer.write_all(raw)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// A line sink for either output format.
+pub enum LineWriter<T> {
+    Bip(BipWriter<BufWriter<File>>),
+    Bsq(BsqWriter<T>),
+}
+
+impl<T> LineWriter<T> {
+    /// `lines` and `pixels` are the dimensions of the output image.
+    pub fn create(
+        format: ImageFormat,
+        path: &dyn AsRef<Path>,
+        lines: usize,
+        pixels: usize,
+    ) -> io::Result<Self> {
+        let file = File::create(path)?;
+
+        Ok(match format {
+            ImageFormat::Bip => LineWriter::Bip(BipWriter::new(BufWriter::new(file))),
+            ImageFormat::Bsq => LineWriter::Bsq(BsqWriter::new(file, lines, pixels)),
+        })
+    }
+}
+
+impl<T> LineSink<T> for LineWriter<T> where T: Copy {
+    fn write_line(&mut self, line: &Array2<T>) -> io::Result<()> {
+        match self {
+            LineWriter::Bip(w) => w.write_line(line),
+            LineWriter::Bsq(w) => w.write_line(line),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            LineWriter::Bip(w) => LineSink::<T>::flush(w),
+            LineWriter::Bsq(w) => w.flush(),
+        }
+    }
+}
+
+/// Writes lines in band sequential order, placing each band of a line in its own plane.
+pub struct BsqWriter<T> {
+    file: File,
+    lines: usize,
+    pixels: usize,
+    line: usize,
+    band: Vec<T>,
+}
+
+impl<T> BsqWriter<T> {
+    /// `lines` and `pixels` are the dimensions of the output image.
+    pub fn new(file: File, lines: usize, pixels: usize) -> Self {
+        Self {
+            file,
+            lines,
+            pixels,
+            line: 0,
+            band: Vec::with_capacity(pixels),
+        }
+    }
+}
+
+impl<T> LineSink<T> for BsqWriter<T> where T: Copy {
+    fn write_line(&mut self, line: &Array2<T>) -> io::Result<()> {
+        let size = mem::size_of::<T>();
+
+        for (c, band) in line.columns().into_iter().enumerate() {
+            self.band.clear();
+            self.band.extend(band.iter());
+
+            let offset = ((c * self.lines + self.line) * self.pixels * size) as u64;
+            let raw = unsafe { make_raw(&self.band) };
+
+            self.file.seek(SeekFrom::Start(offset))?;
+            self.file.write_all(raw)?;
+        }
+
+        self.line += 1;
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // every write has already gone straight to the file
+        Ok(())
+    }
+}
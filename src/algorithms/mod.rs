@@ -0,0 +1,17 @@
+pub mod continuum;
+pub mod correction;
+pub mod correlation;
+pub mod endmembers;
+pub mod expr;
+pub mod filter;
+pub mod histogram;
+pub mod kmeans;
+pub mod lines;
+pub mod pca;
+pub mod resample;
+pub mod resize;
+pub mod roi;
+pub mod sample;
+pub mod spatial;
+pub mod synthetic;
+pub mod unmixing;
@@ -0,0 +1,72 @@
+use ndarray::{Array1, Array2, ArrayBase, Axis, Data, Ix2};
+use ndarray_linalg::{Eigh, Lapack, Scalar, UPLO};
+use num_traits::Float;
+
+use crate::error::{VanadiumError, VanadiumResult};
+
+/// An affine projection of pixels into a reduced space.
+///
+/// Pixels are centered on `means` before being multiplied by `transform`, which has one row per
+/// output dimension and one column per channel.
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct Projection<T> {
+    pub means: Array1<T>,
+    pub transform: Array2<T>,
+}
+
+impl<T> Projection<T>
+    where T: Float + Lapack + Scalar<Real=T> + 'static
+{
+    /// Principal component projection onto the `n_dims` components of largest variance.
+    ///
+    /// `cov_mat` must be the covariance matrix of the image, calculated about `means`.
+    pub fn pca(means: Array1<T>, cov_mat: &Array2<T>, n_dims: usize) -> VanadiumResult<Self> {
+        let (e_val, e_vec) = cov_mat.eigh(UPLO::Upper).map_err(|_| VanadiumError::Unknown)?;
+
+        Ok(Self {
+            means,
+            transform: Self::leading_components(&e_val, &e_vec, n_dims),
+        })
+    }
+
+    /// Minimum noise fraction projection onto the `n_dims` components of highest signal to noise.
+    ///
+    /// `noise_cov` is an estimate of the noise covariance, such as the one given by shift
+    /// differences.
+    /// Components are scaled so that their noise has unit variance.
+    pub fn mnf(
+        means: Array1<T>,
+        cov_mat: &Array2<T>,
+        noise_cov: &Array2<T>,
+        n_dims: usize,
+    ) -> VanadiumResult<Self> {
+        let (e_val, (e_vec, _)) = (cov_mat.view(), noise_cov.view()).eigh(UPLO::Upper)
+            .map_err(|_| VanadiumError::Unknown)?;
+
+        Ok(Self {
+            means,
+            transform: Self::leading_components(&e_val, &e_vec, n_dims),
+        })
+    }
+
+    /// Takes the `n_dims` eigenvectors with the largest eigenvalues as the rows of a transform.
+    pub(crate) fn leading_components(
+        e_val: &Array1<T>,
+        e_vec: &Array2<T>,
+        n_dims: usize,
+    ) -> Array2<T> {
+        let mut order: Vec<usize> = (0..e_val.len()).collect();
+        order.sort_by(|a, b| e_val[*b].partial_cmp(&e_val[*a]).unwrap());
+        order.truncate(n_dims);
+
+        e_vec.select(Axis(1), &order).reversed_axes()
+    }
+}
+
+impl<T> Projection<T> where T: Float + 'static {
+    /// Projects a batch of pixels, one pixel per row.
+    pub fn project<S>(&self, pixels: &ArrayBase<S, Ix2>) -> Array2<T> where S: Data<Elem=T> {
+        (pixels - &self.means).dot(&self.transform.t())
+    }
+}
@@ -0,0 +1,110 @@
+use std::str::FromStr;
+
+use ndarray::Array2;
+
+use crate::error::{VanadiumError, VanadiumResult};
+
+#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum ResampleMethod {
+    /// Gaussian spectral response functions, with widths given by the target FWHM.
+    Gaussian,
+    /// Linear interpolation between the two nearest source bands.
+    Linear,
+}
+
+impl FromStr for ResampleMethod {
+    type Err = VanadiumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gaussian" => Ok(ResampleMethod::Gaussian),
+            "linear" => Ok(ResampleMethod::Linear),
+            _ => Err(VanadiumError::InvalidArgs("Invalid resampling method".to_owned()))
+        }
+    }
+}
+
+/// Builds the matrix mapping spectra sampled at `source` onto bands centered at `target`.
+///
+/// The matrix has one row per target band and one column per source band, so it can be applied
+/// with `write_transformed`.
+/// All wavelengths and widths must be in the same units.
+pub fn resampling_matrix(
+    method: ResampleMethod,
+    source: &[f64],
+    target: &[f64],
+    target_fwhm: Option<&[f64]>,
+) -> VanadiumResult<Array2<f64>> {
+    match method {
+        ResampleMethod::Gaussian => {
+            let fwhm = target_fwhm.ok_or_else(|| {
+                VanadiumError::InvalidArgs("Gaussian resampling requires target FWHM".to_owned())
+            })?;
+
+            gaussian_matrix(source, target, fwhm)
+        }
+        ResampleMethod::Linear => linear_matrix(source, target),
+    }
+}
+
+fn gaussian_matrix(source: &[f64], target: &[f64], fwhm: &[f64]) -> VanadiumResult<Array2<f64>> {
+    let mut matrix = Array2::zeros((target.len(), source.len()));
+
+    // fwhm = 2 sqrt(2 ln 2) sigma
+    let scale = 2.0 * (2.0 * 2f64.ln()).sqrt();
+
+    for (i, (center, width)) in target.iter().zip(fwhm).enumerate() {
+        let sigma = width / scale;
+
+        let mut row = matrix.row_mut(i);
+
+        for (w, x) in row.iter_mut().zip(source) {
+            let z = (x - center) / sigma;
+            *w = (-0.5 * z * z).exp();
+        }
+
+        let total = row.sum();
+
+        if total.is_nan() || total < f64::MIN_POSITIVE {
+            return Err(outside(*center));
+        }
+
+        row.mapv_inplace(|w| w / total);
+    }
+
+    Ok(matrix)
+}
+
+fn linear_matrix(source: &[f64], target: &[f64]) -> VanadiumResult<Array2<f64>> {
+    let mut matrix = Array2::zeros((target.len(), source.len()));
+
+    let mut order: Vec<usize> = (0..source.len()).collect();
+    order.sort_by(|a, b| source[*a].partial_cmp(&source[*b]).unwrap());
+
+    for (i, center) in target.iter().enumerate() {
+        let upper = order.iter().position(|j| source[*j] >= *center).ok_or_else(|| outside(*center))?;
+
+        let hi = order[upper];
+
+        if source[hi] == *center {
+            matrix[[i, hi]] = 1.0;
+            continue;
+        }
+
+        let lo = match upper.checked_sub(1) {
+            Some(lower) => order[lower],
+            None => return Err(outside(*center)),
+        };
+
+        let t = (center - source[lo]) / (source[hi] - source[lo]);
+
+        matrix[[i, lo]] = 1.0 - t;
+        matrix[[i, hi]] = t;
+    }
+
+    Ok(matrix)
+}
+
+fn outside(center: f64) -> VanadiumError {
+    VanadiumError::InvalidArgs(format!("Target band at {} lies outside the source bands", center))
+}
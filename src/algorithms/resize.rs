@@ -0,0 +1,245 @@
+use std::collections::VecDeque;
+use std::io;
+use std::str::FromStr;
+
+use ndarray::{Array2, ArrayBase, Data, Ix2};
+use num_traits::{Float, FromPrimitive};
+
+use crate::algorithms::lines::{LineAssembler, LineSink};
+use crate::error::{VanadiumError, VanadiumResult};
+
+#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum ResizeMethod {
+    Nearest,
+    Bilinear,
+}
+
+impl FromStr for ResizeMethod {
+    type Err = VanadiumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nearest" => Ok(ResizeMethod::Nearest),
+            "bilinear" => Ok(ResizeMethod::Bilinear),
+            _ => Err(VanadiumError::InvalidArgs("Invalid resize method".to_owned()))
+        }
+    }
+}
+
+/// Source indices and weights making up each output index along one axis.
+///
+/// The sources of successive outputs never move backwards, so a streaming pass only needs to keep
+/// the lines between the first source of the next output and the newest line.
+type AxisWeights = Vec<Vec<(usize, f64)>>;
+
+/// A separable spatial resampling of an image, as weights along the lines and pixels.
+pub struct Resampler {
+    lines: AxisWeights,
+    pixels: AxisWeights,
+    /// Input lines and pixels covered by each output line and pixel.
+    scale: (f64, f64),
+}
+
+impl Resampler {
+    /// Averages blocks of `factor.0` lines by `factor.1` pixels.
+    ///
+    /// Lines and pixels left over at the bottom and right edges are dropped.
+    pub fn bin(lines: usize, pixels: usize, factor: (usize, usize)) -> VanadiumResult<Self> {
+        Ok(Self {
+            lines: bin_weights(lines, factor.0)?,
+            pixels: bin_weights(pixels, factor.1)?,
+            scale: (factor.0 as f64, factor.1 as f64),
+        })
+    }
+
+    /// Resizes from `(lines, pixels)` to `size`.
+    pub fn resize(
+        method: ResizeMethod,
+        lines: usize,
+        pixels: usize,
+        size: (usize, usize),
+    ) -> VanadiumResult<Self> {
+        if size.0 == 0 || size.1 == 0 {
+            return Err(VanadiumError::InvalidArgs("Output size must not be zero".to_owned()));
+        }
+
+        let weights = match method {
+            ResizeMethod::Nearest => nearest_weights,
+            ResizeMethod::Bilinear => bilinear_weights,
+        };
+
+        Ok(Self {
+            lines: weights(lines, size.0),
+            pixels: weights(pixels, size.1),
+            scale: (lines as f64 / size.0 as f64, pixels as f64 / size.1 as f64),
+        })
+    }
+
+    /// `(lines, pixels)` of the output.
+    pub fn size(&self) -> (usize, usize) {
+        (self.lines.len(), self.pixels.len())
+    }
+
+    /// Input `(lines, pixels)` covered by each output line and pixel.
+    pub fn scale(&self) -> (f64, f64) {
+        self.scale
+    }
+
+    /// Resamples one line along the pixel axis.
+    fn resample_pixels<T>(&self, line: &Array2<T>) -> Array2<T> where T: Float + FromPrimitive {
+        let mut out = Array2::zeros((self.pixels.len(), line.ncols()));
+
+        for (mut o, weights) in out.outer_iter_mut().zip(&self.pixels) {
+            for (p, w) in weights {
+                let w = T::from_f64(*w).unwrap();
+                o.zip_mut_with(&line.row(*p), |o, x| *o = *o + w * *x);
+            }
+        }
+
+        out
+    }
+}
+
+fn bin_weights(n: usize, factor: usize) -> VanadiumResult<AxisWeights> {
+    if factor == 0 || factor > n {
+        return Err(VanadiumError::InvalidArgs(
+            "Bin factor must be between one and the image size".to_owned()
+        ));
+    }
+
+    let w = 1.0 / factor as f64;
+
+    Ok((0..(n / factor)).map(|i| (0..factor).map(|k| (i * factor + k, w)).collect()).collect())
+}
+
+/// Position in the input of the center of output `i`, in units of input samples.
+fn source_position(i: usize, n_in: usize, n_out: usize) -> f64 {
+    (i as f64 + 0.5) * n_in as f64 / n_out as f64 - 0.5
+}
+
+fn nearest_weights(n_in: usize, n_out: usize) -> AxisWeights {
+    (0..n_out)
+        .map(|i| {
+            let x = source_position(i, n_in, n_out).round().max(0.0) as usize;
+            vec![(x.min(n_in - 1), 1.0)]
+        })
+        .collect()
+}
+
+fn bilinear_weights(n_in: usize, n_out: usize) -> AxisWeights {
+    (0..n_out)
+        .map(|i| {
+            let x = source_position(i, n_in, n_out).max(0.0).min((n_in - 1) as f64);
+
+            let lo = x.floor() as usize;
+            let hi = (lo + 1).min(n_in - 1);
+            let t = x - lo as f64;
+
+            if hi == lo || t == 0.0 {
+                vec![(lo, 1.0)]
+            } else {
+                vec![(lo, 1.0 - t), (hi, t)]
+            }
+        })
+        .collect()
+}
+
+/// Streams an image through a [`Resampler`], keeping only the lines the next output line needs.
+pub struct LineResampler<'a, T, W> {
+    resampler: &'a Resampler,
+    assembler: LineAssembler<T>,
+    /// Recent input lines, already resampled along the pixel axis.
+    lines: VecDeque<Array2<T>>,
+    /// Index of the first line in `lines`.
+    first: usize,
+    /// Index of the next line to be written.
+    next: usize,
+    received: usize,
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<'a, T, W> LineResampler<'a, T, W>
+    where T: Float + FromPrimitive + 'static,
+          W: LineSink<T>
+{
+    pub fn new(resampler: &'a Resampler, pixels: usize, channels: usize, writer: W) -> Self {
+        Self {
+            resampler,
+            assembler: LineAssembler::new(pixels, channels),
+            lines: VecDeque::new(),
+            first: 0,
+            next: 0,
+            received: 0,
+            writer,
+            error: None,
+        }
+    }
+
+    pub fn add<S>(&mut self, pixels: &ArrayBase<S, Ix2>) where S: Data<Elem=T> {
+        let mut completed = Vec::new();
+
+        self.assembler.add(pixels, |line| completed.push(line));
+
+        for line in completed {
+            self.lines.push_back(self.resampler.resample_pixels(&line));
+            self.received += 1;
+
+            while self.next < self.resampler.lines.len() && self.ready(self.next) {
+                self.write_next();
+            }
+
+            self.discard();
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+
+        if self.next < self.resampler.lines.len() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+
+    fn ready(&self, line: usize) -> bool {
+        self.resampler.lines[line].iter().all(|(l, _)| *l < self.received)
+    }
+
+    fn write_next(&mut self) {
+        let first = self.first;
+
+        let mut out = Array2::zeros(self.lines[0].raw_dim());
+
+        for (l, w) in &self.resampler.lines[self.next] {
+            let w = T::from_f64(*w).unwrap();
+            out.zip_mut_with(&self.lines[l - first], |o, x| *o = *o + w * *x);
+        }
+
+        if self.error.is_none() {
+            if let Err(e) = self.writer.write_line(&out) {
+                self.error = Some(e);
+            }
+        }
+
+        self.next += 1;
+    }
+
+    /// Drops lines which no remaining output line needs.
+    fn discard(&mut self) {
+        let needed = match self.resampler.lines.get(self.next) {
+            Some(weights) => weights.iter().map(|(l, _)| *l).min().unwrap_or(self.received),
+            None => self.received,
+        };
+
+        while self.first < needed && !self.lines.is_empty() {
+            self.lines.pop_front();
+            self.first += 1;
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::{BTreeMap, HashMap};
+
+use ndarray::{Array1, Array2, ArrayBase, Axis, Data, Ix2, LinalgScalar};
+use num_traits::{Float, FromPrimitive};
+use serde_json::Value;
+
+use crate::error::{VanadiumError, VanadiumResult};
+
+/// A named polygon in pixel coordinates, where `x` runs along pixels and `y` along lines.
+///
+/// Pixel `(line, pixel)` covers `[pixel, pixel + 1) x [line, line + 1)`, and belongs to the
+/// polygon if its center does. Holes and multi-part polygons are handled with the even-odd rule
+/// over all rings.
+#[derive(Clone, Debug)]
+pub struct Polygon {
+    pub name: String,
+    pub rings: Vec<Vec<(f64, f64)>>,
+}
+
+impl Polygon {
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        let mut inside = false;
+
+        for ring in &self.rings {
+            let mut j = ring.len() - 1;
+
+            for i in 0..ring.len() {
+                let (xi, yi) = ring[i];
+                let (xj, yj) = ring[j];
+
+                if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+                    inside = !inside;
+                }
+
+                j = i;
+            }
+        }
+
+        inside
+    }
+
+    /// Lowest and highest `y` of any vertex.
+    fn y_range(&self) -> (f64, f64) {
+        self.rings.iter().flatten().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), (_, y)| {
+            (lo.min(*y), hi.max(*y))
+        })
+    }
+}
+
+/// Reads the polygons of a GeoJSON feature collection, feature or geometry.
+///
+/// Features are named by their `name` property where they have one, and by their position
+/// otherwise.
+pub fn polygons_from_geojson(s: &str) -> VanadiumResult<Vec<Polygon>> {
+    let invalid = |message: &str| VanadiumError::InvalidArgs(format!("Invalid GeoJSON: {}", message));
+
+    let value: Value = serde_json::from_str(s).map_err(|e| invalid(&e.to_string()))?;
+
+    let features = match value["type"].as_str() {
+        Some("FeatureCollection") => value["features"].as_array()
+            .ok_or_else(|| invalid("missing features"))?
+            .clone(),
+        Some("Feature") => vec![value],
+        Some(_) => vec![serde_json::json!({ "type": "Feature", "geometry": value })],
+        None => return Err(invalid("missing type")),
+    };
+
+    features.iter()
+        .enumerate()
+        .map(|(i, feature)| {
+            let name = feature["properties"]["name"].as_str()
+                .map(str::to_owned)
+                .unwrap_or_else(|| format!("polygon_{}", i));
+
+            let geometry = &feature["geometry"];
+            let coordinates = &geometry["coordinates"];
+
+            let polygons = match geometry["type"].as_str() {
+                Some("Polygon") => vec![coordinates],
+                Some("MultiPolygon") => coordinates.as_array()
+                    .ok_or_else(|| invalid("malformed coordinates"))?
+                    .iter()
+                    .collect(),
+                _ => return Err(invalid("only Polygon and MultiPolygon geometries are supported")),
+            };
+
+            let mut rings = Vec::new();
+
+            for polygon in polygons {
+                for ring in polygon.as_array().ok_or_else(|| invalid("malformed coordinates"))? {
+                    let ring = ring.as_array()
+                        .ok_or_else(|| invalid("malformed coordinates"))?
+                        .iter()
+                        .map(|p| match (p[0].as_f64(), p[1].as_f64()) {
+                            (Some(x), Some(y)) => Ok((x, y)),
+                            _ => Err(invalid("malformed coordinates")),
+                        })
+                        .collect::<VanadiumResult<Vec<_>>>()?;
+
+                    if ring.len() < 3 {
+                        return Err(invalid("rings need at least three vertices"));
+                    }
+
+                    rings.push(ring);
+                }
+            }
+
+            Ok(Polygon { name, rings })
+        })
+        .collect()
+}
+
+/// How the pixels of an image are assigned to regions of interest.
+pub enum Regions {
+    /// Pixels whose centers lie inside each polygon. Overlapping polygons share pixels.
+    Polygons(Vec<Polygon>),
+    /// One region per distinct value of a label raster, given in line-major order.
+    ///
+    /// NaN labels, and labels equal to `ignore`, belong to no region.
+    Labels { labels: Vec<f32>, ignore: Option<f32> },
+}
+
+impl Regions {
+    /// Groups `n` consecutive pixels, starting from pixel `offset` of the image, by region,
+    /// giving the rows of the batch which belong to each.
+    fn group(&self, offset: usize, n: usize, pixels_per_line: usize) -> Vec<(String, Vec<usize>)> {
+        match self {
+            Regions::Polygons(polygons) => {
+                let first_line = (offset / pixels_per_line) as f64;
+                let last_line = ((offset + n) / pixels_per_line + 1) as f64;
+
+                polygons.iter()
+                    .filter(|polygon| {
+                        let (lo, hi) = polygon.y_range();
+                        hi >= first_line && lo <= last_line
+                    })
+                    .map(|polygon| {
+                        let rows = (0..n)
+                            .filter(|row| {
+                                let i = offset + row;
+                                let x = (i % pixels_per_line) as f64 + 0.5;
+                                let y = (i / pixels_per_line) as f64 + 0.5;
+
+                                polygon.contains(x, y)
+                            })
+                            .collect::<Vec<_>>();
+
+                        (polygon.name.clone(), rows)
+                    })
+                    .filter(|(_, rows)| !rows.is_empty())
+                    .collect()
+            }
+            Regions::Labels { labels, ignore } => {
+                let mut groups: HashMap<u32, Vec<usize>> = HashMap::new();
+
+                for (row, label) in labels[offset..(offset + n)].iter().enumerate() {
+                    if !label.is_nan() && Some(*label) != *ignore {
+                        // Both zeros are the same label
+                        groups.entry((label + 0.0).to_bits()).or_default().push(row);
+                    }
+                }
+
+                groups.into_iter()
+                    .map(|(label, rows)| (f32::from_bits(label).to_string(), rows))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Running sums for the pixels of one region.
+///
+/// Pixels are offset by the first pixel seen, so that the sums stay small relative to the
+/// spread of the region and the covariance does not lose precision to cancellation.
+pub struct RegionStats<T> {
+    shift: Array1<T>,
+    sums: Array1<T>,
+    products: Array2<T>,
+    count: usize,
+}
+
+impl<T> RegionStats<T> where T: Float + FromPrimitive + LinalgScalar {
+    pub fn new(channels: usize) -> Self {
+        Self {
+            shift: Array1::zeros(channels),
+            sums: Array1::zeros(channels),
+            products: Array2::zeros((channels, channels)),
+            count: 0,
+        }
+    }
+
+    pub fn add<S>(&mut self, pixels: &ArrayBase<S, Ix2>) where S: Data<Elem=T> {
+        if pixels.nrows() == 0 {
+            return;
+        }
+
+        if self.count == 0 {
+            self.shift.assign(&pixels.row(0));
+        }
+
+        let shifted = pixels - &self.shift;
+
+        self.sums = &self.sums + &shifted.sum_axis(Axis(0));
+        self.products = &self.products + &shifted.t().dot(&shifted);
+        self.count += pixels.nrows();
+    }
+
+    pub fn summary(&self) -> RegionSummary<T> {
+        let n = T::from_usize(self.count.max(1)).unwrap();
+
+        let shifted_mean = self.sums.mapv(|x| x / n);
+
+        let mut covariance = self.products.mapv(|x| x / n);
+
+        for ((i, j), c) in covariance.indexed_iter_mut() {
+            *c = *c - shifted_mean[i] * shifted_mean[j];
+        }
+
+        RegionSummary {
+            count: self.count,
+            mean: &shifted_mean + &self.shift,
+            std_dev: covariance.diag().mapv(|v| v.max(T::zero()).sqrt()),
+            covariance,
+        }
+    }
+}
+
+/// Statistics of the pixels in one region of interest.
+///
+/// Standard deviations and covariances are taken over the region's pixels, dividing by the pixel
+/// count, as for the whole-image statistics.
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct RegionSummary<T> {
+    pub count: usize,
+    pub mean: Array1<T>,
+    pub std_dev: Array1<T>,
+    pub covariance: Array2<T>,
+}
+
+/// Accumulates statistics for every region in a single pass over the image.
+pub struct RegionAccumulator<'a, T> {
+    regions: &'a Regions,
+    pixels_per_line: usize,
+    channels: usize,
+    offset: usize,
+    stats: HashMap<String, RegionStats<T>>,
+}
+
+impl<'a, T> RegionAccumulator<'a, T> where T: Float + FromPrimitive + LinalgScalar {
+    pub fn new(regions: &'a Regions, pixels_per_line: usize, channels: usize) -> Self {
+        Self { regions, pixels_per_line, channels, offset: 0, stats: HashMap::new() }
+    }
+
+    pub fn add<S>(&mut self, pixels: &ArrayBase<S, Ix2>) where S: Data<Elem=T> {
+        let groups = self.regions.group(self.offset, pixels.nrows(), self.pixels_per_line);
+
+        for (name, rows) in groups {
+            let channels = self.channels;
+
+            self.stats.entry(name)
+                .or_insert_with(|| RegionStats::new(channels))
+                .add(&pixels.select(Axis(0), &rows));
+        }
+
+        self.offset += pixels.nrows();
+    }
+
+    /// Statistics of every region with at least one pixel, ordered by name.
+    pub fn summaries(&self) -> BTreeMap<String, RegionSummary<T>> {
+        self.stats.iter()
+            .map(|(name, stats)| (name.clone(), stats.summary()))
+            .collect()
+    }
+}
@@ -0,0 +1,75 @@
+use std::f64::consts::PI;
+
+use ndarray::{Array1, Array2, ArrayBase, Data, Ix2};
+use num_traits::{Float, FromPrimitive};
+use rand::Rng;
+use rand::rngs::StdRng;
+
+/// Draws from the standard normal distribution using the Box-Muller transform.
+pub fn standard_normal<R>(rng: &mut R) -> f64 where R: Rng {
+    // gen yields [0, 1), and ln(0) is not finite
+    let u1 = 1.0 - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+/// A random unit vector, uniformly distributed over directions.
+pub fn random_direction<T, R>(len: usize, rng: &mut R) -> Array1<T>
+    where T: Float + FromPrimitive,
+          R: Rng
+{
+    let v: Array1<f64> = (0..len).map(|_| standard_normal(rng)).collect();
+    let norm = v.dot(&v).sqrt();
+
+    v.mapv(|x| T::from_f64(x / norm).unwrap())
+}
+
+/// Uniform random sample of pixels, gathered in a single pass with reservoir sampling.
+pub struct Reservoir<T> {
+    pub samples: Array2<T>,
+    /// Index of each sampled pixel in the image.
+    pub indices: Vec<usize>,
+    pub seen: usize,
+    pub rng: StdRng,
+}
+
+impl<T> Reservoir<T> where T: Float {
+    pub fn new(capacity: usize, channels: usize, rng: StdRng) -> Self {
+        Self {
+            samples: Array2::zeros((capacity, channels)),
+            indices: vec![0; capacity],
+            seen: 0,
+            rng,
+        }
+    }
+
+    pub fn add<S>(&mut self, pixels: &ArrayBase<S, Ix2>) where S: Data<Elem=T> {
+        let capacity = self.samples.nrows();
+
+        for pixel in pixels.outer_iter() {
+            let slot = if self.seen < capacity {
+                Some(self.seen)
+            } else {
+                let j = self.rng.gen_range(0..=self.seen);
+                if j < capacity { Some(j) } else { None }
+            };
+
+            if let Some(slot) = slot {
+                self.samples.row_mut(slot).assign(&pixel);
+                self.indices[slot] = self.seen;
+            }
+
+            self.seen += 1;
+        }
+    }
+
+    /// The gathered pixel indices and samples, truncated if the image had fewer pixels than
+    /// requested.
+    pub fn into_samples(mut self) -> (Vec<usize>, Array2<T>) {
+        let n = self.seen.min(self.samples.nrows());
+        self.indices.truncate(n);
+
+        (self.indices, self.samples.slice_move(s![..n, ..]))
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::VecDeque;
+use std::io;
+use std::str::FromStr;
+
+use ndarray::{Array2, ArrayBase, Data, Ix2};
+use num_traits::{Float, FromPrimitive};
+
+use crate::error::{VanadiumError, VanadiumResult};
+use crate::algorithms::lines::{LineAssembler, LineSink};
+
+#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum FilterKind {
+    /// Box mean over the window.
+    Smooth,
+    /// The pixel plus its difference from the box mean.
+    Sharpen,
+    /// Gradient magnitude from the 3x3 Sobel operators.
+    Sobel,
+    Median,
+    /// Gaussian-weighted mean over the window.
+    Gaussian,
+}
+
+impl FromStr for FilterKind {
+    type Err = VanadiumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "smooth" => Ok(FilterKind::Smooth),
+            "sharpen" => Ok(FilterKind::Sharpen),
+            "sobel" => Ok(FilterKind::Sobel),
+            "median" => Ok(FilterKind::Median),
+            "gaussian" => Ok(FilterKind::Gaussian),
+            _ => Err(VanadiumError::InvalidArgs("Invalid spatial filter".to_owned()))
+        }
+    }
+}
+
+/// A square filter applied to each band independently.
+///
+/// Pixels beyond the edges of the image are taken from the nearest edge.
+pub enum SpatialFilter<T> {
+    Convolve(Array2<T>),
+    Sobel,
+    Median(usize),
+}
+
+impl<T> SpatialFilter<T> where T: Float + FromPrimitive + 'static {
+    /// `size` is the width of the window, which must be odd; Sobel is always 3x3.
+    ///
+    /// Gaussians get a standard deviation of a sixth of the width, so the window spans three
+    /// deviations either side.
+    pub fn new(kind: FilterKind, size: usize) -> VanadiumResult<Self> {
+        if size % 2 != 1 {
+            return Err(VanadiumError::InvalidArgs("Filter size must be odd".to_owned()));
+        }
+
+        let mean = T::from_usize(size * size).unwrap().recip();
+
+        Ok(match kind {
+            FilterKind::Smooth => SpatialFilter::Convolve(Array2::from_elem((size, size), mean)),
+            FilterKind::Sharpen => {
+                let mut kernel = Array2::from_elem((size, size), -mean);
+                kernel[[size / 2, size / 2]] = kernel[[size / 2, size / 2]] + T::from_f64(2.0).unwrap();
+                SpatialFilter::Convolve(kernel)
+            }
+            FilterKind::Sobel => SpatialFilter::Sobel,
+            FilterKind::Median => SpatialFilter::Median(size),
+            FilterKind::Gaussian => Self::gaussian(size, size as f64 / 6.0)?,
+        })
+    }
+
+    /// A Gaussian blur with standard deviation `sigma`, in pixels, normalized over the window.
+    pub fn gaussian(size: usize, sigma: f64) -> VanadiumResult<Self> {
+        if !(sigma.is_finite() && sigma > 0.0) {
+            return Err(VanadiumError::InvalidArgs(
+                "Gaussian sigma must be positive".to_owned()
+            ));
+        }
+
+        let center = (size / 2) as f64;
+        let weight = |i: usize| (-(i as f64 - center).powi(2) / (2.0 * sigma * sigma)).exp();
+
+        let weights: Vec<f64> = (0..size).map(weight).collect();
+        let total: f64 = weights.iter().sum::<f64>().powi(2);
+
+        let rows: Vec<Vec<T>> = weights.iter()
+            .map(|wi| weights.iter().map(|wj| T::from_f64(wi * wj / total).unwrap()).collect())
+            .collect();
+
+        Self::from_kernel(&rows)
+    }
+
+    /// A convolution with a custom kernel, given as rows, which must be square with an odd width.
+    pub fn from_kernel(rows: &[Vec<T>]) -> VanadiumResult<Self> {
+        let size = rows.len();
+
+        if size % 2 != 1 || rows.iter().any(|r| r.len() != size) {
+            return Err(VanadiumError::InvalidArgs(
+                "Kernel must be square, with an odd width".to_owned()
+            ));
+        }
+
+        if rows.iter().flatten().any(|x| !x.is_finite()) {
+            return Err(VanadiumError::InvalidArgs("Kernel weights must be finite".to_owned()));
+        }
+
+        Ok(SpatialFilter::Convolve(Array2::from_shape_fn((size, size), |(i, j)| rows[i][j])))
+    }
+
+    /// Number of lines needed on either side of each output line.
+    pub fn radius(&self) -> usize {
+        match self {
+            SpatialFilter::Convolve(kernel) => kernel.nrows() / 2,
+            SpatialFilter::Sobel => 1,
+            SpatialFilter::Median(size) => size / 2,
+        }
+    }
+
+    /// Filters one line, given the `2 * radius + 1` lines centered on it.
+    pub fn apply(&self, lines: &[&Array2<T>], out: &mut Array2<T>) {
+        match self {
+            SpatialFilter::Convolve(kernel) => convolve(kernel, lines, out),
+            SpatialFilter::Sobel => {
+                let gx = ndarray::arr2(&[[-1.0, 0.0, 1.0], [-2.0, 0.0, 2.0], [-1.0, 0.0, 1.0]])
+                    .mapv(|x| T::from_f64(x).unwrap());
+                let gy = gx.t().to_owned();
+
+                let mut y = Array2::zeros(out.raw_dim());
+
+                convolve(&gx, lines, out);
+                convolve(&gy, lines, &mut y);
+
+                out.zip_mut_with(&y, |x, y| *x = x.hypot(*y));
+            }
+            SpatialFilter::Median(size) => {
+                let radius = size / 2;
+                let pixels = out.nrows();
+
+                let mut values = Vec::with_capacity(size * size);
+
+                for ((p, c), o) in out.indexed_iter_mut() {
+                    values.clear();
+
+                    for line in lines {
+                        for dx in 0..*size {
+                            values.push(line[[neighbour(p, dx, radius, pixels), c]]);
+                        }
+                    }
+
+                    let middle = values.len() / 2;
+                    values.select_nth_unstable_by(middle, |a, b| {
+                        a.to_f64().unwrap().total_cmp(&b.to_f64().unwrap())
+                    });
+
+                    *o = values[middle];
+                }
+            }
+        }
+    }
+}
+
+fn convolve<T>(kernel: &Array2<T>, lines: &[&Array2<T>], out: &mut Array2<T>)
+    where T: Float + 'static
+{
+    let radius = kernel.ncols() / 2;
+    let pixels = out.nrows();
+
+    out.fill(T::zero());
+
+    for (weights, line) in kernel.outer_iter().zip(lines) {
+        for (dx, weight) in weights.iter().enumerate() {
+            if weight.is_zero() {
+                continue;
+            }
+
+            for (p, mut o) in out.outer_iter_mut().enumerate() {
+                let src = line.row(neighbour(p, dx, radius, pixels));
+                o.zip_mut_with(&src, |o, x| *o = *o + *weight * *x);
+            }
+        }
+    }
+}
+
+/// Index of the pixel at offset `dx - radius` from `p`, clamped to the line.
+#[inline(always)]
+fn neighbour(p: usize, dx: usize, radius: usize, pixels: usize) -> usize {
+    (p + dx).saturating_sub(radius).min(pixels - 1)
+}
+
+/// Reassembles batches of pixels into lines and keeps a rolling window of them, writing each
+/// filtered line as soon as the lines around it have arrived.
+///
+/// Only `2 * radius + 1` lines are held at once, however wide the image is.
+pub struct LineWindow<T, W> {
+    radius: usize,
+    assembler: LineAssembler<T>,
+    lines: VecDeque<Array2<T>>,
+    /// Index of the first line in `lines`.
+    first: usize,
+    /// Index of the next line to be written.
+    next: usize,
+    received: usize,
+    output: Array2<T>,
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<T, W> LineWindow<T, W> where T: Float + FromPrimitive + 'static, W: LineSink<T> {
+    pub fn new(pixels: usize, channels: usize, radius: usize, writer: W) -> Self {
+        Self {
+            radius,
+            assembler: LineAssembler::new(pixels, channels),
+            lines: VecDeque::with_capacity(2 * radius + 1),
+            first: 0,
+            next: 0,
+            received: 0,
+            output: Array2::zeros((pixels, channels)),
+            writer,
+            error: None,
+        }
+    }
+
+    pub fn add<S>(&mut self, pixels: &ArrayBase<S, Ix2>, filter: &SpatialFilter<T>)
+        where S: Data<Elem=T>
+    {
+        let mut completed = Vec::new();
+
+        self.assembler.add(pixels, |line| completed.push(line));
+
+        for line in completed {
+            self.lines.push_back(line);
+            self.received += 1;
+
+            while self.next + self.radius < self.received {
+                self.write_next(filter);
+            }
+        }
+    }
+
+    /// Writes the lines at the bottom of the image, which have no lines below them to wait for.
+    pub fn finish(mut self, filter: &SpatialFilter<T>) -> io::Result<W> {
+        while self.next < self.received {
+            self.write_next(filter);
+        }
+
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+
+    fn write_next(&mut self, filter: &SpatialFilter<T>) {
+        let last = self.received - 1;
+        let (lines, first, next, radius) = (&self.lines, self.first, self.next, self.radius);
+
+        let window: Vec<&Array2<T>> = (0..=(2 * radius))
+            .map(|d| &lines[(next + d).saturating_sub(radius).min(last) - first])
+            .collect();
+
+        filter.apply(&window, &mut self.output);
+
+        if self.error.is_none() {
+            if let Err(e) = self.writer.write_line(&self.output) {
+                self.error = Some(e);
+            }
+        }
+
+        self.next += 1;
+
+        // the next line needs nothing above next - radius
+        while self.first + self.radius < self.next {
+            self.lines.pop_front();
+            self.first += 1;
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use std::io;
+use std::path::Path;
+
+use ndarray::{Array1, Array2, Axis};
+use ndarray_linalg::{Cholesky, UPLO};
+use num_traits::{Float, FromPrimitive};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::algorithms::lines::{LineSink, LineWriter};
+use crate::algorithms::sample::standard_normal;
+use crate::error::{VanadiumError, VanadiumResult};
+use crate::headers::ImageFormat;
+
+/// A distribution of synthetic pixels whose means and covariance are known exactly.
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "model", rename_all = "lowercase")]
+pub enum SyntheticModel {
+    /// Pixels drawn from a multivariate Gaussian, whose covariance must be positive definite.
+    Gaussian { means: Vec<f64>, covariance: Vec<Vec<f64>> },
+    /// Linear mixtures of endmembers, one per row, with abundances drawn uniformly from the
+    /// simplex, plus independent Gaussian noise with standard deviation `noise` in every band.
+    Mixture {
+        endmembers: Vec<Vec<f64>>,
+        #[serde(default)]
+        noise: f64,
+    },
+}
+
+impl SyntheticModel {
+    /// A Gaussian whose band means step up by ten from 100, and whose bands have variance 4
+    /// and a correlation of `0.5^d` between bands `d` apart.
+    pub fn default_gaussian(channels: usize) -> Self {
+        SyntheticModel::Gaussian {
+            means: (0..channels).map(|i| 100.0 + 10.0 * i as f64).collect(),
+            covariance: (0..channels)
+                .map(|i| {
+                    (0..channels).map(|j| 4.0 * 0.5f64.powi((i as i32 - j as i32).abs())).collect()
+                })
+                .collect(),
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        match self {
+            SyntheticModel::Gaussian { means, .. } => means.len(),
+            SyntheticModel::Mixture { endmembers, .. } => endmembers.first().map_or(0, Vec::len),
+        }
+    }
+
+    pub fn validate(&self) -> VanadiumResult<()> {
+        let invalid = |message: &str| {
+            Err(VanadiumError::InvalidArgs(format!("Invalid synthetic model: {}", message)))
+        };
+
+        let channels = self.channels();
+
+        if channels == 0 {
+            return invalid("no channels");
+        }
+
+        match self {
+            SyntheticModel::Gaussian { covariance, .. } => {
+                if covariance.len() != channels || covariance.iter().any(|r| r.len() != channels) {
+                    return invalid("covariance must be square, with one row per channel");
+                }
+            }
+            SyntheticModel::Mixture { endmembers, noise } => {
+                if endmembers.iter().any(|e| e.len() != channels) {
+                    return invalid("endmembers must all have the same number of channels");
+                }
+
+                if noise.is_nan() || *noise < 0.0 {
+                    return invalid("noise must not be negative");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Expected value of every band.
+    pub fn means(&self) -> Array1<f64> {
+        match self {
+            SyntheticModel::Gaussian { means, .. } => Array1::from(means.clone()),
+            SyntheticModel::Mixture { endmembers, .. } => {
+                // Every abundance has an expected value of 1 / k
+                to_array(endmembers).mean_axis(Axis(0)).unwrap()
+            }
+        }
+    }
+
+    /// Covariance of the bands, normalized by the pixel count as the covariance command is.
+    pub fn covariance(&self) -> Array2<f64> {
+        match self {
+            SyntheticModel::Gaussian { covariance, .. } => to_array(covariance),
+            SyntheticModel::Mixture { endmembers, noise } => {
+                let endmembers = to_array(endmembers);
+                let k = endmembers.nrows() as f64;
+
+                // Abundances uniform on the simplex are Dirichlet(1, ..., 1)
+                let shape = (endmembers.nrows(), endmembers.nrows());
+                let abundances = Array2::from_shape_fn(shape, |(i, j)| {
+                    let scaled = if i == j { k - 1.0 } else { -1.0 };
+                    scaled / (k * k * (k + 1.0))
+                });
+
+                endmembers.t().dot(&abundances).dot(&endmembers)
+                    + Array2::<f64>::eye(endmembers.ncols()) * noise.powi(2)
+            }
+        }
+    }
+}
+
+fn to_array(rows: &[Vec<f64>]) -> Array2<f64> {
+    let ncols = rows.first().map_or(0, Vec::len);
+
+    Array2::from_shape_fn((rows.len(), ncols), |(i, j)| rows[i][j])
+}
+
+/// Expected statistics of a synthetic cube, in the form the statistics commands write them.
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct SyntheticStatistics {
+    pub means: Array1<f64>,
+    pub covariance: Array2<f64>,
+}
+
+enum Sampler {
+    Gaussian { means: Array1<f64>, factor: Array2<f64> },
+    Mixture { endmembers: Array2<f64>, noise: f64 },
+}
+
+/// Draws pixels from a synthetic model, the same ones for the same seed.
+pub struct CubeGenerator {
+    sampler: Sampler,
+    rng: StdRng,
+}
+
+impl CubeGenerator {
+    pub fn new(model: &SyntheticModel, seed: u64) -> VanadiumResult<Self> {
+        model.validate()?;
+
+        let sampler = match model {
+            SyntheticModel::Gaussian { .. } => {
+                let factor = model.covariance().cholesky(UPLO::Lower).map_err(|_| {
+                    VanadiumError::InvalidArgs("Covariance must be positive definite".to_owned())
+                })?;
+
+                Sampler::Gaussian { means: model.means(), factor }
+            }
+            SyntheticModel::Mixture { endmembers, noise } => {
+                Sampler::Mixture { endmembers: to_array(endmembers), noise: *noise }
+            }
+        };
+
+        Ok(Self { sampler, rng: StdRng::seed_from_u64(seed) })
+    }
+
+    /// Draws `n` pixels, one per row.
+    pub fn pixels<T>(&mut self, n: usize) -> Array2<T> where T: Float + FromPrimitive {
+        let rng = &mut self.rng;
+
+        let pixels = match &self.sampler {
+            Sampler::Gaussian { means, factor } => {
+                let z = Array2::from_shape_fn((n, means.len()), |_| standard_normal(rng));
+
+                z.dot(&factor.t()) + means
+            }
+            Sampler::Mixture { endmembers, noise } => {
+                // Normalized exponential draws are uniform on the simplex
+                let mut abundances = Array2::from_shape_fn((n, endmembers.nrows()), |_| {
+                    -(1.0 - rng.gen::<f64>()).ln()
+                });
+
+                for mut row in abundances.rows_mut() {
+                    let total = row.sum();
+                    row.mapv_inplace(|a| a / total);
+                }
+
+                let mut pixels = abundances.dot(endmembers);
+                pixels.mapv_inplace(|x| x + noise * standard_normal(rng));
+
+                pixels
+            }
+        };
+
+        pixels.mapv(|x| T::from_f64(x).unwrap())
+    }
+
+    /// Writes a cube of `lines` by `pixels` in the given interleave, drawing one line at a time.
+    pub fn write_cube<T>(
+        &mut self,
+        format: ImageFormat,
+        out: &dyn AsRef<Path>,
+        lines: usize,
+        pixels: usize,
+    ) -> io::Result<()>
+        where T: Float + FromPrimitive
+    {
+        let mut writer = LineWriter::create(format, out, lines, pixels)?;
+
+        for _ in 0..lines {
+            writer.write_line(&self.pixels::<T>(pixels))?;
+        }
+
+        LineSink::<T>::flush(&mut writer)
+    }
+}
@@ -0,0 +1,249 @@
+use std::str::FromStr;
+
+use ndarray::{Array1, Array2, ArrayBase, Data, Ix2};
+use ndarray_linalg::{Inverse, Lapack, Scalar};
+use num_traits::{Float, FromPrimitive};
+
+use crate::error::{VanadiumError, VanadiumResult};
+
+#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum UnmixingMethod {
+    /// Unconstrained least squares.
+    Ucls,
+    /// Sum-to-one constrained least squares.
+    Scls,
+    /// Fully constrained least squares: non-negative and sum-to-one.
+    Fcls,
+}
+
+impl FromStr for UnmixingMethod {
+    type Err = VanadiumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ucls" => Ok(UnmixingMethod::Ucls),
+            "scls" => Ok(UnmixingMethod::Scls),
+            "fcls" => Ok(UnmixingMethod::Fcls),
+            _ => Err(VanadiumError::InvalidArgs("Invalid unmixing method".to_owned()))
+        }
+    }
+}
+
+/// Linear mixture model solver for a fixed set of endmembers.
+///
+/// Everything that depends only on the endmembers is computed once up front, so unmixing a batch
+/// of pixels is a matrix product for UCLS and SCLS, and a small non-negative least squares solve
+/// per pixel for FCLS.
+pub struct Unmixer<T> {
+    method: UnmixingMethod,
+    /// Endmember spectra, one per row.
+    endmembers: Array2<T>,
+    /// `E^T (E E^T)^-1`, mapping a pixel onto its unconstrained abundances.
+    weights: Array2<T>,
+    /// `(E E^T)^-1 1 / (1^T (E E^T)^-1 1)`, the direction of the sum-to-one correction.
+    correction: Array1<T>,
+    /// Normal equations of the FCLS system augmented with the sum-to-one row.
+    fcls_gram: Vec<Vec<f64>>,
+    fcls_delta: f64,
+}
+
+impl<T> Unmixer<T> where T: Float + FromPrimitive + Lapack + Scalar + 'static {
+    pub fn new(method: UnmixingMethod, endmembers: Array2<T>) -> VanadiumResult<Self> {
+        let p = endmembers.nrows();
+
+        let gram_inv = endmembers.dot(&endmembers.t())
+            .inv()
+            .map_err(|_| VanadiumError::InvalidArgs("Endmembers are linearly dependent".to_owned()))?;
+
+        let weights = endmembers.t().dot(&gram_inv);
+
+        let row_sums = gram_inv.sum_axis(ndarray::Axis(1));
+        let total = row_sums.sum();
+        let correction = row_sums.mapv(|x| x / total);
+
+        // the data rows are scaled down so that the sum-to-one row dominates the fit
+        let max = endmembers.iter().fold(T::zero(), |m, x| Float::max(m, Float::abs(*x)));
+        let fcls_delta = 1e-3 / max.to_f64().unwrap().max(f64::MIN_POSITIVE);
+
+        let fcls_gram = (0..p)
+            .map(|i| (0..p)
+                .map(|j| {
+                    let d = endmembers.row(i).dot(&endmembers.row(j)).to_f64().unwrap();
+                    fcls_delta * fcls_delta * d + 1.0
+                })
+                .collect())
+            .collect();
+
+        Ok(Self {
+            method,
+            endmembers,
+            weights,
+            correction,
+            fcls_gram,
+            fcls_delta,
+        })
+    }
+}
+
+impl<T> Unmixer<T> where T: Float + FromPrimitive + 'static {
+    #[inline(always)]
+    pub fn n_endmembers(&self) -> usize {
+        self.endmembers.nrows()
+    }
+
+    /// Writes the abundances of each pixel to `out`, followed by the RMSE of the fit.
+    pub fn unmix<S>(&self, pixels: &ArrayBase<S, Ix2>, out: &mut Array2<T>) where S: Data<Elem=T> {
+        let p = self.n_endmembers();
+
+        let abundances = match self.method {
+            UnmixingMethod::Ucls => pixels.dot(&self.weights),
+            UnmixingMethod::Scls => {
+                let mut a = pixels.dot(&self.weights);
+
+                for mut row in a.outer_iter_mut() {
+                    let excess = row.sum() - T::one();
+                    row.zip_mut_with(&self.correction, |x, c| *x = *x - excess * *c);
+                }
+
+                a
+            }
+            UnmixingMethod::Fcls => {
+                let projected = pixels.dot(&self.endmembers.t());
+                let mut a = Array2::zeros(projected.raw_dim());
+
+                for (row, mut a_row) in projected.outer_iter().zip(a.outer_iter_mut()) {
+                    let f: Vec<f64> = row.iter()
+                        .map(|x| self.fcls_delta * self.fcls_delta * x.to_f64().unwrap() + 1.0)
+                        .collect();
+
+                    for (x, y) in a_row.iter_mut().zip(nnls(&self.fcls_gram, &f)) {
+                        *x = T::from_f64(y).unwrap();
+                    }
+                }
+
+                a
+            }
+        };
+
+        let residuals = pixels.to_owned() - abundances.dot(&self.endmembers);
+        let n_channels = T::from_usize(self.endmembers.ncols()).unwrap();
+
+        for ((mut o, a), r) in out.outer_iter_mut()
+            .zip(abundances.outer_iter())
+            .zip(residuals.outer_iter())
+        {
+            o.slice_mut(s![..p]).assign(&a);
+            o[p] = (r.dot(&r) / n_channels).sqrt();
+        }
+    }
+}
+
+/// Lawson-Hanson active set solver for `min |Ax - b|` subject to `x >= 0`, given the normal
+/// equations `A^T A` and `A^T b`.
+pub fn nnls(gram: &[Vec<f64>], rhs: &[f64]) -> Vec<f64> {
+    const TOLERANCE: f64 = 1e-12;
+
+    let n = rhs.len();
+
+    let mut x = vec![0.0; n];
+    let mut passive = vec![false; n];
+
+    let gradient = |x: &[f64]| -> Vec<f64> {
+        (0..n).map(|i| rhs[i] - (0..n).map(|j| gram[i][j] * x[j]).sum::<f64>()).collect()
+    };
+
+    for _ in 0..(3 * n) {
+        let w = gradient(&x);
+
+        let next = (0..n)
+            .filter(|j| !passive[*j] && w[*j] > TOLERANCE)
+            .max_by(|a, b| w[*a].partial_cmp(&w[*b]).unwrap());
+
+        let next = match next {
+            Some(j) => j,
+            None => break,
+        };
+
+        passive[next] = true;
+
+        loop {
+            let set: Vec<usize> = (0..n).filter(|j| passive[*j]).collect();
+
+            let sub_gram = set.iter().map(|i| set.iter().map(|j| gram[*i][*j]).collect()).collect();
+            let sub_rhs = set.iter().map(|i| rhs[*i]).collect();
+
+            let mut z = vec![0.0; n];
+
+            match solve(sub_gram, sub_rhs) {
+                Some(solution) => {
+                    for (i, v) in set.iter().zip(solution) {
+                        z[*i] = v;
+                    }
+                }
+                None => {
+                    passive[next] = false;
+                    break;
+                }
+            }
+
+            if set.iter().all(|i| z[*i] > TOLERANCE) {
+                x = z;
+                break;
+            }
+
+            // step as far towards z as possible while staying feasible
+            let alpha = set.iter()
+                .filter(|i| z[**i] <= TOLERANCE)
+                .map(|i| x[*i] / (x[*i] - z[*i]))
+                .fold(f64::INFINITY, f64::min);
+
+            for i in 0..n {
+                x[i] += alpha * (z[i] - x[i]);
+
+                if passive[i] && x[i] <= TOLERANCE {
+                    passive[i] = false;
+                    x[i] = 0.0;
+                }
+            }
+        }
+    }
+
+    x
+}
+
+/// Solves a small dense linear system with partial pivoting.
+pub(crate) fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+
+    for col in 0..n {
+        let pivot = (col..n).max_by(|i, j| a[*i][col].abs().partial_cmp(&a[*j][col].abs()).unwrap())?;
+
+        if a[pivot][col].abs() < f64::EPSILON {
+            return None;
+        }
+
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+
+        let pivot_row = a[col].clone();
+
+        for row in (col + 1)..n {
+            let factor = a[row][col] / pivot_row[col];
+
+            for (x, y) in a[row][col..].iter_mut().zip(&pivot_row[col..]) {
+                *x -= factor * y;
+            }
+
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = vec![0.0; n];
+
+    for row in (0..n).rev() {
+        let s: f64 = ((row + 1)..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - s) / a[row][row];
+    }
+
+    Some(x)
+}
@@ -10,8 +10,18 @@ pub enum VanadiumError {
     IoError,
     #[error("Failed to parse header file")]
     InvalidHeader,
+    #[error("Invalid header metadata: {0}")]
+    InvalidMetadata(String),
     #[error("Invalid CLI args: {0}")]
     InvalidArgs(String),
+    #[error("Invalid expression: {0}")]
+    InvalidExpression(String),
+    #[cfg(feature = "glommio-backend")]
+    #[error(
+        "Glommio needs at least {required} bytes of locked memory, but RLIMIT_MEMLOCK allows \
+        {available}; raise the limit with `ulimit -l`"
+    )]
+    LockedMemory { required: u64, available: u64 },
     #[error("Unknown error")]
     Unknown,
 }
\ No newline at end of file
@@ -1,4 +1,7 @@
 use std::path::{Path};
+use std::str::FromStr;
+
+use crate::error::{VanadiumError, VanadiumResult};
 
 #[derive(Clone)]
 #[derive(Serialize, Deserialize)]
@@ -7,6 +10,216 @@ pub struct Header<P> where P: AsRef<Path> {
     pub dims: ImageDims,
     pub format: ImageFormat,
     pub path: P,
+    #[serde(flatten)]
+    pub bands: BandInfo,
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "map info")]
+    pub map_info: Option<MapInfo>,
+}
+
+impl<P> Header<P> where P: AsRef<Path> {
+    /// Checks that the per-band metadata agrees with the number of channels.
+    pub fn validate(&self) -> VanadiumResult<()> {
+        self.bands.validate(self.dims.channels)
+    }
+}
+
+/// Georeferencing of the pixel grid, following the ENVI map info convention.
+#[derive(Clone, PartialEq, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct MapInfo {
+    /// Name of the projection, such as "UTM".
+    pub projection: String,
+    /// `(x, y)` image coordinates of the reference point, where `(1, 1)` is the upper left
+    /// corner of the upper left pixel.
+    pub reference_pixel: (f64, f64),
+    /// `(easting, northing)` of the reference point.
+    pub reference_coordinate: (f64, f64),
+    /// `(x, y)` size of each pixel in map units.
+    pub pixel_size: (f64, f64),
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub datum: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub units: Option<String>,
+}
+
+impl MapInfo {
+    /// Map info of the image cropped to start at the given line and pixel.
+    pub fn cropped(&self, first_line: u64, first_pixel: u64) -> Self {
+        let (x, y) = self.reference_pixel;
+
+        Self {
+            reference_pixel: (x - first_pixel as f64, y - first_line as f64),
+            ..self.clone()
+        }
+    }
+
+    /// Map info of the image resampled so that each output pixel covers `line_scale` input
+    /// lines and `pixel_scale` input pixels.
+    pub fn scaled(&self, line_scale: f64, pixel_scale: f64) -> Self {
+        let (x, y) = self.reference_pixel;
+        let (width, height) = self.pixel_size;
+
+        Self {
+            reference_pixel: (1.0 + (x - 1.0) / pixel_scale, 1.0 + (y - 1.0) / line_scale),
+            pixel_size: (width * pixel_scale, height * line_scale),
+            ..self.clone()
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = VanadiumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bip" => Ok(ImageFormat::Bip),
+            "bsq" => Ok(ImageFormat::Bsq),
+            _ => Err(VanadiumError::InvalidArgs("Invalid image format".to_owned()))
+        }
+    }
+}
+
+/// Optional per-band metadata.
+///
+/// Every list present must have one entry per channel.
+#[derive(Clone, PartialEq, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct BandInfo {
+    /// Center wavelength of each band, in `wavelength_units`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wavelength: Option<Vec<f64>>,
+    /// Full width at half maximum of each band, in `wavelength_units`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fwhm: Option<Vec<f64>>,
+    /// Units of `wavelength` and `fwhm`, such as "nm" or "um".
+    ///
+    /// Nanometres are assumed if absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wavelength_units: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub band_names: Option<Vec<String>>,
+    /// Bad band list, with a zero for each band that should be excluded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bbl: Option<Vec<u8>>,
+    /// Gain applied to the stored values of each band when they are read.
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "data gain values")]
+    pub data_gain_values: Option<Vec<f64>>,
+    /// Offset added to the stored values of each band after the gain.
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "data offset values")]
+    pub data_offset_values: Option<Vec<f64>>,
+    /// Divisor taking calibrated values to reflectance.
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "reflectance scale factor")]
+    pub reflectance_scale_factor: Option<f64>,
+}
+
+impl BandInfo {
+    pub const NONE: Self = Self {
+        wavelength: None,
+        fwhm: None,
+        wavelength_units: None,
+        band_names: None,
+        bbl: None,
+        data_gain_values: None,
+        data_offset_values: None,
+        reflectance_scale_factor: None,
+    };
+
+    pub fn validate(&self, channels: usize) -> VanadiumResult<()> {
+        fn check<X>(name: &str, values: &Option<Vec<X>>, channels: usize) -> VanadiumResult<()> {
+            match values {
+                Some(v) if v.len() != channels => Err(VanadiumError::InvalidMetadata(
+                    format!("{} has {} entries, but the image has {} channels", name, v.len(), channels)
+                )),
+                _ => Ok(()),
+            }
+        }
+
+        check("wavelength", &self.wavelength, channels)?;
+        check("fwhm", &self.fwhm, channels)?;
+        check("band_names", &self.band_names, channels)?;
+        check("bbl", &self.bbl, channels)?;
+        check("data_gain_values", &self.data_gain_values, channels)?;
+        check("data_offset_values", &self.data_offset_values, channels)?;
+
+        if self.reflectance_scale_factor == Some(0.0) {
+            return Err(VanadiumError::InvalidMetadata(
+                "reflectance_scale_factor must not be zero".to_owned()
+            ));
+        }
+
+        self.units_per_nanometre().map(|_| ())
+    }
+
+    /// How many of `wavelength_units` make up one nanometre.
+    pub fn units_per_nanometre(&self) -> VanadiumResult<f64> {
+        match self.wavelength_units.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("nm") | Some("nanometers") | Some("nanometres") => Ok(1.0),
+            Some("um") | Some("micrometers") | Some("micrometres") | Some("microns") => Ok(1e-3),
+            Some(units) => Err(VanadiumError::InvalidMetadata(
+                format!("unknown wavelength units {}", units)
+            )),
+        }
+    }
+
+    /// Center wavelengths in nanometres, regardless of the header's units.
+    pub fn wavelength_nm(&self) -> Option<Vec<f64>> {
+        self.to_nanometres(&self.wavelength)
+    }
+
+    /// Band widths in nanometres, regardless of the header's units.
+    pub fn fwhm_nm(&self) -> Option<Vec<f64>> {
+        self.to_nanometres(&self.fwhm)
+    }
+
+    fn to_nanometres(&self, values: &Option<Vec<f64>>) -> Option<Vec<f64>> {
+        let scale = self.units_per_nanometre().ok()?;
+
+        values.as_ref().map(|v| v.iter().map(|x| x / scale).collect())
+    }
+
+    /// Metadata of the given bands, in order.
+    pub fn select(&self, bands: &[usize]) -> Self {
+        fn pick<X: Clone>(values: &Option<Vec<X>>, bands: &[usize]) -> Option<Vec<X>> {
+            values.as_ref().map(|v| bands.iter().map(|b| v[*b].clone()).collect())
+        }
+
+        Self {
+            wavelength: pick(&self.wavelength, bands),
+            fwhm: pick(&self.fwhm, bands),
+            wavelength_units: self.wavelength_units.clone(),
+            band_names: pick(&self.band_names, bands),
+            bbl: pick(&self.bbl, bands),
+            data_gain_values: pick(&self.data_gain_values, bands),
+            data_offset_values: pick(&self.data_offset_values, bands),
+            reflectance_scale_factor: self.reflectance_scale_factor,
+        }
+    }
+
+    /// Whether stored values must be calibrated when they are read.
+    pub fn is_calibrated(&self) -> bool {
+        self.data_gain_values.is_some() || self.data_offset_values.is_some()
+            || self.reflectance_scale_factor.is_some()
+    }
+
+    /// Metadata for data which has already been calibrated.
+    pub fn without_calibration(&self) -> Self {
+        Self {
+            data_gain_values: None,
+            data_offset_values: None,
+            reflectance_scale_factor: None,
+            ..self.clone()
+        }
+    }
+
+    /// Metadata for output bands which are named but have no wavelength.
+    pub fn named<I>(names: I) -> Self where I: IntoIterator<Item=String> {
+        Self {
+            band_names: Some(names.into_iter().collect()),
+            ..Self::NONE
+        }
+    }
 }
 
 #[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
@@ -4,9 +4,17 @@ use std::marker::PhantomData;
 use std::mem;
 use std::ops::{AddAssign, DivAssign, SubAssign};
 
-use ndarray::{Array1, Array2, ArrayViewMut2, Axis};
+use ndarray::{Array1, Array2, ArrayViewMut2, Axis, LinalgScalar, Zip};
+use ndarray::linalg::general_mat_mul;
 use num_traits::{Float, FromPrimitive};
 
+use crate::algorithms::continuum::ContinuumRemoval;
+use crate::algorithms::expr::Expr;
+use crate::algorithms::kmeans::{ClusterAccumulator, nearest_clusters};
+use crate::algorithms::pca::Projection;
+use crate::algorithms::sample::Reservoir;
+use crate::algorithms::unmixing::Unmixer;
+use crate::error::{VanadiumError, VanadiumResult};
 use crate::headers::ImageDims;
 
 #[derive(Clone)]
@@ -35,6 +43,18 @@ impl<T> BipDims<T> {
     pub fn num_pixels(&self) -> usize {
         self.dims.lines * self.dims.pixels
     }
+
+    /// Byte offset of the pixel at `(line, pixel)`, checking that `n` pixels from it lie within
+    /// the same line.
+    pub fn pixel_offset(&self, line: usize, pixel: usize, n: usize) -> VanadiumResult<u64> {
+        if line >= self.dims.lines || pixel + n > self.dims.pixels {
+            return Err(VanadiumError::InvalidArgs(format!(
+                "Pixels {}..{} of line {} lie outside the image", pixel, pixel + n, line
+            )));
+        }
+
+        Ok(((line * self.dims.pixels + pixel) * self.pixel_length() * mem::size_of::<T>()) as u64)
+    }
 }
 
 /// # Bip-Specific Methods & Functions
@@ -62,6 +82,51 @@ impl<T> BipDims<T>
         acc.mapv_inplace(|x| x / length);
     }
 
+    /// Accumulates the sum of every column of the image separately.
+    ///
+    /// Batches do not line up with lines, so the accumulator tracks how many pixels have been seen
+    /// to place each pixel in its column.
+    pub fn accumulate_column_means(pixel: &mut Array2<T>, acc: &mut (Array2<T>, usize)) {
+        let (sums, offset) = acc;
+        let columns = sums.nrows();
+
+        for (i, p) in pixel.outer_iter().enumerate() {
+            sums.row_mut((*offset + i) % columns).zip_mut_with(&p, |s, x| *s += *x);
+        }
+
+        *offset += pixel.nrows();
+    }
+
+    pub fn normalize_column_means_accumulator(&self, acc: &mut Array2<T>) {
+        let length = T::from_usize(self.dims.lines).unwrap();
+        acc.mapv_inplace(|x| x / length);
+    }
+
+    pub fn accumulate_column_standard_deviations(
+        pixel: &mut Array2<T>,
+        column_means: &Array2<T>,
+        acc: &mut (Array2<T>, usize),
+    ) {
+        let (sums, offset) = acc;
+        let columns = sums.nrows();
+
+        for (i, p) in pixel.outer_iter().enumerate() {
+            let column = (*offset + i) % columns;
+
+            Zip::from(sums.row_mut(column))
+                .and(&p)
+                .and(column_means.row(column))
+                .for_each(|s, x, m| *s += (*x - *m).powi(2));
+        }
+
+        *offset += pixel.nrows();
+    }
+
+    pub fn normalize_column_standard_deviations_accumulator(&self, acc: &mut Array2<T>) {
+        let length = T::from_usize(self.dims.lines).unwrap();
+        acc.mapv_inplace(|x| (x / length).sqrt());
+    }
+
     pub fn accumulate_standard_deviations(
         pixel: &mut Array2<T>,
         means: &Array1<T>,
@@ -95,14 +160,24 @@ impl<T> BipDims<T>
         }
 
         // hot
-        *acc += &pixel.t().dot(pixel);
+        accumulate_gram_upper(pixel, acc);
     }
 
+    /// Mirrors the upper triangle left by [`BipDims::accumulate_covariances`], then divides by the
+    /// number of pixels.
     pub fn normalize_covariances_accumulator(&self, acc: &mut Array2<T>) {
         let length = T::from_usize(self.num_pixels()).unwrap();
+
+        for i in 0..acc.nrows() {
+            for j in 0..i {
+                acc[[i, j]] = acc[[j, i]];
+            }
+        }
+
         acc.mapv_inplace(|x| x / length);
     }
 
+    /// Applies a linear transform with one row per output channel to each pixel.
     pub fn map_transform(
         pixel: &mut ArrayViewMut2<T>,
         transform: &Array2<T>,
@@ -119,6 +194,179 @@ impl<T> BipDims<T>
         }
 
         // hot
-        *out = transform.dot(&pixel.t())
+        let n = pixel.nrows();
+        out.slice_mut(s![..n, ..]).assign(&pixel.dot(&transform.t()));
+    }
+
+    /// Accumulates the outer products of differences between neighbouring pixels.
+    ///
+    /// This shift-difference estimate of the noise assumes that the signal varies slowly between
+    /// adjacent pixels, so that their difference is dominated by noise.
+    pub fn accumulate_noise_covariances(pixel: &mut Array2<T>, acc: &mut (Array2<T>, usize)) {
+        let n = pixel.nrows();
+
+        if n < 2 {
+            return;
+        }
+
+        let diff = &pixel.slice(s![1.., ..]) - &pixel.slice(s![..n - 1, ..]);
+
+        acc.0 += &diff.t().dot(&diff);
+        acc.1 += n - 1;
     }
-}
\ No newline at end of file
+
+    pub fn normalize_noise_covariances_accumulator(acc: &mut (Array2<T>, usize)) {
+        // the difference of two pixels carries the noise of both
+        let length = T::from_usize(2 * acc.1.max(1)).unwrap();
+        acc.0.mapv_inplace(|x| x / length);
+    }
+
+    pub fn accumulate_reservoir(pixel: &mut Array2<T>, acc: &mut Reservoir<T>) {
+        acc.add(pixel);
+    }
+
+    /// Assigns each pixel to its nearest centroid and adds it to that cluster's sums.
+    ///
+    /// `centroids` must already be projected if a projection is given.
+    pub fn accumulate_clusters(
+        pixel: &mut Array2<T>,
+        centroids: &Array2<T>,
+        projection: Option<&Projection<T>>,
+        acc: &mut ClusterAccumulator<T>,
+    ) {
+        let labels = match projection {
+            Some(projection) => nearest_clusters(&projection.project(pixel), centroids),
+            None => nearest_clusters(pixel, centroids),
+        };
+
+        acc.add(pixel, &labels);
+    }
+
+    pub fn map_classify(
+        pixel: &mut ArrayViewMut2<T>,
+        centroids: &Array2<T>,
+        projection: Option<&Projection<T>>,
+        out: &mut Array2<T>,
+    ) {
+        let labels = match projection {
+            Some(projection) => nearest_clusters(&projection.project(pixel), centroids),
+            None => nearest_clusters(pixel, centroids),
+        };
+
+        for (mut row, label) in out.outer_iter_mut().zip(labels) {
+            row[0] = T::from_usize(label).unwrap();
+        }
+    }
+
+    pub fn map_unmix(pixel: &mut ArrayViewMut2<T>, unmixer: &Unmixer<T>, out: &mut Array2<T>) {
+        unmixer.unmix(pixel, out);
+    }
+
+    /// Subtracts the dark level of each column of a line, then divides by its flat-field gain.
+    pub fn map_column_correction(
+        line: &mut ArrayViewMut2<T>,
+        dark: &Array2<T>,
+        gain: Option<&Array2<T>>,
+        out: &mut Array2<T>,
+    ) {
+        *line -= dark;
+
+        if let Some(gain) = gain {
+            *line /= gain;
+        }
+
+        out.assign(line);
+    }
+
+    /// Applies a separate gain and offset to every band of every column of a line.
+    pub fn map_column_gain_offset(
+        line: &mut ArrayViewMut2<T>,
+        gains: &Array2<T>,
+        offsets: &Array2<T>,
+        out: &mut Array2<T>,
+    ) {
+        Zip::from(out).and(line).and(gains).and(offsets).for_each(|o, x, g, b| *o = *x * *g + *b);
+    }
+
+    pub fn map_continuum_removal(
+        pixel: &mut ArrayViewMut2<T>,
+        removal: &ContinuumRemoval,
+        out: &mut Array2<T>,
+    ) {
+        removal.remove(pixel, out);
+    }
+
+    /// Evaluates each expression into its own output channel.
+    pub fn map_expressions(pixel: &mut ArrayViewMut2<T>, exprs: &[Expr], out: &mut Array2<T>) {
+        let n = pixel.nrows();
+
+        for (j, expr) in exprs.iter().enumerate() {
+            out.slice_mut(s![..n, j]).assign(&expr.eval(pixel));
+        }
+    }
+}
+
+/// Adds `pixels^T pixels` to `acc` in place.
+///
+/// Only the upper triangle of `acc` is guaranteed to be updated, so the lower triangle must be
+/// mirrored from it once accumulation is done.
+fn accumulate_gram_upper<T>(pixels: &Array2<T>, acc: &mut Array2<T>) where T: LinalgScalar {
+    #[cfg(any(feature = "openblas", feature = "netlib"))]
+    {
+        if syrk_upper(pixels, acc) {
+            return;
+        }
+    }
+
+    general_mat_mul(T::one(), &pixels.t(), pixels, T::one(), acc);
+}
+
+/// Symmetric rank-k update of the upper triangle of `acc` through CBLAS, which needs half the
+/// FLOPs of a general product.
+///
+/// Returns false, leaving `acc` untouched, if the element type or layouts are not supported.
+#[cfg(any(feature = "openblas", feature = "netlib"))]
+fn syrk_upper<T>(pixels: &Array2<T>, acc: &mut Array2<T>) -> bool where T: 'static {
+    use std::any::TypeId;
+    use std::convert::TryFrom;
+    use std::os::raw::c_int;
+
+    use cblas_sys::{cblas_dsyrk, cblas_ssyrk, CBLAS_LAYOUT, CBLAS_TRANSPOSE, CBLAS_UPLO};
+
+    if !pixels.is_standard_layout() || !acc.is_standard_layout() {
+        return false;
+    }
+
+    let (n, k) = match (c_int::try_from(acc.nrows()), c_int::try_from(pixels.nrows())) {
+        (Ok(n), Ok(k)) => (n, k),
+        _ => return false,
+    };
+
+    if k == 0 {
+        return true;
+    }
+
+    let (layout, uplo, trans) = (
+        CBLAS_LAYOUT::CblasRowMajor,
+        CBLAS_UPLO::CblasUpper,
+        CBLAS_TRANSPOSE::CblasTrans,
+    );
+
+    // Safety: the element type has been checked, and both arrays are contiguous and row major,
+    // with `pixels` being k x n and `acc` being n x n.
+    unsafe {
+        if TypeId::of::<T>() == TypeId::of::<f32>() {
+            let a = pixels.as_ptr() as *const f32;
+            let c = acc.as_mut_ptr() as *mut f32;
+            cblas_ssyrk(layout, uplo, trans, n, k, 1.0, a, n, 1.0, c, n);
+        } else if TypeId::of::<T>() == TypeId::of::<f64>() {
+            let a = pixels.as_ptr() as *const f64;
+            let c = acc.as_mut_ptr() as *mut f64;
+            cblas_dsyrk(layout, uplo, trans, n, k, 1.0, a, n, 1.0, c, n);
+        } else {
+            return false;
+        }
+    }
+
+    true
+}
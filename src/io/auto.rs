@@ -0,0 +1,148 @@
+use std::fs;
+use std::path::Path;
+
+use crate::io::IoConfig;
+use crate::opt::IoBackend;
+
+/// What is known about the machine and the file when choosing a backend.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Probe {
+    /// Backends compiled into this build, in order of preference.
+    pub compiled: Vec<IoBackend>,
+    /// Whether the kernel accepts io_uring, and glommio's memory can be locked.
+    pub io_uring: bool,
+    /// Whether the file can be opened with `O_DIRECT`, which glommio needs.
+    pub direct_io: bool,
+    pub file_size: u64,
+    /// Memory which can hold the file in page cache, if known.
+    pub available_memory: Option<u64>,
+}
+
+impl Probe {
+    pub fn new(path: &Path, config: &IoConfig) -> Self {
+        Self {
+            compiled: compiled_backends(),
+            io_uring: io_uring_available(config),
+            direct_io: direct_io_supported(path),
+            file_size: fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+            available_memory: available_memory(),
+        }
+    }
+
+    /// Whether the whole file fits in page cache, so that repeated passes are served from memory.
+    pub fn fits_in_cache(&self) -> bool {
+        match self.available_memory {
+            Some(memory) => self.file_size <= memory,
+            None => false,
+        }
+    }
+}
+
+/// Picks a backend for the probed file, and the reason for picking it.
+///
+/// Files which fit in page cache are memory mapped, as every pass after the first reads from
+/// memory. Larger files are read with direct io through glommio where the kernel and filesystem
+/// allow it, so that streaming them does not evict everything else from the cache, and with
+/// plain syscalls otherwise.
+pub fn choose(probe: &Probe) -> (IoBackend, &'static str) {
+    let has = |backend| probe.compiled.contains(&backend);
+
+    let glommio = has(IoBackend::Glommio) && probe.io_uring && probe.direct_io;
+
+    if probe.fits_in_cache() && has(IoBackend::Mapped) {
+        (IoBackend::Mapped, "the file fits in page cache")
+    } else if glommio {
+        (IoBackend::Glommio, "the file is larger than page cache, and direct io is available")
+    } else if has(IoBackend::Syscall) {
+        (IoBackend::Syscall, "direct io is unavailable")
+    } else {
+        let backend = probe.compiled.first().copied().unwrap_or(IoBackend::Syscall);
+
+        (backend, "no better backend is compiled in")
+    }
+}
+
+/// Chooses a backend for the image at `path`, logging the decision.
+pub fn select_backend(path: &Path, config: &IoConfig) -> IoBackend {
+    let probe = Probe::new(path, config);
+    let (backend, reason) = choose(&probe);
+
+    eprintln!("Using the {:?} backend for {}, as {}", backend, path.display(), reason);
+
+    backend
+}
+
+/// Backends compiled into this build, in order of preference.
+pub fn compiled_backends() -> Vec<IoBackend> {
+    let mut backends = Vec::new();
+
+    if cfg!(feature = "glommio-backend") {
+        backends.push(IoBackend::Glommio);
+    }
+
+    if cfg!(feature = "syscall-backend") {
+        backends.push(IoBackend::Syscall);
+    }
+
+    if cfg!(feature = "mapped-backend") {
+        backends.push(IoBackend::Mapped);
+    }
+
+    if cfg!(feature = "tokio-backend") {
+        backends.push(IoBackend::Tokio);
+    }
+
+    backends
+}
+
+#[cfg(feature = "glommio-backend")]
+fn io_uring_available(config: &IoConfig) -> bool {
+    // io_uring_params, which the kernel fills in, is 120 bytes
+    let mut params = [0u32; 30];
+
+    // Safety: the kernel only writes within the params struct it is given
+    let fd = unsafe { libc::syscall(libc::SYS_io_uring_setup, 1u32, params.as_mut_ptr()) };
+
+    if fd < 0 {
+        return false;
+    }
+
+    // Safety: the ring was just created, and nothing else holds its fd
+    unsafe {
+        libc::close(fd as libc::c_int);
+    }
+
+    crate::io::glommio::locked_memory_budget(config).is_ok()
+}
+
+#[cfg(not(feature = "glommio-backend"))]
+fn io_uring_available(_config: &IoConfig) -> bool {
+    false
+}
+
+#[cfg(feature = "glommio-backend")]
+fn direct_io_supported(path: &Path) -> bool {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    // Filesystems without O_DIRECT, such as tmpfs, refuse to open the file at all
+    fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_DIRECT)
+        .open(path)
+        .is_ok()
+}
+
+#[cfg(not(feature = "glommio-backend"))]
+fn direct_io_supported(_path: &Path) -> bool {
+    false
+}
+
+/// Memory available without swapping, which includes reclaimable page cache.
+fn available_memory() -> Option<u64> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+
+    let line = meminfo.lines().find(|line| line.starts_with("MemAvailable:"))?;
+    let kib = line.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+
+    Some(kib * 1024)
+}
@@ -0,0 +1,174 @@
+use std::fmt::Write;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::error::{VanadiumError, VanadiumResult};
+use crate::io::BasicImage;
+use crate::opt::IoBackend;
+
+/// An operation which can be benchmarked.
+#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BenchOp {
+    Means,
+    Std,
+    Cov,
+}
+
+impl FromStr for BenchOp {
+    type Err = VanadiumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "means" => Ok(BenchOp::Means),
+            "std" => Ok(BenchOp::Std),
+            "cov" => Ok(BenchOp::Cov),
+            _ => Err(VanadiumError::InvalidArgs("Invalid benchmark operation".to_owned()))
+        }
+    }
+}
+
+impl BenchOp {
+    /// Runs the operation as its command does.
+    ///
+    /// Standard deviations are taken around the means, so they make a pass for the means first.
+    pub fn run(&self, image: &mut dyn BasicImage<f32>) -> VanadiumResult<()> {
+        match self {
+            BenchOp::Means => image.means().map(|_| ()),
+            BenchOp::Std => {
+                let means = image.means()?;
+                image.std_deviations(&means).map(|_| ())
+            }
+            BenchOp::Cov => image.covariance_matrix(None, None).map(|_| ()),
+        }
+    }
+
+    /// Number of passes the operation makes over the image.
+    pub fn passes(&self) -> u64 {
+        match self {
+            BenchOp::Std => 2,
+            BenchOp::Means | BenchOp::Cov => 1,
+        }
+    }
+
+    /// Name of the operation in the benchmark tables.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BenchOp::Means => "means",
+            BenchOp::Std => "std",
+            BenchOp::Cov => "cov",
+        }
+    }
+}
+
+/// Name of a backend in the benchmark tables.
+pub fn tool_name(backend: IoBackend) -> &'static str {
+    match backend {
+        IoBackend::Auto => "vanadium (auto)",
+        IoBackend::Glommio => "vanadium (io-uring)",
+        IoBackend::Syscall => "vanadium (syscall)",
+        IoBackend::Mapped => "vanadium (mmap)",
+        IoBackend::Tokio => "vanadium (tokio)",
+    }
+}
+
+/// Timing of one pass over an image.
+#[derive(Copy, Clone, Debug)]
+pub struct Trial {
+    pub wall: Duration,
+    /// Time spent processing batches; the rest of the pass is spent waiting on IO.
+    pub compute: Duration,
+}
+
+/// Timings of one operation on one backend, over every trial.
+///
+/// Times are in seconds, and throughput in GiB of image read per second of wall time.
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct BenchResult {
+    pub tool: String,
+    pub op: BenchOp,
+    pub trials: usize,
+    pub bytes: u64,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub throughput: f64,
+    pub io_wait: f64,
+    pub compute: f64,
+}
+
+impl BenchResult {
+    pub fn new(backend: IoBackend, op: BenchOp, bytes: u64, trials: &[Trial]) -> Self {
+        let n = trials.len().max(1) as f64;
+        let mean_of = |f: &dyn Fn(&Trial) -> f64| trials.iter().map(f).sum::<f64>() / n;
+
+        let mean = mean_of(&|t| t.wall.as_secs_f64());
+        let variance = mean_of(&|t| (t.wall.as_secs_f64() - mean).powi(2));
+        let compute = mean_of(&|t| t.compute.as_secs_f64());
+
+        Self {
+            tool: tool_name(backend).to_owned(),
+            op,
+            trials: trials.len(),
+            bytes,
+            mean,
+            std_dev: variance.sqrt(),
+            throughput: if mean > 0.0 { bytes as f64 / (1u64 << 30) as f64 / mean } else { 0.0 },
+            io_wait: (mean - compute).max(0.0),
+            compute,
+        }
+    }
+}
+
+/// Formats the results as a Markdown table, in the layout of the README's benchmark tables.
+pub fn markdown_table(results: &[BenchResult]) -> String {
+    let mut table = String::new();
+
+    table.push_str(
+        "| Tool                | Operation | Time (mean ± σ)      | Throughput [GiB/s] \
+        | IO wait [s] | Compute [s] |\n"
+    );
+    table.push_str(
+        "|:--------------------|:----------|---------------------:|-------------------:\
+        |------------:|------------:|\n"
+    );
+
+    for r in results {
+        let time = format!("{:.3} s ± {:.3} s", r.mean, r.std_dev);
+
+        writeln!(
+            table,
+            "| {:<19} | {:<9} | {:>20} | {:>18.3} | {:>11.3} | {:>11.3} |",
+            r.tool, r.op.name(), time, r.throughput, r.io_wait, r.compute
+        ).unwrap();
+    }
+
+    table
+}
+
+/// Asks the kernel to drop the file's pages from the page cache, so the next pass reads it cold.
+///
+/// Only clean pages are dropped, which is every page of an image that is only being read.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+pub fn drop_cache(path: &Path) -> VanadiumResult<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = std::fs::File::open(path)
+        .map_err(|_| VanadiumError::FileNotFound(path.display().to_string()))?;
+
+    // Safety: the file descriptor stays open for the duration of the call
+    let result = unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+
+    if result != 0 {
+        return Err(VanadiumError::IoError);
+    }
+
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+pub fn drop_cache(_path: &Path) -> VanadiumResult<()> {
+    Err(VanadiumError::InvalidArgs("Dropping caches is not supported in this build".to_owned()))
+}
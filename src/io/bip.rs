@@ -1,14 +1,35 @@
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::iter::Sum;
 use std::ops::{AddAssign, DivAssign, SubAssign};
 use std::path::Path;
 
 use image::{RgbImage};
-use ndarray::{Array1, Array2, ArrayViewMut2};
+use ndarray::{Array1, Array2, ArrayViewMut2, Axis, Zip};
 use ndarray_linalg::{Lapack, Scalar};
 use num_traits::{Float, FromPrimitive};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 
-use crate::error::VanadiumResult;
+use crate::algorithms::continuum::ContinuumRemoval;
+use crate::algorithms::endmembers::{
+    ExtremeAccumulator, NfindrAccumulator, orthogonal_direction, PpiAccumulator, SpectralLibrary,
+    stack_rows,
+};
+use crate::algorithms::expr::Expr;
+use crate::algorithms::histogram::{accumulate_distributions, BandDistribution};
+use crate::algorithms::kmeans::{
+    ClusterAccumulator, Clustering, isodata_step, kmeans_plus_plus, KMeansParams,
+};
+use crate::algorithms::lines::LineWriter;
+use crate::algorithms::pca::Projection;
+use crate::algorithms::resize::{LineResampler, Resampler};
+use crate::algorithms::roi::{RegionAccumulator, Regions, RegionSummary};
+use crate::algorithms::sample::{random_direction, Reservoir};
+use crate::algorithms::spatial::{LineWindow, SpatialFilter};
+use crate::algorithms::unmixing::Unmixer;
+use crate::error::{VanadiumError, VanadiumResult};
+use crate::headers::ImageFormat;
 use crate::image_formats::bip::BipDims;
 use crate::io::BasicImage;
 
@@ -39,6 +60,22 @@ pub trait Bip<T> {
         f: F,
     ) -> VanadiumResult<()>
         where F: FnMut(&mut ArrayViewMut2<T>, &mut Array2<T>);
+    /// Reads `n` consecutive pixels of one line, starting at `(line, pixel)`, seeking straight to
+    /// them rather than scanning the file.
+    fn read_pixels(&mut self, line: usize, pixel: usize, n: usize) -> VanadiumResult<Array2<T>>;
+
+    /// Maps the image one whole line at a time, so that row `i` of each batch is column `i`.
+    fn map_lines_and_write<F>(
+        &mut self,
+        name: &str,
+        out: &dyn AsRef<Path>,
+        n_output_channels: usize,
+        f: F,
+    ) -> VanadiumResult<()>
+        where F: FnMut(&mut ArrayViewMut2<T>, &mut Array2<T>)
+    {
+        self.crop_map(name, None, None, n_output_channels, out, f)
+    }
 }
 
 impl<C, T> BasicImage<T> for C
@@ -82,6 +119,98 @@ impl<C, T> BasicImage<T> for C
         Ok(res)
     }
 
+    fn column_means(&mut self) -> VanadiumResult<Array2<T>> {
+        let accumulator = (Array2::zeros((self.dims().dims.pixels, self.dims().pixel_length())), 0);
+
+        let (mut res, _) = self.fold_batched("column means", accumulator, |pixels, acc| {
+            BipDims::accumulate_column_means(pixels, acc)
+        })?;
+
+        self.dims().normalize_column_means_accumulator(&mut res);
+
+        Ok(res)
+    }
+
+    fn region_statistics(
+        &mut self,
+        regions: &Regions,
+    ) -> VanadiumResult<BTreeMap<String, RegionSummary<T>>> {
+        let dims = self.dims().dims.clone();
+
+        let accumulator = RegionAccumulator::new(regions, dims.pixels, dims.channels);
+
+        let accumulator = self.fold_batched("region statistics", accumulator, |pixels, acc| {
+            acc.add(pixels)
+        })?;
+
+        Ok(accumulator.summaries())
+    }
+
+    fn band_distributions(
+        &mut self,
+        bins: usize,
+        sketch_size: usize,
+        seed: u64,
+    ) -> VanadiumResult<Vec<BandDistribution>> {
+        let accumulator: Vec<_> = (0..self.dims().pixel_length())
+            .map(|band| BandDistribution::new(bins, sketch_size, seed.wrapping_add(band as u64)))
+            .collect();
+
+        self.fold_batched("band distributions", accumulator, |pixels, acc| {
+            accumulate_distributions(pixels, acc)
+        })
+    }
+
+    fn read_band(&mut self, band: usize) -> VanadiumResult<Vec<T>> {
+        if band >= self.dims().pixel_length() {
+            return Err(VanadiumError::InvalidArgs(format!("Band {} is not in the image", band)));
+        }
+
+        let values = Vec::with_capacity(self.dims().num_pixels());
+
+        self.fold_batched("read band", values, |pixels, acc| {
+            acc.extend(pixels.column(band).iter().cloned())
+        })
+    }
+
+    fn column_std_deviations(&mut self, column_means: &Array2<T>) -> VanadiumResult<Array2<T>> {
+        let accumulator = (Array2::zeros(column_means.raw_dim()), 0);
+
+        let (mut res, _) = self.fold_batched("column std", accumulator, |pixels, acc| {
+            BipDims::accumulate_column_standard_deviations(pixels, column_means, acc)
+        })?;
+
+        self.dims().normalize_column_standard_deviations_accumulator(&mut res);
+
+        Ok(res)
+    }
+
+    fn write_destriped(
+        &mut self,
+        gains: &Array2<T>,
+        offsets: &Array2<T>,
+        out: &dyn AsRef<Path>,
+    ) -> VanadiumResult<()> {
+        let channels = self.dims().pixel_length();
+
+        self.map_lines_and_write("destripe", out, channels, |line, write_array| {
+            BipDims::map_column_gain_offset(line, gains, offsets, write_array)
+        })
+    }
+
+    fn write_column_corrected(
+        &mut self,
+        dark: &Array2<T>,
+        gain: Option<&Array2<T>>,
+        out: &dyn AsRef<Path>,
+    ) -> VanadiumResult<()> {
+        let channels = self.dims().pixel_length();
+
+        self.map_lines_and_write("correct", out, channels, |line, write_array| {
+            BipDims::map_column_correction(line, dark, gain, write_array)
+        })
+    }
+
     fn write_transformed(
         &mut self,
         transform: &Array2<T>,
@@ -90,7 +219,7 @@ impl<C, T> BasicImage<T> for C
         std_devs: Option<&Array1<T>>,
     ) -> VanadiumResult<()>
     {
-        self.map_and_write_batched("write", out, transform.ncols(), |pixels, write_array| {
+        self.map_and_write_batched("write", out, transform.nrows(), |pixels, write_array| {
             BipDims::map_transform(pixels, transform, write_array, means, std_devs)
         })
     }
@@ -99,6 +228,20 @@ impl<C, T> BasicImage<T> for C
         self.crop_map("crop", rows, cols, self.dims().dims.channels, out, |r, w| *w = r.to_owned())
     }
 
+    fn subset(
+        &mut self,
+        rows: Option<(u64, u64)>,
+        cols: Option<(u64, u64)>,
+        bands: &[usize],
+        out: &dyn AsRef<Path>,
+    ) -> VanadiumResult<()> {
+        if bands.is_empty() {
+            return Err(VanadiumError::InvalidArgs("Subset contains no bands".to_owned()));
+        }
+
+        self.crop_map("subset", rows, cols, bands.len(), out, |r, w| *w = r.select(Axis(1), bands))
+    }
+
     fn rgb_batched(
         &mut self,
         colormap: &mut dyn FnMut(&mut Array2<T>) -> Array2<u8>,
@@ -118,4 +261,301 @@ impl<C, T> BasicImage<T> for C
 
         Ok(RgbImage::from_raw(width as u32, height as u32, vec).unwrap())
     }
+
+    fn sample_pixels(&mut self, n: usize, seed: u64) -> VanadiumResult<(Vec<usize>, Array2<T>)> {
+        let accumulator = Reservoir::new(n, self.dims().pixel_length(), StdRng::seed_from_u64(seed));
+
+        let res = self.fold_batched("sample", accumulator, |pixels, acc| {
+            BipDims::accumulate_reservoir(pixels, acc)
+        })?;
+
+        Ok(res.into_samples())
+    }
+
+    fn kmeans(&mut self, params: &KMeansParams<T>) -> VanadiumResult<Clustering<T>> {
+        let mut rng = StdRng::seed_from_u64(params.seed);
+
+        let (_, sample) = self.sample_pixels(params.sample_size, rng.gen())?;
+
+        // Each initial centroid is a distinct sampled pixel
+        if sample.nrows() < params.clusters {
+            return Err(VanadiumError::InvalidArgs(format!(
+                "K-means needs a sample of at least {} pixels, one per cluster",
+                params.clusters
+            )));
+        }
+
+        let seeds = match &params.projection {
+            Some(projection) => kmeans_plus_plus(&projection.project(&sample), params.clusters, &mut rng),
+            None => kmeans_plus_plus(&sample, params.clusters, &mut rng),
+        };
+
+        let mut centroids = sample.select(Axis(0), &seeds);
+        let mut counts = vec![0; centroids.nrows()];
+        let mut iterations = 0;
+
+        while iterations < params.max_iterations {
+            iterations += 1;
+
+            let search = match &params.projection {
+                Some(projection) => projection.project(&centroids),
+                None => centroids.clone(),
+            };
+
+            let accumulator = ClusterAccumulator::new(centroids.nrows(), self.dims().pixel_length());
+
+            let acc = self.fold_batched("k-means", accumulator, |pixels, acc| {
+                BipDims::accumulate_clusters(pixels, &search, params.projection.as_ref(), acc)
+            })?;
+
+            let updated = acc.centroids(&centroids);
+
+            let mut shift = T::zero();
+
+            Zip::from(updated.rows()).and(centroids.rows()).for_each(|a, b| {
+                let diff = &a - &b;
+                shift = Float::max(shift, Float::sqrt(diff.dot(&diff)));
+            });
+
+            let mut converged = shift <= params.tolerance;
+
+            centroids = updated;
+            counts = acc.counts.clone();
+
+            // the last iteration must leave centroids that match the counts
+            if params.isodata.is_enabled() && iterations < params.max_iterations {
+                if let Some(split_merged) = isodata_step(&centroids, &acc, &params.isodata) {
+                    centroids = split_merged;
+                    converged = false;
+                }
+            }
+
+            if converged {
+                break;
+            }
+        }
+
+        Ok(Clustering { centroids, counts, iterations })
+    }
+
+    fn write_classified(
+        &mut self,
+        centroids: &Array2<T>,
+        projection: Option<&Projection<T>>,
+        out: &dyn AsRef<Path>,
+    ) -> VanadiumResult<()> {
+        let search = match projection {
+            Some(projection) => projection.project(centroids),
+            None => centroids.clone(),
+        };
+
+        self.map_and_write_batched("classify", out, 1, |pixels, write_array| {
+            BipDims::map_classify(pixels, &search, projection, write_array)
+        })
+    }
+
+    fn noise_covariance_matrix(&mut self) -> VanadiumResult<Array2<T>> {
+        let channels = self.dims().pixel_length();
+        let accumulator = (Array2::zeros((channels, channels)), 0);
+
+        let mut res = self.fold_batched("noise", accumulator, |pixels, acc| {
+            BipDims::accumulate_noise_covariances(pixels, acc)
+        })?;
+
+        BipDims::normalize_noise_covariances_accumulator(&mut res);
+
+        Ok(res.0)
+    }
+
+    fn endmembers_ppi(
+        &mut self,
+        projection: &Projection<T>,
+        n_endmembers: usize,
+        n_skewers: usize,
+        seed: u64,
+    ) -> VanadiumResult<SpectralLibrary<T>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let skewers: Vec<Array1<T>> = (0..n_skewers)
+            .map(|_| random_direction(projection.transform.nrows(), &mut rng))
+            .collect();
+
+        let accumulator = PpiAccumulator::new(stack_rows(skewers.iter()));
+
+        let res = self.fold_batched("ppi", accumulator, |pixels, acc| {
+            acc.add(pixels, projection)
+        })?;
+
+        Ok(res.into_library(n_endmembers, self.dims().dims.pixels))
+    }
+
+    fn endmembers_nfindr(
+        &mut self,
+        projection: &Projection<T>,
+        max_iterations: usize,
+        sample_size: usize,
+        seed: u64,
+    ) -> VanadiumResult<SpectralLibrary<T>> {
+        let n_endmembers = projection.transform.nrows() + 1;
+
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let (indices, sample) = self.sample_pixels(sample_size, rng.gen())?;
+        let reduced = projection.project(&sample);
+
+        // spreading the initial simplex out makes a degenerate start unlikely
+        let seeds = kmeans_plus_plus(&reduced, n_endmembers, &mut rng);
+
+        let mut acc = NfindrAccumulator::new(
+            reduced.select(Axis(0), &seeds),
+            seeds.iter().map(|i| indices[*i]).collect(),
+            sample.select(Axis(0), &seeds),
+        )?;
+
+        for _ in 0..max_iterations {
+            acc.start_pass();
+
+            acc = self.fold_batched("n-findr", acc, |pixels, acc| {
+                acc.add(pixels, projection)
+            })?;
+
+            if acc.replacements() == 0 {
+                break;
+            }
+        }
+
+        Ok(acc.into_library(self.dims().dims.pixels))
+    }
+
+    fn endmembers_vca(
+        &mut self,
+        projection: &Projection<T>,
+        seed: u64,
+    ) -> VanadiumResult<SpectralLibrary<T>> {
+        let n_dims = projection.transform.nrows();
+
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let mut indices = Vec::with_capacity(n_dims);
+        let mut spectra = Vec::with_capacity(n_dims);
+        let mut reduced: Vec<Array1<T>> = Vec::with_capacity(n_dims);
+
+        // the first direction only has to avoid the last axis, as in the original algorithm
+        let mut initial = Array1::zeros(n_dims);
+        initial[n_dims - 1] = T::one();
+
+        for _ in 0..n_dims {
+            let basis = if reduced.is_empty() {
+                std::slice::from_ref(&initial)
+            } else {
+                reduced.as_slice()
+            };
+
+            let direction = orthogonal_direction(random_direction(n_dims, &mut rng), basis);
+
+            let res = self.fold_batched("vca", ExtremeAccumulator::new(direction), |pixels, acc| {
+                acc.add(pixels, projection)
+            })?;
+
+            let (index, spectrum, r) = res.into_extreme().ok_or(VanadiumError::Unknown)?;
+
+            indices.push(index);
+            spectra.push(spectrum);
+            reduced.push(r);
+        }
+
+        Ok(SpectralLibrary::from_pixels(&indices, stack_rows(spectra.iter()), self.dims().dims.pixels))
+    }
+
+    fn write_unmixed(&mut self, unmixer: &Unmixer<T>, out: &dyn AsRef<Path>) -> VanadiumResult<()> {
+        self.map_and_write_batched("unmix", out, unmixer.n_endmembers() + 1, |pixels, write_array| {
+            BipDims::map_unmix(pixels, unmixer, write_array)
+        })
+    }
+
+    fn write_expressions(&mut self, exprs: &[Expr], out: &dyn AsRef<Path>) -> VanadiumResult<()> {
+        self.map_and_write_batched("index", out, exprs.len(), |pixels, write_array| {
+            BipDims::map_expressions(pixels, exprs, write_array)
+        })
+    }
+
+    fn write_spatially_filtered(
+        &mut self,
+        filter: &SpatialFilter<T>,
+        format: ImageFormat,
+        out: &dyn AsRef<Path>,
+    ) -> VanadiumResult<()> {
+        let dims = self.dims().dims.clone();
+
+        let writer = LineWriter::create(format, out, dims.lines, dims.pixels)
+            .map_err(|_| VanadiumError::IoError)?;
+
+        let window = LineWindow::new(dims.pixels, dims.channels, filter.radius(), writer);
+
+        let window = self.fold_batched("spatial", window, |pixels, acc| {
+            acc.add(pixels, filter)
+        })?;
+
+        window.finish(filter).map_err(|_| VanadiumError::IoError)?;
+
+        Ok(())
+    }
+
+    fn write_resampled(
+        &mut self,
+        resampler: &Resampler,
+        format: ImageFormat,
+        out: &dyn AsRef<Path>,
+    ) -> VanadiumResult<()> {
+        let dims = self.dims().dims.clone();
+        let (lines, pixels) = resampler.size();
+
+        let writer = LineWriter::create(format, out, lines, pixels)
+            .map_err(|_| VanadiumError::IoError)?;
+
+        let window = LineResampler::new(resampler, dims.pixels, dims.channels, writer);
+
+        let window = self.fold_batched("resample", window, |pixels, acc| acc.add(pixels))?;
+
+        window.finish().map_err(|_| VanadiumError::IoError)?;
+
+        Ok(())
+    }
+
+    fn spectrum(&mut self, line: usize, pixel: usize, radius: usize) -> VanadiumResult<Array1<T>> {
+        let dims = self.dims().dims.clone();
+
+        self.dims().pixel_offset(line, pixel, 1)?;
+
+        let first_pixel = pixel.saturating_sub(radius);
+        let n = (pixel + radius).min(dims.pixels - 1) + 1 - first_pixel;
+
+        let mut sum = Array1::zeros(dims.channels);
+        let mut count = 0;
+
+        for l in line.saturating_sub(radius)..=(line + radius).min(dims.lines - 1) {
+            let pixels = self.read_pixels(l, first_pixel, n)?;
+
+            for p in pixels.outer_iter() {
+                sum.zip_mut_with(&p, |s, x| *s += *x);
+            }
+
+            count += n;
+        }
+
+        let count = T::from_usize(count).unwrap();
+        sum.mapv_inplace(|x| x / count);
+
+        Ok(sum)
+    }
+
+    fn write_continuum_removed(
+        &mut self,
+        removal: &ContinuumRemoval,
+        out: &dyn AsRef<Path>,
+    ) -> VanadiumResult<()> {
+        self.map_and_write_batched("continuum", out, removal.n_outputs(), |pixels, write_array| {
+            BipDims::map_continuum_removal(pixels, removal, write_array)
+        })
+    }
 }
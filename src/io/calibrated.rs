@@ -0,0 +1,129 @@
+use std::path::Path;
+
+use ndarray::{Array1, Array2, ArrayBase, ArrayViewMut2, DataMut, Ix2, Zip};
+use num_traits::{Float, FromPrimitive};
+
+use crate::error::VanadiumResult;
+use crate::headers::BandInfo;
+use crate::image_formats::bip::BipDims;
+use crate::io::bip::Bip;
+
+/// Per-band linear calibration, mapping raw values to `gain * x + offset`.
+#[derive(Clone, Debug)]
+pub struct Calibration<T> {
+    pub gains: Array1<T>,
+    pub offsets: Array1<T>,
+}
+
+impl<T> Calibration<T> where T: Float + FromPrimitive + 'static {
+    /// Builds the calibration declared in a header, or `None` if it declares none.
+    ///
+    /// The reflectance scale factor divides both the gain and the offset.
+    pub fn from_bands(bands: &BandInfo, channels: usize) -> Option<Self> {
+        if !bands.is_calibrated() {
+            return None;
+        }
+
+        let scale = bands.reflectance_scale_factor.unwrap_or(1.0);
+
+        let convert = |values: &Option<Vec<f64>>, default: f64| -> Array1<T> {
+            match values {
+                Some(v) => v.iter().map(|x| T::from_f64(x / scale).unwrap()).collect(),
+                None => Array1::from_elem(channels, T::from_f64(default / scale).unwrap()),
+            }
+        };
+
+        Some(Self {
+            gains: convert(&bands.data_gain_values, 1.0),
+            offsets: convert(&bands.data_offset_values, 0.0),
+        })
+    }
+
+    /// Calibrates a batch of pixels in place.
+    pub fn apply<S>(&self, pixels: &mut ArrayBase<S, Ix2>) where S: DataMut<Elem=T> {
+        for mut pixel in pixels.outer_iter_mut() {
+            Zip::from(&mut pixel).and(&self.gains).and(&self.offsets).for_each(|x, g, o| {
+                *x = *x * *g + *o
+            });
+        }
+    }
+}
+
+/// An image whose pixels are calibrated as they are read.
+///
+/// Every statistic and output computed from this image sees calibrated values, without a
+/// calibrated copy being written first.
+pub struct CalibratedBip<C, T> {
+    inner: C,
+    calibration: Calibration<T>,
+}
+
+impl<C, T> CalibratedBip<C, T> {
+    pub fn new(inner: C, calibration: Calibration<T>) -> Self {
+        Self { inner, calibration }
+    }
+}
+
+impl<C, T> Bip<T> for CalibratedBip<C, T>
+    where C: Bip<T>,
+          T: Float + FromPrimitive + 'static
+{
+    fn fold_batched<F, A>(&mut self, name: &str, accumulator: A, mut f: F) -> VanadiumResult<A>
+        where F: FnMut(&mut Array2<T>, &mut A)
+    {
+        let calibration = &self.calibration;
+
+        self.inner.fold_batched(name, accumulator, |pixels, acc| {
+            calibration.apply(pixels);
+            f(pixels, acc)
+        })
+    }
+
+    fn dims(&self) -> &BipDims<T> {
+        self.inner.dims()
+    }
+
+    fn map_and_write_batched<F>(
+        &mut self,
+        name: &str,
+        out: &dyn AsRef<Path>,
+        n_output_channels: usize,
+        mut f: F,
+    ) -> VanadiumResult<()>
+        where F: FnMut(&mut ArrayViewMut2<T>, &mut Array2<T>)
+    {
+        let calibration = &self.calibration;
+
+        self.inner.map_and_write_batched(name, out, n_output_channels, |pixels, write_array| {
+            calibration.apply(pixels);
+            f(pixels, write_array)
+        })
+    }
+
+    fn crop_map<F>(
+        &mut self,
+        name: &str,
+        rows: Option<(u64, u64)>,
+        cols: Option<(u64, u64)>,
+        n_output_channels: usize,
+        out: &dyn AsRef<Path>,
+        mut f: F,
+    ) -> VanadiumResult<()>
+        where F: FnMut(&mut ArrayViewMut2<T>, &mut Array2<T>)
+    {
+        let calibration = &self.calibration;
+
+        self.inner.crop_map(name, rows, cols, n_output_channels, out, |pixels, write_array| {
+            calibration.apply(pixels);
+            f(pixels, write_array)
+        })
+    }
+
+    fn read_pixels(&mut self, line: usize, pixel: usize, n: usize) -> VanadiumResult<Array2<T>> {
+        let mut pixels = self.inner.read_pixels(line, pixel, n)?;
+
+        self.calibration.apply(&mut pixels);
+
+        Ok(pixels)
+    }
+}
@@ -13,39 +13,36 @@ use num_traits::{Float, FromPrimitive};
 use crate::error::{VanadiumError, VanadiumResult};
 use crate::headers::{Header, ImageFormat};
 use crate::image_formats::bip::BipDims;
-use crate::io::BATCH_SIZE;
+use crate::io::IoConfig;
+use crate::io::glommio::locked_memory_budget;
 use crate::io::bip::Bip;
 use crate::util::{make_raw, make_raw_mut};
 
-const READ_AHEAD: usize = 16;
-const PIN_CPU: usize = 1;
-
-// todo make variable
-// not everyone has exactly this much locked memory
-// maybe make it static or part of structure?
-const LOCKED_MEMORY: usize = 524_288;
-
 pub struct GlommioBip<P, T> where P: AsRef<Path> {
     headers: Header<P>,
     executor: LocalExecutor,
     bip: BipDims<T>,
+    config: IoConfig,
 }
 
 impl<P, T> GlommioBip<P, T> where P: AsRef<Path> + ToString {
-    pub fn new(headers: Header<P>) -> VanadiumResult<Self <>> {
+    pub fn new(headers: Header<P>, config: IoConfig) -> VanadiumResult<Self <>> {
         assert_eq!(ImageFormat::Bip, headers.format);
 
+        let io_memory = locked_memory_budget(&config)?;
+
         let executor = LocalExecutorBuilder::new()
-            .pin_to_cpu(PIN_CPU)
+            .pin_to_cpu(config.pin_cpu)
+            .io_memory(io_memory)
             .make()
-            .map_err(|_| VanadiumError::Unknown).unwrap();
+            .map_err(|_| VanadiumError::Unknown)?;
 
         let bip = BipDims {
             dims: headers.dims.clone(),
             phantom: Default::default(),
         };
 
-        Ok(Self { headers, executor, bip })
+        Ok(Self { headers, executor, bip, config })
     }
 
     async fn open_input_file(&self) -> VanadiumResult<DmaFile> {
@@ -62,8 +59,8 @@ impl<P, T> GlommioBip<P, T> where P: AsRef<Path> + ToString {
         let file = self.open_input_file().await?;
 
         Ok(DmaStreamReaderBuilder::new(file)
-            .with_buffer_size(LOCKED_MEMORY)
-            .with_read_ahead(READ_AHEAD)
+            .with_buffer_size(self.config.buffer_size)
+            .with_read_ahead(self.config.read_ahead)
             .build())
     }
 
@@ -80,8 +77,8 @@ impl<P, T> GlommioBip<P, T> where P: AsRef<Path> + ToString {
     async fn open_output_writer(&self, out: &dyn AsRef<Path>) -> VanadiumResult<DmaStreamWriter> {
         let file = self.open_output_file(out).await?;
         Ok(DmaStreamWriterBuilder::new(file)
-            .with_buffer_size(LOCKED_MEMORY)
-            .with_write_behind(READ_AHEAD)
+            .with_buffer_size(self.config.buffer_size)
+            .with_write_behind(self.config.read_ahead)
             .build())
     }
 }
@@ -95,13 +92,14 @@ impl<P, T> Bip<T> for GlommioBip<P, T>
         where F: FnMut(&mut Array2<T>, &mut A)
     {
         let name = name.to_owned();
+        let batch_size = self.config.batch_size;
 
         self.executor.run(async {
             make_bar!(pb, self.bip.num_pixels() as u64, name);
 
             let mut reader = self.open_input_reader().await?;
 
-            let mut buffer: Vec<T> = vec![T::zero(); BATCH_SIZE * self.bip.pixel_length()];
+            let mut buffer: Vec<T> = vec![T::zero(); batch_size * self.bip.pixel_length()];
 
             let mut seek = 0;
             let byte_len = buffer.len() * mem::size_of::<T>();
@@ -112,14 +110,14 @@ impl<P, T> Bip<T> for GlommioBip<P, T>
                     reader.read_exact(raw_buffer).await.is_ok()
                 }
             } {
-                let shape = (BATCH_SIZE, self.bip.pixel_length());
+                let shape = (batch_size, self.bip.pixel_length());
                 let mut pixel = Array2::from_shape_vec(shape, buffer).unwrap();
 
                 f(&mut pixel, &mut accumulator);
 
                 buffer = pixel.into_raw_vec();
 
-                inc_bar!(pb, BATCH_SIZE as u64);
+                inc_bar!(pb, batch_size as u64);
 
                 seek += byte_len;
             }
@@ -130,7 +128,19 @@ impl<P, T> Bip<T> for GlommioBip<P, T>
             let n_elements = unsafe {
                 let raw_buffer = make_raw_mut(&mut buffer);
 
-                let n_bytes = reader.read(raw_buffer).await.map_err(|_| VanadiumError::IoError)?;
+                // A single read stops at the end of the reader's buffer, which the tail can span
+                let mut n_bytes = 0;
+
+                loop {
+                    let n = reader.read(&mut raw_buffer[n_bytes..]).await
+                        .map_err(|_| VanadiumError::IoError)?;
+
+                    if n == 0 {
+                        break;
+                    }
+
+                    n_bytes += n;
+                }
 
                 // todo use a proper error handling approach, this can be triggered by user error
                 assert_eq!(0, n_bytes % mem::size_of::<T>());
@@ -162,24 +172,28 @@ impl<P, T> Bip<T> for GlommioBip<P, T>
         where F: FnMut(&mut ArrayViewMut2<T>, &mut Array2<T>)
     {
         let name = name.to_owned();
+        let batch_size = self.config.batch_size;
 
         self.executor.run(async {
             make_bar!(pb, self.bip.num_pixels() as u64, name);
 
             let mut read_array = Array2::from_shape_vec(
-                (BATCH_SIZE, self.bip.pixel_length()),
-                vec![T::zero(); BATCH_SIZE * self.bip.pixel_length()],
+                (batch_size, self.bip.pixel_length()),
+                vec![T::zero(); batch_size * self.bip.pixel_length()],
             ).unwrap();
 
             let mut write_array = Array2::from_shape_vec(
-                (BATCH_SIZE, n_output_channels),
-                vec![T::zero(); BATCH_SIZE * n_output_channels],
+                (batch_size, n_output_channels),
+                vec![T::zero(); batch_size * n_output_channels],
             ).unwrap();
 
             let mut reader = self.open_input_reader().await?;
 
             let mut writer = self.open_output_writer(out).await?;
 
+            let mut seek = 0;
+            let byte_len = batch_size * self.bip.pixel_length() * mem::size_of::<T>();
+
             while {
                 unsafe {
                     let raw_read_buffer = make_raw_mut(read_array.as_slice_mut().unwrap());
@@ -190,16 +204,34 @@ impl<P, T> Bip<T> for GlommioBip<P, T>
 
                 unsafe {
                     let raw_write_buffer = make_raw(write_array.as_slice().unwrap());
-                    writer.write(raw_write_buffer).await.map_err(|_| VanadiumError::IoError)?;
+                    writer.write_all(raw_write_buffer).await.map_err(|_| VanadiumError::IoError)?;
                 }
 
-                inc_bar!(pb, BATCH_SIZE as u64);
+                inc_bar!(pb, batch_size as u64);
+
+                seek += byte_len;
             }
 
+            // The failed read may have consumed part of the tail, so read it afresh
+            let mut reader = self.open_input_reader().await?;
+            reader.skip(seek as u64);
+
             let n_elements = unsafe {
                 let raw_buffer = make_raw_mut(read_array.as_slice_mut().unwrap());
 
-                let n_bytes = reader.read(raw_buffer).await.map_err(|_| VanadiumError::IoError)?;
+                // A single read stops at the end of the reader's buffer, which the tail can span
+                let mut n_bytes = 0;
+
+                loop {
+                    let n = reader.read(&mut raw_buffer[n_bytes..]).await
+                        .map_err(|_| VanadiumError::IoError)?;
+
+                    if n == 0 {
+                        break;
+                    }
+
+                    n_bytes += n;
+                }
 
                 // todo use a proper error handling approach, this can be triggered by user error
                 assert_eq!(0, n_bytes % mem::size_of::<T>());
@@ -223,7 +255,7 @@ impl<P, T> Bip<T> for GlommioBip<P, T>
                 }
             }
 
-            writer.flush().await.unwrap();
+            writer.close().await.map_err(|_| VanadiumError::IoError)?;
 
             Ok(())
         })
@@ -302,9 +334,37 @@ impl<P, T> Bip<T> for GlommioBip<P, T>
                 reader.skip(end_row_skip);
             }
 
-            writer.flush().await.unwrap();
+            writer.close().await.map_err(|_| VanadiumError::IoError)?;
 
             Ok(())
         })
     }
+
+    fn read_pixels(&mut self, line: usize, pixel: usize, n: usize) -> VanadiumResult<Array2<T>> {
+        let offset = self.bip.pixel_offset(line, pixel, n)?;
+
+        let mut pixels = Array2::zeros((n, self.bip.pixel_length()));
+
+        self.executor.run(async {
+            let file = self.open_input_file().await?;
+
+            unsafe {
+                let raw_buffer = make_raw_mut(pixels.as_slice_mut().unwrap());
+
+                let read = file.read_at(offset, raw_buffer.len())
+                    .await
+                    .map_err(|_| VanadiumError::IoError)?;
+
+                if read.len() != raw_buffer.len() {
+                    return Err(VanadiumError::IoError);
+                }
+
+                raw_buffer.copy_from_slice(&read);
+            }
+
+            file.close().await.map_err(|_| VanadiumError::IoError)
+        })?;
+
+        Ok(pixels)
+    }
 }
\ No newline at end of file
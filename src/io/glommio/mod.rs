@@ -1 +1,46 @@
-pub mod bip;
\ No newline at end of file
+use crate::error::{VanadiumError, VanadiumResult};
+use crate::io::IoConfig;
+
+pub mod bip;
+
+/// Glommio refuses to start with less locked memory than this.
+const MIN_LOCKED_MEMORY: u64 = 512 * 1024;
+
+/// Memory for glommio to register with io_uring, which has to fit within `RLIMIT_MEMLOCK`.
+///
+/// A configuration asking for more than the limit allows is clamped to the limit with a warning,
+/// as glommio only needs more buffers to read further ahead. Only a limit below the minimum
+/// glommio starts with is an error; without this check it shows up as a failure to build the
+/// executor.
+pub fn locked_memory_budget(config: &IoConfig) -> VanadiumResult<usize> {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+
+    // Safety: getrlimit only writes to the struct it is given
+    if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit) } != 0 {
+        return Err(VanadiumError::IoError);
+    }
+
+    let requested = config.locked_memory();
+
+    if limit.rlim_cur == libc::RLIM_INFINITY {
+        return Ok(requested);
+    }
+
+    let available = limit.rlim_cur;
+
+    if available < MIN_LOCKED_MEMORY {
+        return Err(VanadiumError::LockedMemory { required: MIN_LOCKED_MEMORY, available });
+    }
+
+    if requested as u64 > available {
+        eprintln!(
+            "Glommio asked for {} bytes of locked memory, but RLIMIT_MEMLOCK allows {}; \
+            using {} bytes",
+            requested, available, available
+        );
+
+        return Ok(available as usize);
+    }
+
+    Ok(requested)
+}
@@ -1,28 +1,31 @@
 use std::{io, mem};
 use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
 use std::path::Path;
 
 use byteorder::{LittleEndian, ReadBytesExt};
-use memmap2::MmapMut;
+use memmap2::Mmap;
 use ndarray::{Array2, ArrayViewMut2};
 
-use crate::error::VanadiumResult;
+use crate::error::{VanadiumError, VanadiumResult};
 use crate::headers::{Header, ImageFormat};
 use crate::image_formats::bip::BipDims;
-use crate::io::BATCH_SIZE;
+use crate::io::IoConfig;
 use crate::io::bip::Bip;
+use crate::util::make_raw;
 
 pub struct MappedBip<T> {
-    map: MmapMut,
+    map: Mmap,
     bip: BipDims<T>,
+    config: IoConfig,
 }
 
 impl<T> MappedBip<T> {
-    pub fn new<P>(header: Header<P>) -> io::Result<Self> where P: AsRef<Path> {
+    pub fn new<P>(header: Header<P>, config: IoConfig) -> io::Result<Self> where P: AsRef<Path> {
         assert_eq!(ImageFormat::Bip, header.format);
 
+        // Inputs are only ever read, and may be on read-only filesystems
         let file = OpenOptions::new()
-            .write(true)
             .read(true)
             .open(header.path)?;
 
@@ -31,11 +34,12 @@ impl<T> MappedBip<T> {
             phantom: Default::default(),
         };
 
-        let map = unsafe { MmapMut::map_mut(&file).unwrap() };
+        let map = unsafe { Mmap::map(&file)? };
 
         Ok(Self {
             map,
             bip,
+            config,
         })
     }
 }
@@ -47,10 +51,11 @@ impl Bip<f32> for MappedBip<f32> {
         let mut seek = 0;
 
         let name = name.to_owned();
+        let batch_size = self.config.batch_size;
 
         make_bar!(pb, self.bip.num_pixels() as u64, name);
 
-        let mut buffer = vec![0.0; BATCH_SIZE * self.bip.pixel_length()];
+        let mut buffer = vec![0.0; batch_size * self.bip.pixel_length()];
 
         let byte_len = buffer.len() * mem::size_of::<f32>();
 
@@ -67,16 +72,17 @@ impl Bip<f32> for MappedBip<f32> {
         } {
             seek += byte_len;
 
-            let mut pixel = Array2::from_shape_vec((BATCH_SIZE, self.bip.pixel_length()), buffer).unwrap();
+            let shape = (batch_size, self.bip.pixel_length());
+            let mut pixel = Array2::from_shape_vec(shape, buffer).unwrap();
 
             f(&mut pixel, &mut accumulator);
 
             buffer = pixel.into_raw_vec();
 
-            inc_bar!(pb, BATCH_SIZE as u64);
+            inc_bar!(pb, batch_size as u64);
         }
 
-        let d = &mut self.map[seek..];
+        let mut d = &self.map[seek..];
 
         let n_bytes = d.len();
 
@@ -86,7 +92,7 @@ impl Bip<f32> for MappedBip<f32> {
 
         let b = &mut buffer[..n_elements];
 
-        (&*d).read_f32_into::<LittleEndian>(b).unwrap();
+        d.read_f32_into::<LittleEndian>(b).unwrap();
 
         if n_elements > 0 {
             let shape = (n_elements / self.bip.pixel_length(), self.bip.pixel_length());
@@ -103,16 +109,84 @@ impl Bip<f32> for MappedBip<f32> {
     }
 
     fn map_and_write_batched<F>(
-        &mut self, _name: &str, _out: &dyn AsRef<Path>, _n_output_channels: usize, _f: F,
+        &mut self,
+        name: &str,
+        out: &dyn AsRef<Path>,
+        n_output_channels: usize,
+        f: F,
     ) -> VanadiumResult<()>
         where F: FnMut(&mut ArrayViewMut2<f32>, &mut Array2<f32>)
     {
-        todo!()
+        self.crop_map(name, None, None, n_output_channels, out, f)
+    }
+
+    fn crop_map<F>(
+        &mut self,
+        name: &str,
+        rows: Option<(u64, u64)>,
+        cols: Option<(u64, u64)>,
+        n_output_channels: usize,
+        out: &dyn AsRef<Path>,
+        mut f: F,
+    ) -> VanadiumResult<()>
+        where F: FnMut(&mut ArrayViewMut2<f32>, &mut Array2<f32>)
+    {
+        let write_file = OpenOptions::new()
+            .truncate(true)
+            .write(true)
+            .create(true)
+            .open(out)
+            .map_err(|_| VanadiumError::IoError)?;
+
+        let mut write_file = BufWriter::new(write_file);
+
+        let (start_col, end_col) = cols.unwrap_or((0, self.bip.dims.pixels as u64));
+        let (start_row, end_row) = rows.unwrap_or((0, self.bip.dims.lines as u64));
+
+        let row_length = (end_col - start_col) as usize;
+
+        let pixel_bytes = self.bip.pixel_length() * mem::size_of::<f32>();
+        let line_bytes = self.bip.dims.pixels * pixel_bytes;
+
+        let name = name.to_owned();
+
+        make_bar!(pb, self.bip.num_pixels() as u64, name);
+
+        let mut read_array = Array2::zeros((row_length, self.bip.pixel_length()));
+        let mut write_array = Array2::zeros((row_length, n_output_channels));
+
+        for row in start_row..end_row {
+            let start = row as usize * line_bytes + start_col as usize * pixel_bytes;
+            let end = start + row_length * pixel_bytes;
+
+            let mut d = &self.map[start..end];
+            d.read_f32_into::<LittleEndian>(read_array.as_slice_mut().unwrap())
+                .map_err(|_| VanadiumError::IoError)?;
+
+            f(&mut read_array.view_mut(), &mut write_array);
+
+            unsafe {
+                let raw_write_buffer = make_raw(write_array.as_slice().unwrap());
+                write_file.write_all(raw_write_buffer).map_err(|_| VanadiumError::IoError)?;
+            }
+
+            inc_bar!(pb, row_length as u64);
+        }
+
+        write_file.flush().map_err(|_| VanadiumError::IoError)
     }
 
-    fn crop_map<F>(&mut self, _name: &str, _rows: Option<(u64, u64)>, _cols: Option<(u64, u64)>,
-                   _n_output_channels: usize, _out: &dyn AsRef<Path>, _f: F) -> VanadiumResult<()>
-        where F: FnMut(&mut ArrayViewMut2<f32>, &mut Array2<f32>) {
-        todo!()
+    fn read_pixels(&mut self, line: usize, pixel: usize, n: usize) -> VanadiumResult<Array2<f32>> {
+        let offset = self.bip.pixel_offset(line, pixel, n)? as usize;
+
+        let mut pixels = Array2::zeros((n, self.bip.pixel_length()));
+
+        let byte_len = pixels.len() * mem::size_of::<f32>();
+
+        let mut d = &self.map[offset..(offset + byte_len)];
+        d.read_f32_into::<LittleEndian>(pixels.as_slice_mut().unwrap())
+            .map_err(|_| VanadiumError::IoError)?;
+
+        Ok(pixels)
     }
 }
\ No newline at end of file
@@ -1,17 +1,70 @@
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use ndarray::{Array1, Array2};
 use ndarray_linalg::{Eig, Lapack, Scalar};
 use num_traits::real::Real;
 
+use crate::algorithms::continuum::ContinuumRemoval;
+use crate::algorithms::endmembers::SpectralLibrary;
+use crate::algorithms::expr::Expr;
+use crate::algorithms::histogram::BandDistribution;
+use crate::algorithms::kmeans::{Clustering, KMeansParams};
+use crate::algorithms::pca::Projection;
+use crate::algorithms::resize::Resampler;
+use crate::algorithms::roi::{Regions, RegionSummary};
+use crate::algorithms::spatial::SpatialFilter;
+use crate::algorithms::unmixing::Unmixer;
 use crate::error::{VanadiumError, VanadiumResult};
+use crate::headers::ImageFormat;
 use image::{RgbImage};
 
 #[cfg(feature = "progress")]
 const UPDATE_FREQ: u64 = 8;
 
-// todo possibly make variable
-const BATCH_SIZE: usize = 1024;
+/// Tuning for the IO backends, shared by all of them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IoConfig {
+    /// Number of pixels read and processed at once.
+    pub batch_size: usize,
+    /// Number of buffers the glommio backend reads ahead of, and writes behind, processing.
+    pub read_ahead: usize,
+    /// Size in bytes of each glommio buffer.
+    pub buffer_size: usize,
+    /// CPU the glommio executor is pinned to.
+    pub pin_cpu: usize,
+}
+
+impl Default for IoConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 1024,
+            read_ahead: 16,
+            buffer_size: 524_288,
+            pin_cpu: 1,
+        }
+    }
+}
+
+impl IoConfig {
+    /// Memory which glommio registers with io_uring, enough for a reader and a writer.
+    ///
+    /// Registered memory is locked, so it has to fit within `RLIMIT_MEMLOCK`.
+    #[cfg(feature = "glommio-backend")]
+    pub fn locked_memory(&self) -> usize {
+        2 * self.buffer_size * self.read_ahead
+    }
+
+    pub fn validate(&self) -> VanadiumResult<()> {
+        if self.batch_size == 0 || self.read_ahead == 0 || self.buffer_size == 0 {
+            return Err(VanadiumError::InvalidArgs(
+                "Batch size, read ahead and buffer size must not be zero".to_owned()
+            ));
+        }
+
+        Ok(())
+    }
+}
 
 macro_rules! make_bar {
     ($i:ident, $x:expr, $m:expr) => {
@@ -42,7 +95,11 @@ macro_rules! inc_bar {
 // #[cfg(feature = "tokio-uring-backend")]
 // pub mod tokio_uring;
 
+pub mod auto;
+pub mod bench;
 pub mod bip;
+pub mod calibrated;
+pub mod timed;
 
 #[cfg(feature = "glommio-backend")]
 pub mod glommio;
@@ -62,6 +119,34 @@ pub trait BasicImage<T> where
     fn means(&mut self) -> VanadiumResult<Array1<T>>;
     fn std_deviations(&mut self, means: &Array1<T>) -> VanadiumResult<Array1<T>>;
     fn covariance_matrix(&mut self, means: Option<&Array1<T>>, std_devs: Option<&Array1<T>>) -> VanadiumResult<Array2<T>>;
+    fn column_means(&mut self) -> VanadiumResult<Array2<T>>;
+    fn column_std_deviations(&mut self, column_means: &Array2<T>) -> VanadiumResult<Array2<T>>;
+    /// Statistics of every region with at least one pixel, in a single pass over the image.
+    fn region_statistics(
+        &mut self,
+        regions: &Regions,
+    ) -> VanadiumResult<BTreeMap<String, RegionSummary<T>>>;
+    /// Extremes, histograms and quantile sketches of every band, in a single pass.
+    fn band_distributions(
+        &mut self,
+        bins: usize,
+        sketch_size: usize,
+        seed: u64,
+    ) -> VanadiumResult<Vec<BandDistribution>>;
+    /// Every value of one band, in line-major order.
+    fn read_band(&mut self, band: usize) -> VanadiumResult<Vec<T>>;
+    fn write_destriped(
+        &mut self,
+        gains: &Array2<T>,
+        offsets: &Array2<T>,
+        out: &dyn AsRef<Path>,
+    ) -> VanadiumResult<()>;
+    fn write_column_corrected(
+        &mut self,
+        dark: &Array2<T>,
+        gain: Option<&Array2<T>>,
+        out: &dyn AsRef<Path>,
+    ) -> VanadiumResult<()>;
     fn write_transformed(
         &mut self,
         transform: &Array2<T>,
@@ -78,6 +163,13 @@ pub trait BasicImage<T> where
 
         Ok(e_vec.slice(s![..n_dims, ..]).mapv(|x| T::from_real(x.re())))
     }
+    fn subset(
+        &mut self,
+        rows: Option<(u64, u64)>,
+        cols: Option<(u64, u64)>,
+        bands: &[usize],
+        out: &dyn AsRef<Path>,
+    ) -> VanadiumResult<()>;
     fn crop(
         &mut self,
         rows: Option<(u64, u64)>,
@@ -88,4 +180,54 @@ pub trait BasicImage<T> where
         &mut self,
         colormap: &mut dyn FnMut(&mut Array2<T>) -> Array2<u8>
     ) -> VanadiumResult<RgbImage>;
-}
\ No newline at end of file
+    fn sample_pixels(&mut self, n: usize, seed: u64) -> VanadiumResult<(Vec<usize>, Array2<T>)>;
+    fn kmeans(&mut self, params: &KMeansParams<T>) -> VanadiumResult<Clustering<T>>;
+    fn write_classified(
+        &mut self,
+        centroids: &Array2<T>,
+        projection: Option<&Projection<T>>,
+        out: &dyn AsRef<Path>,
+    ) -> VanadiumResult<()>;
+    fn noise_covariance_matrix(&mut self) -> VanadiumResult<Array2<T>>;
+    fn endmembers_ppi(
+        &mut self,
+        projection: &Projection<T>,
+        n_endmembers: usize,
+        n_skewers: usize,
+        seed: u64,
+    ) -> VanadiumResult<SpectralLibrary<T>>;
+    fn endmembers_nfindr(
+        &mut self,
+        projection: &Projection<T>,
+        max_iterations: usize,
+        sample_size: usize,
+        seed: u64,
+    ) -> VanadiumResult<SpectralLibrary<T>>;
+    fn endmembers_vca(
+        &mut self,
+        projection: &Projection<T>,
+        seed: u64,
+    ) -> VanadiumResult<SpectralLibrary<T>>;
+    fn write_unmixed(&mut self, unmixer: &Unmixer<T>, out: &dyn AsRef<Path>) -> VanadiumResult<()>;
+    fn write_continuum_removed(
+        &mut self,
+        removal: &ContinuumRemoval,
+        out: &dyn AsRef<Path>,
+    ) -> VanadiumResult<()>;
+    fn write_spatially_filtered(
+        &mut self,
+        filter: &SpatialFilter<T>,
+        format: ImageFormat,
+        out: &dyn AsRef<Path>,
+    ) -> VanadiumResult<()>;
+    /// Mean spectrum over the window of `radius` lines and pixels around `(line, pixel)`,
+    /// clipped to the image.
+    fn spectrum(&mut self, line: usize, pixel: usize, radius: usize) -> VanadiumResult<Array1<T>>;
+    fn write_resampled(
+        &mut self,
+        resampler: &Resampler,
+        format: ImageFormat,
+        out: &dyn AsRef<Path>,
+    ) -> VanadiumResult<()>;
+    fn write_expressions(&mut self, exprs: &[Expr], out: &dyn AsRef<Path>) -> VanadiumResult<()>;
+}
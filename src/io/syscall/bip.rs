@@ -9,17 +9,18 @@ use ndarray::{Array2, ArrayViewMut2};
 use crate::error::{VanadiumError, VanadiumResult};
 use crate::headers::{Header, ImageFormat};
 use crate::image_formats::bip::BipDims;
-use crate::io::BATCH_SIZE;
+use crate::io::IoConfig;
 use crate::io::bip::Bip;
 use crate::util::{make_raw, make_raw_mut};
 
 pub struct SyscallBip<T> {
     file: File,
     dims: BipDims<T>,
+    config: IoConfig,
 }
 
 impl<T> SyscallBip<T> {
-    pub fn new<P>(header: Header<P>) -> io::Result<Self> where P: AsRef<Path> {
+    pub fn new<P>(header: Header<P>, config: IoConfig) -> io::Result<Self> where P: AsRef<Path> {
         assert_eq!(ImageFormat::Bip, header.format);
         let bip = BipDims {
             dims: header.dims,
@@ -31,6 +32,7 @@ impl<T> SyscallBip<T> {
         Ok(Self {
             file,
             dims: bip,
+            config,
         })
     }
 }
@@ -42,23 +44,24 @@ impl Bip<f32> for SyscallBip<f32> {
         self.file.seek(SeekFrom::Start(0)).map_err(|_| VanadiumError::IoError)?;
 
         let name = name.to_owned();
+        let batch_size = self.config.batch_size;
 
         make_bar!(pb, self.dims.num_pixels() as u64, name);
 
-        let mut buffer = vec![0.0; BATCH_SIZE * self.dims.pixel_length()];
+        let mut buffer = vec![0.0; batch_size * self.dims.pixel_length()];
 
         let mut seek = 0;
         let byte_len = buffer.len() * mem::size_of::<f32>();
 
         while self.file.read_f32_into::<LittleEndian>(&mut buffer).is_ok() {
-            let mut pixel = Array2::from_shape_vec((BATCH_SIZE, self.dims.pixel_length()), buffer)
+            let mut pixel = Array2::from_shape_vec((batch_size, self.dims.pixel_length()), buffer)
                 .unwrap();
 
             f(&mut pixel, &mut accumulator);
 
             buffer = pixel.into_raw_vec();
 
-            inc_bar!(pb, BATCH_SIZE as u64);
+            inc_bar!(pb, batch_size as u64);
 
             seek += byte_len;
         }
@@ -172,7 +175,7 @@ impl Bip<f32> for SyscallBip<f32> {
                 write_file.write_all(raw_write_buffer).unwrap();
             }
 
-            inc_bar!(pb, BATCH_SIZE as u64);
+            inc_bar!(pb, self.config.batch_size as u64);
 
             row += 1;
 
@@ -181,4 +184,19 @@ impl Bip<f32> for SyscallBip<f32> {
 
         Ok(())
     }
+
+    fn read_pixels(&mut self, line: usize, pixel: usize, n: usize) -> VanadiumResult<Array2<f32>> {
+        let offset = self.dims.pixel_offset(line, pixel, n)?;
+
+        let mut pixels = Array2::zeros((n, self.dims.pixel_length()));
+
+        self.file.seek(SeekFrom::Start(offset)).map_err(|_| VanadiumError::IoError)?;
+
+        unsafe {
+            let raw_buffer = make_raw_mut(pixels.as_slice_mut().unwrap());
+            self.file.read_exact(raw_buffer).map_err(|_| VanadiumError::IoError)?;
+        }
+
+        Ok(pixels)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,88 @@
+use std::cell::Cell;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use ndarray::{Array2, ArrayViewMut2};
+
+use crate::error::VanadiumResult;
+use crate::image_formats::bip::BipDims;
+use crate::io::bip::Bip;
+
+/// Total time spent processing batches, shared between a timed image and whoever reads it.
+pub type ComputeTimer = Rc<Cell<Duration>>;
+
+/// An image which records how long is spent processing each batch it reads.
+///
+/// Only the closures given to the image are timed, so the rest of a pass is time spent waiting
+/// on IO.
+pub struct TimedBip<C> {
+    inner: C,
+    compute: ComputeTimer,
+}
+
+impl<C> TimedBip<C> {
+    pub fn new(inner: C, compute: ComputeTimer) -> Self {
+        Self { inner, compute }
+    }
+}
+
+impl<C, T> Bip<T> for TimedBip<C> where C: Bip<T> {
+    fn fold_batched<F, A>(&mut self, name: &str, accumulator: A, mut f: F) -> VanadiumResult<A>
+        where F: FnMut(&mut Array2<T>, &mut A)
+    {
+        let compute = &self.compute;
+
+        self.inner.fold_batched(name, accumulator, |pixels, acc| {
+            let start = Instant::now();
+            f(pixels, acc);
+            compute.set(compute.get() + start.elapsed());
+        })
+    }
+
+    fn dims(&self) -> &BipDims<T> {
+        self.inner.dims()
+    }
+
+    fn map_and_write_batched<F>(
+        &mut self,
+        name: &str,
+        out: &dyn AsRef<Path>,
+        n_output_channels: usize,
+        mut f: F,
+    ) -> VanadiumResult<()>
+        where F: FnMut(&mut ArrayViewMut2<T>, &mut Array2<T>)
+    {
+        let compute = &self.compute;
+
+        self.inner.map_and_write_batched(name, out, n_output_channels, |pixels, write_array| {
+            let start = Instant::now();
+            f(pixels, write_array);
+            compute.set(compute.get() + start.elapsed());
+        })
+    }
+
+    fn crop_map<F>(
+        &mut self,
+        name: &str,
+        rows: Option<(u64, u64)>,
+        cols: Option<(u64, u64)>,
+        n_output_channels: usize,
+        out: &dyn AsRef<Path>,
+        mut f: F,
+    ) -> VanadiumResult<()>
+        where F: FnMut(&mut ArrayViewMut2<T>, &mut Array2<T>)
+    {
+        let compute = &self.compute;
+
+        self.inner.crop_map(name, rows, cols, n_output_channels, out, |pixels, write_array| {
+            let start = Instant::now();
+            f(pixels, write_array);
+            compute.set(compute.get() + start.elapsed());
+        })
+    }
+
+    fn read_pixels(&mut self, line: usize, pixel: usize, n: usize) -> VanadiumResult<Array2<T>> {
+        self.inner.read_pixels(line, pixel, n)
+    }
+}
@@ -10,25 +10,26 @@ use std::sync::Arc;
 use ndarray::{Array2, ArrayViewMut2};
 use num_traits::{Float, FromPrimitive};
 use tokio::fs::File;
-use tokio::io::{AsyncReadExt, AsyncSeek, AsyncSeekExt};
+use tokio::io::{AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWriteExt, BufWriter};
 use tokio::runtime;
 use tokio::sync::Mutex;
 
-use crate::error::VanadiumResult;
+use crate::error::{VanadiumError, VanadiumResult};
 use crate::headers::{Header, ImageFormat};
 use crate::image_formats::bip::BipDims;
-use crate::io::BATCH_SIZE;
+use crate::io::IoConfig;
 use crate::io::bip::Bip;
-use crate::util::make_raw_mut;
+use crate::util::{make_raw, make_raw_mut};
 
 pub struct TokioBip<T> {
     file: Arc<Mutex<File>>,
     rt: Arc<runtime::Runtime>,
     dims: BipDims<T>,
+    config: IoConfig,
 }
 
 impl<T> TokioBip<T> {
-    pub fn new<P>(header: Header<P>) -> io::Result<Self> where P: AsRef<Path> {
+    pub fn new<P>(header: Header<P>, config: IoConfig) -> io::Result<Self> where P: AsRef<Path> {
         assert_eq!(ImageFormat::Bip, header.format);
 
         let dims = BipDims {
@@ -45,6 +46,7 @@ impl<T> TokioBip<T> {
             file,
             rt: Arc::new(rt),
             dims,
+            config,
         })
     }
 }
@@ -64,14 +66,19 @@ impl<T> Bip<T> for TokioBip<T>
             let (tx, mut rx) = tokio::sync::mpsc::channel(4);
 
             let pl = self.dims.pixel_length();
+            let batch_size = self.config.batch_size;
 
             let make_buffer = move || {
                 Array2::from_shape_vec(
-                    (BATCH_SIZE, pl),
-                    vec![T::zero(); BATCH_SIZE * pl],
+                    (batch_size, pl),
+                    vec![T::zero(); batch_size * pl],
                 ).unwrap()
             };
 
+            // Reads elsewhere may have moved the cursor
+            self.file.lock().await.seek(SeekFrom::Start(0)).await
+                .map_err(|_| VanadiumError::IoError)?;
+
             let mut fi = self.file.clone();
 
             tokio::task::spawn(async move {
@@ -91,13 +98,13 @@ impl<T> Bip<T> for TokioBip<T>
                 }
             });
 
-            let byte_len = BATCH_SIZE * pl * mem::size_of::<f32>();
+            let byte_len = batch_size * pl * mem::size_of::<f32>();
             let mut seek = 0;
 
             while let Some(mut buffer) = rx.recv().await {
                 tokio::task::block_in_place(|| {
                     f(&mut buffer, &mut accumulator);
-                    inc_bar!(pb, BATCH_SIZE as u64);
+                    inc_bar!(pb, batch_size as u64);
                 });
 
                 seek += byte_len;
@@ -137,11 +144,95 @@ impl<T> Bip<T> for TokioBip<T>
         &self.dims
     }
 
-    fn map_and_write_batched<F>(&mut self, name: &str, out: &dyn AsRef<Path>, n_output_channels: usize, f: F) -> VanadiumResult<()> where F: FnMut(&mut ArrayViewMut2<T>, &mut Array2<T>) {
-        todo!()
+    fn map_and_write_batched<F>(
+        &mut self,
+        name: &str,
+        out: &dyn AsRef<Path>,
+        n_output_channels: usize,
+        f: F,
+    ) -> VanadiumResult<()>
+        where F: FnMut(&mut ArrayViewMut2<T>, &mut Array2<T>)
+    {
+        self.crop_map(name, None, None, n_output_channels, out, f)
+    }
+
+    fn crop_map<F>(
+        &mut self,
+        name: &str,
+        rows: Option<(u64, u64)>,
+        cols: Option<(u64, u64)>,
+        n_output_channels: usize,
+        out: &dyn AsRef<Path>,
+        mut f: F,
+    ) -> VanadiumResult<()>
+        where F: FnMut(&mut ArrayViewMut2<T>, &mut Array2<T>)
+    {
+        let name = name.to_owned();
+
+        let (start_col, end_col) = cols.unwrap_or((0, self.dims.dims.pixels as u64));
+        let (start_row, end_row) = rows.unwrap_or((0, self.dims.dims.lines as u64));
+
+        let row_length = (end_col - start_col) as usize;
+
+        let pixel_bytes = (self.dims.pixel_length() * mem::size_of::<T>()) as u64;
+        let line_bytes = self.dims.dims.pixels as u64 * pixel_bytes;
+
+        self.rt.clone().block_on(async {
+            make_bar!(pb, self.dims.num_pixels() as u64, name);
+
+            let write_file = File::create(out.as_ref()).await
+                .map_err(|_| VanadiumError::IoError)?;
+            let mut write_file = BufWriter::new(write_file);
+
+            let mut read_array = Array2::zeros((row_length, self.dims.pixel_length()));
+            let mut write_array = Array2::zeros((row_length, n_output_channels));
+
+            let mut file = self.file.lock().await;
+
+            for row in start_row..end_row {
+                let offset = row * line_bytes + start_col * pixel_bytes;
+                file.seek(SeekFrom::Start(offset)).await.map_err(|_| VanadiumError::IoError)?;
+
+                unsafe {
+                    let raw_read_buffer = make_raw_mut(read_array.as_slice_mut().unwrap());
+                    file.read_exact(raw_read_buffer).await.map_err(|_| VanadiumError::IoError)?;
+                }
+
+                tokio::task::block_in_place(|| {
+                    f(&mut read_array.view_mut(), &mut write_array);
+                });
+
+                unsafe {
+                    let raw_write_buffer = make_raw(write_array.as_slice().unwrap());
+                    write_file.write_all(raw_write_buffer).await
+                        .map_err(|_| VanadiumError::IoError)?;
+                }
+
+                inc_bar!(pb, row_length as u64);
+            }
+
+            write_file.flush().await.map_err(|_| VanadiumError::IoError)
+        })
     }
 
-    fn crop_map<F>(&mut self, name: &str, rows: Option<(u64, u64)>, cols: Option<(u64, u64)>, n_output_channels: usize, out: &dyn AsRef<Path>, f: F) -> VanadiumResult<()> where F: FnMut(&mut ArrayViewMut2<T>, &mut Array2<T>) {
-        todo!()
+    fn read_pixels(&mut self, line: usize, pixel: usize, n: usize) -> VanadiumResult<Array2<T>> {
+        let offset = self.dims.pixel_offset(line, pixel, n)?;
+
+        let mut pixels = Array2::zeros((n, self.dims.pixel_length()));
+
+        self.rt.block_on(async {
+            let mut file = self.file.lock().await;
+
+            file.seek(SeekFrom::Start(offset)).await.map_err(|_| VanadiumError::IoError)?;
+
+            unsafe {
+                let raw_buffer = make_raw_mut(pixels.as_slice_mut().unwrap());
+                file.read_exact(raw_buffer).await.map_err(|_| VanadiumError::IoError)?;
+            }
+
+            Ok(())
+        })?;
+
+        Ok(pixels)
     }
 }
\ No newline at end of file
@@ -8,22 +8,48 @@ extern crate openblas_src;
 extern crate serde;
 
 use std::error::Error;
-use std::fs::{File, OpenOptions};
+use std::fs::{self, File, OpenOptions};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
+use ndarray::{Array1, Array2};
 use structopt::StructOpt;
 
-use crate::error::VanadiumError;
-use crate::headers::{Header, ImageDims, ImageFormat};
-use crate::io::BasicImage;
+use crate::algorithms::continuum::ContinuumRemoval;
+use crate::algorithms::correction::{ColumnStatistics, flat_field_gain};
+use crate::algorithms::correlation::{correlation_matrix, CorrelationReport};
+use crate::algorithms::endmembers::{parse_coordinates, SpectralLibrary, stack_rows};
+use crate::algorithms::expr::{Expr, ExprContext, Statistic};
+use crate::algorithms::filter::savitzky_golay;
+use crate::algorithms::histogram::BandStatistics;
+use crate::algorithms::kmeans::{IsodataParams, KMeansParams};
+use crate::algorithms::pca::Projection;
+use crate::algorithms::resample::resampling_matrix;
+use crate::algorithms::resize::Resampler;
+use crate::algorithms::roi::{polygons_from_geojson, Regions};
+use crate::algorithms::spatial::{FilterKind, SpatialFilter};
+use crate::algorithms::synthetic::{CubeGenerator, SyntheticModel, SyntheticStatistics};
+use crate::algorithms::unmixing::Unmixer;
+use crate::error::{VanadiumError, VanadiumResult};
+use crate::headers::{BandInfo, Header, ImageDims, ImageFormat};
+use crate::io::{BasicImage, IoConfig};
+use crate::io::auto::select_backend;
+use crate::io::bench::{BenchResult, drop_cache, markdown_table, Trial};
+use crate::io::bip::Bip;
+use crate::io::calibrated::{CalibratedBip, Calibration};
+use crate::io::timed::{ComputeTimer, TimedBip};
 #[cfg(feature = "glommio")]
 use crate::io::bip::GlommioBip;
 #[cfg(feature = "syscall-backend")]
 use crate::io::bip::SyscallBip;
 #[cfg(feature = "memmap2")]
 use crate::io::mapped::bip::MappedBip;
-use crate::opt::{IoBackend, Operation, VanadiumArgs};
+use crate::opt::{EndmemberMethod, GridSize, IoBackend, Operation, Reduction, VanadiumArgs};
 use crate::io::tokio::bip::TokioBip;
 
+mod algorithms;
+
 #[cfg(not(tarpaulin_include))]
 mod headers;
 
@@ -44,17 +70,54 @@ mod tests;
 mod opt;
 
 #[cfg(not(tarpaulin_include))]
-fn get_image(backend: IoBackend, headers: Header<String>) -> Box<dyn BasicImage<f32>> {
+fn get_image(
+    backend: IoBackend,
+    config: &IoConfig,
+    headers: Header<String>,
+) -> VanadiumResult<Box<dyn BasicImage<f32>>> {
+    open_image(backend, config, headers, None)
+}
+
+/// Opens an image, adding the time spent processing its batches to `timer` if given.
+#[cfg(not(tarpaulin_include))]
+fn open_image(
+    backend: IoBackend,
+    config: &IoConfig,
+    headers: Header<String>,
+    timer: Option<ComputeTimer>,
+) -> VanadiumResult<Box<dyn BasicImage<f32>>> {
     assert_eq!(ImageFormat::Bip, headers.format);
+
+    let calibration = Calibration::from_bands(&headers.bands, headers.dims.channels);
+
+    // Glommio reports its own errors, such as a locked memory limit too low to start
+    let io_error = |_: std::io::Error| VanadiumError::IoError;
+
     match backend {
+        IoBackend::Auto => {
+            let backend = select_backend(headers.path.as_ref(), config);
+
+            open_image(backend, config, headers, timer)
+        }
         #[cfg(feature = "glommio-backend")]
-        IoBackend::Glommio => Box::new(GlommioBip::new(headers).unwrap()),
+        IoBackend::Glommio => {
+            Ok(wrapped(GlommioBip::new(headers, config.clone())?, calibration, timer))
+        }
         #[cfg(feature = "tokio-backend")]
-        IoBackend::Tokio => Box::new(TokioBip::new(headers).unwrap()),
+        IoBackend::Tokio => {
+            let image = TokioBip::new(headers, config.clone()).map_err(io_error)?;
+            Ok(wrapped(image, calibration, timer))
+        }
         #[cfg(feature = "syscall-backend")]
-        IoBackend::Syscall => Box::new(SyscallBip::new(headers).unwrap()),
+        IoBackend::Syscall => {
+            let image = SyscallBip::new(headers, config.clone()).map_err(io_error)?;
+            Ok(wrapped(image, calibration, timer))
+        }
         #[cfg(feature = "mapped-backend")]
-        IoBackend::Mapped => Box::new(MappedBip::new(headers).unwrap()),
+        IoBackend::Mapped => {
+            let image = MappedBip::new(headers, config.clone()).map_err(io_error)?;
+            Ok(wrapped(image, calibration, timer))
+        }
         #[cfg(not(all(
         feature = "mapped-backend",
         feature = "glommio-backend",
@@ -65,15 +128,152 @@ fn get_image(backend: IoBackend, headers: Header<String>) -> Box<dyn BasicImage<
     }
 }
 
+/// Wraps an image in the adapters it needs, timing it if asked to and calibrating it if needed.
+fn wrapped<C>(
+    image: C,
+    calibration: Option<Calibration<f32>>,
+    timer: Option<ComputeTimer>,
+) -> Box<dyn BasicImage<f32>>
+    where C: Bip<f32> + 'static
+{
+    match timer {
+        Some(timer) => calibrated(TimedBip::new(image, timer), calibration),
+        None => calibrated(image, calibration),
+    }
+}
+
+/// Wraps an image so that its pixels are calibrated as they are read, if needed.
+fn calibrated<C>(image: C, calibration: Option<Calibration<f32>>) -> Box<dyn BasicImage<f32>>
+    where C: Bip<f32> + 'static
+{
+    match calibration {
+        Some(calibration) => Box::new(CalibratedBip::new(image, calibration)),
+        None => Box::new(image),
+    }
+}
+
+fn column_statistics(image: &mut dyn BasicImage<f32>) -> VanadiumResult<ColumnStatistics<f32>> {
+    let means = image.column_means()?;
+    let std_devs = image.column_std_deviations(&means)?;
+
+    Ok(ColumnStatistics { means, std_devs })
+}
+
+/// Writes a spatially resampled image, with a header giving its new dims and map info.
+fn write_resampled(
+    backend: IoBackend,
+    config: &IoConfig,
+    header: Header<String>,
+    resampler: &Resampler,
+    format: ImageFormat,
+    output: PathBuf,
+    output_header: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let (lines, pixels) = resampler.size();
+    let (line_scale, pixel_scale) = resampler.scale();
+
+    let header_out = Header {
+        dims: ImageDims { lines, pixels, ..header.dims.clone() },
+        format,
+        path: output,
+        bands: header.bands.without_calibration(),
+        map_info: header.map_info.as_ref().map(|m| m.scaled(line_scale, pixel_scale)),
+    };
+
+    let mut image = get_image(backend, config, header)?;
+
+    image.write_resampled(resampler, format, &header_out.path)?;
+
+    if let Some(output_header) = output_header {
+        serde_json::to_writer(create_output(&output_header)?, &header_out)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(not(tarpaulin_include))]
+fn read_header(path: &Path) -> VanadiumResult<Header<String>> {
+    let file = File::open(path)
+        .map_err(|_| VanadiumError::FileNotFound(path.display().to_string()))?;
+
+    let header: Header<String> = serde_json::from_reader(file)
+        .map_err(|_| VanadiumError::InvalidHeader)?;
+
+    header.validate()?;
+
+    Ok(header)
+}
+
+/// Dimensions of an image after cropping to the given rows and columns.
+fn cropped_dims(
+    dims: &ImageDims,
+    rows: Option<(u64, u64)>,
+    cols: Option<(u64, u64)>,
+    channels: usize,
+) -> ImageDims {
+    let (start_row, end_row) = rows.unwrap_or((0, dims.lines as u64));
+    let (start_col, end_col) = cols.unwrap_or((0, dims.pixels as u64));
+
+    ImageDims {
+        channels,
+        lines: (end_row - start_row) as usize,
+        pixels: (end_col - start_col) as usize,
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+fn create_output(path: &Path) -> VanadiumResult<File> {
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .map_err(|_| VanadiumError::IoError)
+}
+
+#[cfg(not(tarpaulin_include))]
+fn reduce(
+    image: &mut dyn BasicImage<f32>,
+    reduction: Reduction,
+    n_dims: usize,
+    means: Option<PathBuf>,
+    covariances: Option<PathBuf>,
+) -> Result<Projection<f32>, Box<dyn Error>> {
+    let means = if let Some(m) = means {
+        serde_json::from_reader(File::open(m)?)?
+    } else {
+        image.means()?
+    };
+
+    let cov = if let Some(c) = covariances {
+        serde_json::from_reader(File::open(c)?)?
+    } else {
+        image.covariance_matrix(Some(&means), None)?
+    };
+
+    let projection = match reduction {
+        Reduction::Pca => Projection::pca(means, &cov, n_dims)?,
+        Reduction::Mnf => {
+            let noise = image.noise_covariance_matrix()?;
+            Projection::mnf(means, &cov, &noise, n_dims)?
+        }
+    };
+
+    Ok(projection)
+}
+
 #[cfg(not(tarpaulin_include))]
 fn main() -> Result<(), Box<dyn Error>> {
     let args: VanadiumArgs = VanadiumArgs::from_args();
 
+    let io = args.io_config();
+    io.validate()?;
+
     match args.op {
         Operation::Means { header, output } => {
             let header = serde_json::from_reader(File::open(header).unwrap()).map_err(|_|
                 VanadiumError::InvalidHeader)?;
-            let mut image = get_image(args.backend, header);
+            let mut image = get_image(args.backend, &io, header)?;
 
             let means = image.means()?;
 
@@ -89,7 +289,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         Operation::StandardDeviations { header, output, means } => {
             let header = serde_json::from_reader(File::open(header).unwrap()).map_err(|_|
                 VanadiumError::InvalidHeader)?;
-            let mut image = get_image(args.backend, header);
+            let mut image = get_image(args.backend, &io, header)?;
 
             let file = OpenOptions::new()
                 .write(true)
@@ -111,7 +311,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         Operation::Covariances { header, output, means, std_devs } => {
             let header = serde_json::from_reader(File::open(header).unwrap()).map_err(|_|
                 VanadiumError::InvalidHeader)?;
-            let mut image = get_image(args.backend, header);
+            let mut image = get_image(args.backend, &io, header)?;
 
             let means = means.map(|x| serde_json::from_reader(File::open(x).unwrap()).unwrap());
             let std_devs = std_devs.map(|x| serde_json::from_reader(File::open(x).unwrap()).unwrap());
@@ -143,21 +343,816 @@ fn main() -> Result<(), Box<dyn Error>> {
                 },
                 format: ImageFormat::Bip,
                 path: data_path,
+                bands: BandInfo::NONE,
+                map_info: None,
             };
 
             serde_json::to_writer(file, &header).unwrap();
         }
-        Operation::Crop { header, output, rows, cols } => {
+        Operation::Crop { header, output, output_header, rows, cols } => {
             let rows = rows.map(|x| (x[0], x[1]));
             let cols = cols.map(|x| (x[0], x[1]));
 
-            let header = serde_json::from_reader(File::open(header)?)?;
+            let header = read_header(&header)?;
+            let dims = header.dims.clone();
+            let map_info = header.map_info.clone();
+            let bands = header.bands.without_calibration();
 
-            let mut image = get_image(args.backend, header);
+            let mut image = get_image(args.backend, &io, header)?;
 
             image.crop(rows, cols, &output)?;
+
+            if let Some(output_header) = output_header {
+                let header = Header {
+                    dims: cropped_dims(&dims, rows, cols, dims.channels),
+                    format: ImageFormat::Bip,
+                    path: output,
+                    bands,
+                    map_info: map_info.map(|m| {
+                        m.cropped(rows.map_or(0, |r| r.0), cols.map_or(0, |c| c.0))
+                    }),
+                };
+
+                serde_json::to_writer(create_output(&output_header)?, &header)?;
+            }
+        }
+        Operation::Subset { header, output, output_header, rows, cols, bands, bbl } => {
+            let rows = rows.map(|x| (x[0], x[1]));
+            let cols = cols.map(|x| (x[0], x[1]));
+
+            let header = read_header(&header)?;
+            let dims = header.dims.clone();
+            let map_info = header.map_info.clone();
+
+            let mut bands = bands.map(|b| b.0).unwrap_or_else(|| (0..dims.channels).collect());
+
+            if let Some(&band) = bands.iter().find(|b| **b >= dims.channels) {
+                return Err(VanadiumError::InvalidArgs(format!("Band {} out of range", band)).into());
+            }
+
+            if bbl {
+                let good = header.bands.bbl.as_ref().ok_or_else(|| {
+                    VanadiumError::InvalidArgs("Header has no bad band list".to_owned())
+                })?;
+
+                bands.retain(|b| good.get(*b) != Some(&0));
+
+                if bands.is_empty() {
+                    return Err(VanadiumError::InvalidArgs(
+                        "Every selected band is in the bad band list".to_owned()
+                    ).into());
+                }
+            }
+
+            let band_info = header.bands.select(&bands).without_calibration();
+
+            let mut image = get_image(args.backend, &io, header)?;
+
+            image.subset(rows, cols, &bands, &output)?;
+
+            if let Some(output_header) = output_header {
+                let header = Header {
+                    dims: cropped_dims(&dims, rows, cols, bands.len()),
+                    format: ImageFormat::Bip,
+                    path: output,
+                    bands: band_info,
+                    map_info: map_info.map(|m| {
+                        m.cropped(rows.map_or(0, |r| r.0), cols.map_or(0, |c| c.0))
+                    }),
+                };
+
+                serde_json::to_writer(create_output(&output_header)?, &header)?;
+            }
+        }
+        Operation::Kmeans {
+            header, output, centroids, label_header, clusters, max_iterations, tolerance,
+            sample_size, seed, pca, min_cluster_size, split_std_dev, merge_distance, min_clusters,
+            max_clusters
+        } => {
+            if clusters == 0 || min_clusters == Some(0) {
+                return Err(VanadiumError::InvalidArgs(
+                    "K-means needs at least one cluster".to_owned()
+                ).into());
+            }
+
+            if sample_size == 0 {
+                return Err(VanadiumError::InvalidArgs(
+                    "K-means needs a sample of at least one pixel".to_owned()
+                ).into());
+            }
+
+            let header = read_header(&header)?;
+            let dims = header.dims.clone();
+            let map_info = header.map_info.clone();
+
+            let mut image = get_image(args.backend, &io, header)?;
+
+            let projection = if let Some(n_dims) = pca {
+                let means = image.means()?;
+                let cov = image.covariance_matrix(Some(&means), None)?;
+
+                Some(Projection::pca(means, &cov, n_dims)?)
+            } else {
+                None
+            };
+
+            let params = KMeansParams {
+                clusters,
+                max_iterations,
+                tolerance,
+                sample_size,
+                seed,
+                projection,
+                isodata: IsodataParams {
+                    min_cluster_size,
+                    split_std_dev,
+                    merge_distance,
+                    min_clusters: min_clusters.unwrap_or((clusters / 2).max(1)),
+                    max_clusters: max_clusters.unwrap_or(clusters * 2),
+                },
+            };
+
+            let clustering = image.kmeans(&params)?;
+
+            image.write_classified(&clustering.centroids, params.projection.as_ref(), &output)?;
+
+            serde_json::to_writer(create_output(&centroids)?, &clustering)?;
+
+            if let Some(label_header) = label_header {
+                let header = Header {
+                    dims: ImageDims { channels: 1, ..dims },
+                    format: ImageFormat::Bip,
+                    path: output,
+                    bands: BandInfo::NONE,
+                    map_info,
+                };
+
+                serde_json::to_writer(create_output(&label_header)?, &header)?;
+            }
+        }
+        Operation::Endmembers {
+            header, output, csv, method, endmembers, reduction, dims, skewers, max_iterations,
+            sample_size, seed, means, covariances
+        } => {
+            if endmembers == 0 {
+                return Err(VanadiumError::InvalidArgs(
+                    "At least one endmember must be extracted".to_owned()
+                ).into());
+            }
+
+            let n_dims = match method {
+                EndmemberMethod::Ppi => dims.unwrap_or(endmembers),
+                EndmemberMethod::Nfindr => endmembers.checked_sub(1).filter(|x| *x > 0)
+                    .ok_or_else(|| VanadiumError::InvalidArgs(
+                        "N-FINDR needs at least two endmembers".to_owned()
+                    ))?,
+                EndmemberMethod::Vca => endmembers,
+            };
+
+            if n_dims == 0 {
+                return Err(VanadiumError::InvalidArgs(
+                    "PPI needs at least one reduced dimension".to_owned()
+                ).into());
+            }
+
+            let header = read_header(&header)?;
+
+            // The initial simplex is drawn from the sample, which has one vertex per endmember
+            let n_pixels = header.dims.lines * header.dims.pixels;
+
+            if method == EndmemberMethod::Nfindr && sample_size.min(n_pixels) < endmembers {
+                return Err(VanadiumError::InvalidArgs(format!(
+                    "N-FINDR needs a sample of at least {} pixels, one per endmember",
+                    endmembers
+                )).into());
+            }
+
+            let wavelengths = header.bands.wavelength_nm();
+            let mut image = get_image(args.backend, &io, header)?;
+
+            let projection = reduce(image.as_mut(), reduction, n_dims, means, covariances)?;
+
+            let library: SpectralLibrary<f32> = match method {
+                EndmemberMethod::Ppi => image.endmembers_ppi(&projection, endmembers, skewers, seed)?,
+                EndmemberMethod::Nfindr => {
+                    image.endmembers_nfindr(&projection, max_iterations, sample_size, seed)?
+                }
+                EndmemberMethod::Vca => image.endmembers_vca(&projection, seed)?,
+            };
+
+            let library = library.with_wavelengths(wavelengths);
+
+            serde_json::to_writer(create_output(&output)?, &library)?;
+
+            if let Some(csv) = csv {
+                library.write_csv(BufWriter::new(create_output(&csv)?))?;
+            }
+        }
+        Operation::Unmix { header, output, output_header, library, method } => {
+            let header = read_header(&header)?;
+            let dims = header.dims.clone();
+            let map_info = header.map_info.clone();
+
+            let library: SpectralLibrary<f32> = serde_json::from_reader(File::open(library)?)?;
+
+            if library.spectra.ncols() != dims.channels {
+                return Err(VanadiumError::InvalidArgs(
+                    "Library spectra do not match the image's channels".to_owned()
+                ).into());
+            }
+
+            let unmixer = Unmixer::new(method, library.spectra)?;
+
+            let names = library.names.into_iter().chain(std::iter::once("rmse".to_owned()));
+            let band_info = BandInfo::named(names);
+
+            let mut image = get_image(args.backend, &io, header)?;
+
+            image.write_unmixed(&unmixer, &output)?;
+
+            if let Some(output_header) = output_header {
+                let header = Header {
+                    dims: ImageDims { channels: unmixer.n_endmembers() + 1, ..dims },
+                    format: ImageFormat::Bip,
+                    path: output,
+                    bands: band_info,
+                    map_info,
+                };
+
+                serde_json::to_writer(create_output(&output_header)?, &header)?;
+            }
+        }
+        Operation::Index { header, output, output_header, exprs } => {
+            let header = read_header(&header)?;
+            let dims = header.dims.clone();
+            let map_info = header.map_info.clone();
+
+            let wavelengths = header.bands.wavelength_nm();
+
+            let ctx = ExprContext {
+                channels: dims.channels,
+                wavelengths: wavelengths.as_deref(),
+                ..ExprContext::default()
+            };
+
+            let band_info = BandInfo::named(exprs.iter().cloned());
+
+            let exprs = exprs.iter()
+                .map(|e| Expr::parse(e)?.resolve(&ctx))
+                .collect::<VanadiumResult<Vec<_>>>()?;
+
+            let mut image = get_image(args.backend, &io, header)?;
+
+            image.write_expressions(&exprs, &output)?;
+
+            if let Some(output_header) = output_header {
+                let header = Header {
+                    dims: ImageDims { channels: exprs.len(), ..dims },
+                    format: ImageFormat::Bip,
+                    path: output,
+                    bands: band_info,
+                    map_info,
+                };
+
+                serde_json::to_writer(create_output(&output_header)?, &header)?;
+            }
+        }
+        Operation::BandMath { header, output, output_header, exprs, means, std_devs } => {
+            let header = read_header(&header)?;
+            let dims = header.dims.clone();
+            let map_info = header.map_info.clone();
+            let wavelengths = header.bands.wavelength_nm();
+
+            let named = exprs.iter()
+                .map(|e| Expr::parse_named(e))
+                .collect::<VanadiumResult<Vec<_>>>()?;
+
+            for (i, (name, _)) in named.iter().enumerate() {
+                if named[..i].iter().any(|(other, _)| other == name) {
+                    return Err(VanadiumError::InvalidExpression(
+                        format!("output {} is defined more than once", name)
+                    ).into());
+                }
+            }
+
+            let uses = |stat| named.iter().any(|(_, e)| e.uses(stat));
+
+            let mut image = get_image(args.backend, &io, header)?;
+
+            let needs_means = uses(Statistic::Mean) || uses(Statistic::StdDev);
+
+            let means: Option<Array1<f32>> = match means {
+                Some(path) => Some(serde_json::from_reader(File::open(path)?)?),
+                None if needs_means => Some(image.means()?),
+                None => None,
+            };
+
+            let std_devs: Option<Array1<f32>> = match std_devs {
+                Some(path) => Some(serde_json::from_reader(File::open(path)?)?),
+                None if uses(Statistic::StdDev) => Some(image.std_deviations(means.as_ref().unwrap())?),
+                None => None,
+            };
+
+            let to_f64 = |x: Option<Array1<f32>>| x.map(|x| x.iter().map(|v| *v as f64).collect::<Vec<_>>());
+            let means = to_f64(means);
+            let std_devs = to_f64(std_devs);
+
+            for (stats, name) in [(&means, "means"), (&std_devs, "std devs")] {
+                if stats.as_ref().is_some_and(|x| x.len() != dims.channels) {
+                    return Err(VanadiumError::InvalidArgs(
+                        format!("Number of {} does not match the image's channels", name)
+                    ).into());
+                }
+            }
+
+            let ctx = ExprContext {
+                channels: dims.channels,
+                wavelengths: wavelengths.as_deref(),
+                means: means.as_deref(),
+                std_devs: std_devs.as_deref(),
+            };
+
+            let band_info = BandInfo::named(named.iter().map(|(name, _)| name.clone()));
+
+            let exprs = named.into_iter()
+                .map(|(_, e)| e.resolve(&ctx))
+                .collect::<VanadiumResult<Vec<_>>>()?;
+
+            image.write_expressions(&exprs, &output)?;
+
+            if let Some(output_header) = output_header {
+                let header = Header {
+                    dims: ImageDims { channels: exprs.len(), ..dims },
+                    format: ImageFormat::Bip,
+                    path: output,
+                    bands: band_info,
+                    map_info,
+                };
+
+                serde_json::to_writer(create_output(&output_header)?, &header)?;
+            }
+        }
+        Operation::Resample { header, output, output_header, target, method } => {
+            let header = read_header(&header)?;
+            let dims = header.dims.clone();
+            let map_info = header.map_info.clone();
+
+            let target: BandInfo = serde_json::from_reader(File::open(target)?)
+                .map_err(|_| VanadiumError::InvalidHeader)?;
+
+            let target_wavelengths = target.wavelength_nm().ok_or_else(|| {
+                VanadiumError::InvalidMetadata("target has no wavelengths".to_owned())
+            })?;
+
+            target.validate(target_wavelengths.len())?;
+
+            let source_wavelengths = header.bands.wavelength_nm().ok_or_else(|| {
+                VanadiumError::InvalidMetadata("header has no wavelengths".to_owned())
+            })?;
+
+            let transform = resampling_matrix(
+                method,
+                &source_wavelengths,
+                &target_wavelengths,
+                target.fwhm_nm().as_deref(),
+            )?.mapv(|x| x as f32);
+
+            let mut image = get_image(args.backend, &io, header)?;
+
+            image.write_transformed(&transform, &output, None, None)?;
+
+            if let Some(output_header) = output_header {
+                let header = Header {
+                    dims: ImageDims { channels: target_wavelengths.len(), ..dims },
+                    format: ImageFormat::Bip,
+                    path: output,
+                    bands: target,
+                    map_info,
+                };
+
+                serde_json::to_writer(create_output(&output_header)?, &header)?;
+            }
+        }
+        Operation::Calibrate { header, output, output_header } => {
+            let header = read_header(&header)?;
+            let dims = header.dims.clone();
+            let map_info = header.map_info.clone();
+
+            if !header.bands.is_calibrated() {
+                return Err(VanadiumError::InvalidMetadata(
+                    "header declares no gains, offsets or reflectance scale factor".to_owned()
+                ).into());
+            }
+
+            let bands = header.bands.without_calibration();
+
+            let mut image = get_image(args.backend, &io, header)?;
+
+            image.crop(None, None, &output)?;
+
+            if let Some(output_header) = output_header {
+                let header = Header {
+                    dims,
+                    format: ImageFormat::Bip,
+                    path: output,
+                    bands,
+                    map_info,
+                };
+
+                serde_json::to_writer(create_output(&output_header)?, &header)?;
+            }
+        }
+        Operation::Correct { header, output, output_header, dark, flat } => {
+            let header = read_header(&header)?;
+            let dims = header.dims.clone();
+            let map_info = header.map_info.clone();
+            let bands = header.bands.without_calibration();
+            let backend = args.backend;
+
+            let column_means = |path: &Path| -> Result<Array2<f32>, Box<dyn Error>> {
+                let reference = read_header(path)?;
+
+                if reference.dims.channels != dims.channels || reference.dims.pixels != dims.pixels {
+                    return Err(VanadiumError::InvalidArgs(format!(
+                        "{} does not match the image's channels and pixels",
+                        path.display()
+                    )).into());
+                }
+
+                Ok(get_image(backend, &io, reference)?.column_means()?)
+            };
+
+            let dark = column_means(&dark)?;
+
+            let gain = match flat {
+                Some(flat) => Some(flat_field_gain(&dark, &column_means(&flat)?)),
+                None => None,
+            };
+
+            let mut image = get_image(args.backend, &io, header)?;
+
+            image.write_column_corrected(&dark, gain.as_ref(), &output)?;
+
+            if let Some(output_header) = output_header {
+                let header = Header {
+                    dims,
+                    format: ImageFormat::Bip,
+                    path: output,
+                    bands,
+                    map_info,
+                };
+
+                serde_json::to_writer(create_output(&output_header)?, &header)?;
+            }
+        }
+        Operation::ColumnStatistics { header, output } => {
+            let header = read_header(&header)?;
+
+            let mut image = get_image(args.backend, &io, header)?;
+
+            let stats = column_statistics(image.as_mut())?;
+
+            serde_json::to_writer(create_output(&output)?, &stats)?;
+        }
+        Operation::Destripe { header, output, output_header, stats } => {
+            let header = read_header(&header)?;
+            let dims = header.dims.clone();
+            let map_info = header.map_info.clone();
+            let bands = header.bands.without_calibration();
+
+            let mut image = get_image(args.backend, &io, header)?;
+
+            let stats: ColumnStatistics<f32> = match stats {
+                Some(path) => serde_json::from_reader(File::open(path)?)?,
+                None => column_statistics(image.as_mut())?,
+            };
+
+            if stats.means.dim() != (dims.pixels, dims.channels) {
+                return Err(VanadiumError::InvalidArgs(
+                    "Column statistics do not match the image".to_owned()
+                ).into());
+            }
+
+            let (gains, offsets) = stats.destripe_coefficients();
+
+            image.write_destriped(&gains, &offsets, &output)?;
+
+            if let Some(output_header) = output_header {
+                let header = Header {
+                    dims,
+                    format: ImageFormat::Bip,
+                    path: output,
+                    bands,
+                    map_info,
+                };
+
+                serde_json::to_writer(create_output(&output_header)?, &header)?;
+            }
+        }
+        Operation::SpectralFilter { header, output, output_header, window, order, derivative } => {
+            let header = read_header(&header)?;
+            let dims = header.dims.clone();
+            let map_info = header.map_info.clone();
+            let bands = header.bands.without_calibration();
+
+            let positions = header.bands.wavelength_nm()
+                .unwrap_or_else(|| (0..dims.channels).map(|i| i as f64).collect());
+
+            let transform = savitzky_golay(&positions, window, order, derivative)?
+                .mapv(|x| x as f32);
+
+            let mut image = get_image(args.backend, &io, header)?;
+
+            image.write_transformed(&transform, &output, None, None)?;
+
+            if let Some(output_header) = output_header {
+                let header = Header {
+                    dims,
+                    format: ImageFormat::Bip,
+                    path: output,
+                    bands,
+                    map_info,
+                };
+
+                serde_json::to_writer(create_output(&output_header)?, &header)?;
+            }
+        }
+        Operation::ContinuumRemove { header, output, output_header, window, feature } => {
+            let header = read_header(&header)?;
+            let dims = header.dims.clone();
+            let map_info = header.map_info.clone();
+
+            let wavelengths = header.bands.wavelength_nm().ok_or_else(|| {
+                VanadiumError::InvalidMetadata("header has no wavelengths".to_owned())
+            })?;
+
+            let removal = ContinuumRemoval::new(
+                &wavelengths,
+                window.map(|x| (x[0], x[1])),
+                feature.as_ref().map(|x| (x[0], x[1])),
+            )?;
+
+            let mut bands = header.bands.select(removal.bands()).without_calibration();
+
+            if feature.is_some() {
+                // the extra outputs have no wavelength, so the bands are identified by name
+                let mut names: Vec<String> = match bands.band_names {
+                    Some(names) => names,
+                    None => removal.bands().iter().map(|b| format!("{} nm", wavelengths[*b])).collect(),
+                };
+
+                names.push("band_depth".to_owned());
+                names.push("band_center".to_owned());
+
+                bands = BandInfo::named(names);
+            }
+
+            let mut image = get_image(args.backend, &io, header)?;
+
+            image.write_continuum_removed(&removal, &output)?;
+
+            if let Some(output_header) = output_header {
+                let header = Header {
+                    dims: ImageDims { channels: removal.n_outputs(), ..dims },
+                    format: ImageFormat::Bip,
+                    path: output,
+                    bands,
+                    map_info,
+                };
+
+                serde_json::to_writer(create_output(&output_header)?, &header)?;
+            }
+        }
+        Operation::SpatialFilter {
+            header, output, output_header, kernel, size, sigma, kernel_file, format
+        } => {
+            let header = read_header(&header)?;
+            let dims = header.dims.clone();
+            let map_info = header.map_info.clone();
+            let bands = header.bands.without_calibration();
+
+            let filter = match (kernel, kernel_file) {
+                (_, Some(path)) => {
+                    let rows: Vec<Vec<f32>> = serde_json::from_reader(File::open(path)?)?;
+                    SpatialFilter::from_kernel(&rows)?
+                }
+                (Some(FilterKind::Gaussian), None) => match sigma {
+                    Some(sigma) => SpatialFilter::gaussian(size, sigma)?,
+                    None => SpatialFilter::new(FilterKind::Gaussian, size)?,
+                },
+                (Some(kind), None) => SpatialFilter::new(kind, size)?,
+                (None, None) => return Err(VanadiumError::InvalidArgs(
+                    "Either a kernel or a kernel file is needed".to_owned()
+                ).into()),
+            };
+
+            let mut image = get_image(args.backend, &io, header)?;
+
+            image.write_spatially_filtered(&filter, format, &output)?;
+
+            if let Some(output_header) = output_header {
+                let header = Header {
+                    dims,
+                    format,
+                    path: output,
+                    bands,
+                    map_info,
+                };
+
+                serde_json::to_writer(create_output(&output_header)?, &header)?;
+            }
+        }
+        Operation::Bin { header, output, output_header, factor, format } => {
+            let header = read_header(&header)?;
+            let dims = header.dims.clone();
+
+            let resampler = Resampler::bin(dims.lines, dims.pixels, (factor.0, factor.1))?;
+
+            write_resampled(args.backend, &io, header, &resampler, format, output, output_header)?;
+        }
+        Operation::Resize { header, output, output_header, size, method, format } => {
+            let header = read_header(&header)?;
+            let dims = header.dims.clone();
+
+            let resampler = Resampler::resize(method, dims.lines, dims.pixels, (size.0, size.1))?;
+
+            write_resampled(args.backend, &io, header, &resampler, format, output, output_header)?;
+        }
+        Operation::Spectrum { header, output, csv, coordinates, radius } => {
+            let header = read_header(&header)?;
+            let wavelengths = header.bands.wavelength_nm();
+
+            let coordinates = parse_coordinates(&fs::read_to_string(coordinates)?)?;
+
+            let mut image = get_image(args.backend, &io, header)?;
+
+            let spectra = coordinates.iter()
+                .map(|(line, pixel)| image.spectrum(*line, *pixel, radius))
+                .collect::<VanadiumResult<Vec<_>>>()?;
+
+            let library = SpectralLibrary {
+                names: coordinates.iter().map(|(l, p)| format!("pixel_{}_{}", l, p)).collect(),
+                coordinates,
+                spectra: stack_rows(spectra.iter()),
+                wavelengths,
+            };
+
+            serde_json::to_writer(create_output(&output)?, &library)?;
+
+            if let Some(csv) = csv {
+                library.write_csv(BufWriter::new(create_output(&csv)?))?;
+            }
+        }
+        Operation::RoiStatistics { header, output, polygons, labels, ignore } => {
+            let header = read_header(&header)?;
+            let dims = header.dims.clone();
+
+            let regions = match (polygons, labels) {
+                (Some(polygons), _) => Regions::Polygons(
+                    polygons_from_geojson(&fs::read_to_string(polygons)?)?
+                ),
+                (None, Some(labels)) => {
+                    let labels = read_header(&labels)?;
+
+                    if labels.dims.lines != dims.lines || labels.dims.pixels != dims.pixels {
+                        return Err(VanadiumError::InvalidArgs(
+                            "Label raster does not match the image's lines and pixels".to_owned()
+                        ).into());
+                    }
+
+                    let labels = get_image(args.backend, &io, labels)?.read_band(0)?;
+
+                    Regions::Labels { labels, ignore }
+                }
+                (None, None) => unreachable!(),
+            };
+
+            let mut image = get_image(args.backend, &io, header)?;
+
+            let statistics = image.region_statistics(&regions)?;
+
+            serde_json::to_writer(create_output(&output)?, &statistics)?;
+        }
+        Operation::BandStatistics { header, output, bins, quantiles, sketch_size, seed } => {
+            if bins == 0 {
+                return Err(VanadiumError::InvalidArgs("Need at least one bin".to_owned()).into());
+            }
+
+            if quantiles.iter().any(|q| !(0.0..=1.0).contains(q)) {
+                return Err(VanadiumError::InvalidArgs(
+                    "Quantiles must be between zero and one".to_owned()
+                ).into());
+            }
+
+            let header = read_header(&header)?;
+            let mut image = get_image(args.backend, &io, header)?;
+
+            let distributions = image.band_distributions(bins, sketch_size, seed)?;
+
+            let statistics = BandStatistics {
+                quantiles: quantiles.clone(),
+                bands: distributions.iter().map(|d| d.summary(&quantiles)).collect(),
+            };
+
+            serde_json::to_writer(create_output(&output)?, &statistics)?;
+        }
+        Operation::Correlations { header, output, means, report, top, threshold } => {
+            let header = read_header(&header)?;
+            let mut image = get_image(args.backend, &io, header)?;
+
+            let means = match means {
+                Some(m) => serde_json::from_reader(File::open(m)?)?,
+                None => image.means()?,
+            };
+
+            let covariances = image.covariance_matrix(Some(&means), None)?;
+            let correlations = correlation_matrix(&covariances);
+
+            serde_json::to_writer(create_output(&output)?, &correlations)?;
+
+            if let Some(report) = report {
+                let correlation_report = CorrelationReport::new(&correlations, top, threshold);
+
+                serde_json::to_writer(create_output(&report)?, &correlation_report)?;
+            }
+        }
+        Operation::Bench { header, output, table, ops, backends, trials, drop_caches } => {
+            if trials == 0 {
+                return Err(VanadiumError::InvalidArgs("Trials must not be zero".to_owned()).into());
+            }
+
+            let header = read_header(&header)?;
+            let dims = &header.dims;
+            let values = dims.lines * dims.pixels * dims.channels;
+            let bytes = (values * std::mem::size_of::<f32>()) as u64;
+
+            let mut results = Vec::new();
+
+            for backend in backends.0 {
+                for op in &ops {
+                    let mut timings = Vec::with_capacity(trials);
+
+                    for _ in 0..trials {
+                        if drop_caches {
+                            drop_cache(Path::new(&header.path))?;
+                        }
+
+                        let timer = ComputeTimer::default();
+                        let image_header = header.clone();
+                        let mut image = open_image(backend, &io, image_header, Some(timer.clone()))?;
+
+                        let start = Instant::now();
+                        op.run(image.as_mut())?;
+
+                        timings.push(Trial { wall: start.elapsed(), compute: timer.get() });
+                    }
+
+                    results.push(BenchResult::new(backend, *op, bytes * op.passes(), &timings));
+                }
+            }
+
+            serde_json::to_writer(create_output(&output)?, &results)?;
+
+            let markdown = markdown_table(&results);
+
+            match table {
+                Some(table) => fs::write(table, markdown)?,
+                None => print!("{}", markdown),
+            }
+        }
+        Operation::Generate {
+            output, output_header, size, model, channels, statistics, format, seed
+        } => {
+            let model = match model {
+                Some(m) => serde_json::from_reader(File::open(m)?)?,
+                None => SyntheticModel::default_gaussian(channels),
+            };
+
+            let GridSize(lines, pixels) = size;
+
+            let mut generator = CubeGenerator::new(&model, seed)?;
+            generator.write_cube::<f32>(format, &output, lines, pixels)?;
+
+            let header = Header {
+                dims: ImageDims { channels: model.channels(), lines, pixels },
+                format,
+                path: output,
+                bands: BandInfo::NONE,
+                map_info: None,
+            };
+
+            serde_json::to_writer(create_output(&output_header)?, &header)?;
+
+            if let Some(statistics) = statistics {
+                let expected = SyntheticStatistics {
+                    means: model.means(),
+                    covariance: model.covariance(),
+                };
+
+                serde_json::to_writer(create_output(&statistics)?, &expected)?;
+            }
         }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -2,10 +2,19 @@ use std::path::PathBuf;
 use std::str::FromStr;
 
 use structopt::StructOpt;
+use crate::algorithms::resample::ResampleMethod;
+use crate::algorithms::resize::ResizeMethod;
+use crate::algorithms::spatial::FilterKind;
+use crate::algorithms::unmixing::UnmixingMethod;
 use crate::error::VanadiumError;
+use crate::headers::ImageFormat;
+use crate::io::IoConfig;
+use crate::io::auto::compiled_backends;
+use crate::io::bench::BenchOp;
 
 #[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub enum IoBackend {
+    Auto,
     Glommio,
     Syscall,
     Mapped,
@@ -17,6 +26,7 @@ impl FromStr for IoBackend {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
+            "auto" => Ok(IoBackend::Auto),
             "glommio" => Ok(IoBackend::Glommio),
             "syscall" => Ok(IoBackend::Syscall),
             "mmap" => Ok(IoBackend::Mapped),
@@ -26,18 +36,151 @@ impl FromStr for IoBackend {
     }
 }
 
+#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum EndmemberMethod {
+    Ppi,
+    Nfindr,
+    Vca,
+}
+
+impl FromStr for EndmemberMethod {
+    type Err = VanadiumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ppi" => Ok(EndmemberMethod::Ppi),
+            "nfindr" | "n-findr" => Ok(EndmemberMethod::Nfindr),
+            "vca" => Ok(EndmemberMethod::Vca),
+            _ => Err(VanadiumError::InvalidArgs("Invalid endmember extraction method".to_owned()))
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum Reduction {
+    Pca,
+    Mnf,
+}
+
+impl FromStr for Reduction {
+    type Err = VanadiumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pca" => Ok(Reduction::Pca),
+            "mnf" => Ok(Reduction::Mnf),
+            _ => Err(VanadiumError::InvalidArgs("Invalid dimensionality reduction".to_owned()))
+        }
+    }
+}
+
+/// A list of band indices, written as comma separated inclusive ranges such as `0-100,120-150`.
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct BandList(pub Vec<usize>);
+
+impl FromStr for BandList {
+    type Err = VanadiumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || VanadiumError::InvalidArgs(format!("Invalid band list: {}", s));
+
+        let mut bands = Vec::new();
+
+        for part in s.split(',') {
+            let mut ends = part.splitn(2, '-').map(|x| x.trim().parse::<usize>());
+
+            let start = ends.next().unwrap().map_err(|_| invalid())?;
+            let end = ends.next().unwrap_or(Ok(start)).map_err(|_| invalid())?;
+
+            if end < start {
+                return Err(invalid());
+            }
+
+            bands.extend(start..=end);
+        }
+
+        Ok(BandList(bands))
+    }
+}
+
+/// A list of IO backends, written as comma separated names, or `all` for every compiled backend.
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct BackendList(pub Vec<IoBackend>);
+
+impl FromStr for BackendList {
+    type Err = VanadiumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "all" {
+            return Ok(BackendList(compiled_backends()));
+        }
+
+        s.split(',')
+            .map(|name| name.trim().parse())
+            .collect::<Result<_, _>>()
+            .map(BackendList)
+    }
+}
+
+/// A number of lines by a number of pixels, written as `LINESxPIXELS`.
+#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct GridSize(pub usize, pub usize);
+
+impl FromStr for GridSize {
+    type Err = VanadiumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || VanadiumError::InvalidArgs(format!("Expected LINESxPIXELS, got {}", s));
+
+        let mut parts = s.splitn(2, 'x').map(|x| x.trim().parse::<usize>());
+
+        let lines = parts.next().unwrap().map_err(|_| invalid())?;
+        let pixels = parts.next().ok_or_else(invalid)?.map_err(|_| invalid())?;
+
+        Ok(GridSize(lines, pixels))
+    }
+}
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Vanadium", about = "A tool for fast hyperspectral image processing.")]
 pub struct VanadiumArgs {
-    /// Specifies the IO backend to use. Currently, glommio (io-uring) and syscall are supported.
-    #[structopt(long)]
+    /// Specifies the IO backend to use: auto, glommio (io-uring), syscall, mmap or tokio.
+    ///
+    /// auto picks a backend for each image from its size, the memory available to cache it and
+    /// whether io_uring and direct io can be used, and logs its choice.
+    #[structopt(long, default_value = "auto")]
     pub backend: IoBackend,
+    /// Number of pixels read and processed at once.
+    #[structopt(long, env = "VANADIUM_BATCH_SIZE", default_value = "1024")]
+    pub batch_size: usize,
+    /// Number of buffers the glommio backend reads ahead of, and writes behind, processing.
+    #[structopt(long, env = "VANADIUM_READ_AHEAD", default_value = "16")]
+    pub read_ahead: usize,
+    /// Size in bytes of each glommio buffer.
+    ///
+    /// Glommio locks `2 * buffer-size * read-ahead` bytes of memory, clamped to the locked
+    /// memory limit (see `ulimit -l`).
+    #[structopt(long, env = "VANADIUM_BUFFER_SIZE", default_value = "524288")]
+    pub buffer_size: usize,
+    /// CPU to pin the glommio executor to.
+    #[structopt(long, env = "VANADIUM_PIN_CPU", default_value = "1")]
+    pub pin_cpu: usize,
     /// Subcommand to invoke.
     #[structopt(subcommand)]
     pub op: Operation,
 }
 
+impl VanadiumArgs {
+    pub fn io_config(&self) -> IoConfig {
+        IoConfig {
+            batch_size: self.batch_size,
+            read_ahead: self.read_ahead,
+            buffer_size: self.buffer_size,
+            pin_cpu: self.pin_cpu,
+        }
+    }
+}
+
 #[derive(Debug, StructOpt)]
 pub enum Operation {
     /// Calculate the spectral means for all bands.
@@ -124,6 +267,33 @@ pub enum Operation {
         /// Output path for the new data file.
         #[structopt(short, long)]
         output: PathBuf,
+        /// Optional output path for a header describing the new data file.
+        #[structopt(long)]
+        output_header: Option<PathBuf>,
+        /// Optional range of rows to be kept.
+        ///
+        /// Defaults to keep all.
+        #[structopt(short, long, number_of_values = 2)]
+        rows: Option<Vec<u64>>,
+        /// Optional range of columns to be kept.
+        ///
+        /// Defaults to keep all.
+        #[structopt(short, long, number_of_values = 2)]
+        cols: Option<Vec<u64>>,
+    },
+    /// Crop an image and select a subset of its bands in a single pass.
+    Subset {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output path for the new data file.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional output path for a header describing the new data file.
+        #[structopt(long)]
+        output_header: Option<PathBuf>,
         /// Optional range of rows to be kept.
         ///
         /// Defaults to keep all.
@@ -134,5 +304,595 @@ pub enum Operation {
         /// Defaults to keep all.
         #[structopt(short, long, number_of_values = 2)]
         cols: Option<Vec<u64>>,
+        /// Optional bands to keep, in output order, such as `0-100,120-150`.
+        ///
+        /// Ranges are inclusive.
+        /// Defaults to keep all.
+        #[structopt(short, long)]
+        bands: Option<BandList>,
+        /// Drop the bands marked bad in the header's bad band list.
+        #[structopt(long)]
+        bbl: bool,
+    },
+    /// Classify an image into clusters with k-means, optionally applying ISODATA rules.
+    Kmeans {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output path for the class label raster.
+        ///
+        /// The raster has a single band, holding the index of each pixel's cluster.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Output JSON file to store the cluster centroids in.
+        #[structopt(long)]
+        centroids: PathBuf,
+        /// Optional output path for a header describing the class label raster.
+        #[structopt(long)]
+        label_header: Option<PathBuf>,
+        /// Number of clusters to seed.
+        #[structopt(short = "k", long)]
+        clusters: usize,
+        /// Maximum number of passes over the image.
+        #[structopt(long, default_value = "20")]
+        max_iterations: usize,
+        /// Clustering stops once no centroid moves further than this between passes.
+        #[structopt(long, default_value = "0.0001")]
+        tolerance: f32,
+        /// Number of pixels randomly sampled for k-means++ seeding.
+        #[structopt(long, default_value = "10000")]
+        sample_size: usize,
+        /// Seed for the random number generator.
+        #[structopt(long, default_value = "0")]
+        seed: u64,
+        /// Optional number of principal components to cluster in.
+        ///
+        /// If present, pixels are assigned to clusters in PCA space, which is cheaper for images
+        /// with many bands.
+        /// This costs two extra passes to calculate the means and covariances.
+        #[structopt(long)]
+        pca: Option<usize>,
+        /// ISODATA: discard clusters with fewer pixels than this.
+        #[structopt(long)]
+        min_cluster_size: Option<usize>,
+        /// ISODATA: split clusters whose standard deviation along any band exceeds this.
+        #[structopt(long)]
+        split_std_dev: Option<f32>,
+        /// ISODATA: merge clusters whose centroids are closer than this.
+        #[structopt(long)]
+        merge_distance: Option<f32>,
+        /// ISODATA: never merge or discard below this many clusters.
+        ///
+        /// Defaults to half the number of seeded clusters.
+        #[structopt(long)]
+        min_clusters: Option<usize>,
+        /// ISODATA: never split above this many clusters.
+        ///
+        /// Defaults to twice the number of seeded clusters.
+        #[structopt(long)]
+        max_clusters: Option<usize>,
+    },
+    /// Extract endmember spectra from an image.
+    Endmembers {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output JSON file to store the endmember spectral library in.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional output path for a CSV copy of the spectral library.
+        #[structopt(long)]
+        csv: Option<PathBuf>,
+        /// Extraction algorithm to use: ppi, nfindr or vca.
+        #[structopt(long)]
+        method: EndmemberMethod,
+        /// Number of endmembers to extract.
+        #[structopt(short = "n", long)]
+        endmembers: usize,
+        /// Dimensionality reduction to run the extraction in: pca or mnf.
+        #[structopt(long, default_value = "pca")]
+        reduction: Reduction,
+        /// Number of reduced dimensions used by PPI.
+        ///
+        /// Defaults to the number of endmembers.
+        /// N-FINDR and VCA always use one less than, and exactly, the number of endmembers.
+        #[structopt(long)]
+        dims: Option<usize>,
+        /// Number of random skewers used by PPI.
+        #[structopt(long, default_value = "1000")]
+        skewers: usize,
+        /// Maximum number of passes over the image made by N-FINDR.
+        #[structopt(long, default_value = "10")]
+        max_iterations: usize,
+        /// Number of pixels randomly sampled for the initial N-FINDR simplex.
+        #[structopt(long, default_value = "10000")]
+        sample_size: usize,
+        /// Seed for the random number generator.
+        #[structopt(long, default_value = "0")]
+        seed: u64,
+        /// Optional path to a file containing cached spectral means.
+        ///
+        /// If not present, means will be calculated first.
+        #[structopt(short, long)]
+        means: Option<PathBuf>,
+        /// Optional path to a file containing a cached covariance matrix, calculated with the
+        /// means but without standard deviations.
+        ///
+        /// If not present, covariances will be calculated first.
+        #[structopt(long)]
+        covariances: Option<PathBuf>,
+    },
+    /// Unmix an image into per-pixel endmember abundances.
+    Unmix {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output path for the abundance data file.
+        ///
+        /// The output has one band per endmember, in library order, followed by a band holding
+        /// the RMSE of the fit.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional output path for a header describing the abundance data file.
+        #[structopt(long)]
+        output_header: Option<PathBuf>,
+        /// Spectral library JSON file holding the endmembers, as written by `endmembers`.
+        #[structopt(short, long)]
+        library: PathBuf,
+        /// Unmixing method: ucls, scls or fcls.
+        #[structopt(long, default_value = "fcls")]
+        method: UnmixingMethod,
+    },
+    /// Compute spectral indices from band math expressions.
+    Index {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output path for the index data file, with one band per expression.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional output path for a header describing the index data file.
+        #[structopt(long)]
+        output_header: Option<PathBuf>,
+        /// Index expression, such as "(b[800nm]-b[670nm])/(b[800nm]+b[670nm])".
+        ///
+        /// Bands are selected by index with `b12` or `b[12]`, or by the nearest wavelength in the
+        /// header with `b[800nm]` or `b[0.8um]`.
+        /// Expressions may use `+`, `-`, `*`, `/`, `^` and parentheses.
+        /// May be given more than once.
+        #[structopt(short, long = "expr", required = true, number_of_values = 1)]
+        exprs: Vec<String>,
+    },
+    /// Evaluate named band math expressions in a single pass, writing one band per expression.
+    BandMath {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output path for the data file, with one band per expression.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional output path for a header describing the data file.
+        #[structopt(long)]
+        output_header: Option<PathBuf>,
+        /// Named expression, such as `out1=b1*0.5+b3`.
+        ///
+        /// Bands are referenced as in the `index` command.
+        /// Besides arithmetic, expressions may use comparisons, `&&`, `||`, `!`,
+        /// `if(cond, a, b)`, `min`, `max`, `abs`, `log`, `log10`, `exp` and `sqrt`.
+        /// The image mean and standard deviation of a band are written `mean[b3]` and `std[b3]`.
+        /// May be given more than once; output bands are in the order given.
+        #[structopt(short, long = "expr", required = true, number_of_values = 1)]
+        exprs: Vec<String>,
+        /// Optional path to a file containing cached spectral means.
+        ///
+        /// If not present and an expression uses `mean[]`, means will be calculated first.
+        #[structopt(short, long)]
+        means: Option<PathBuf>,
+        /// Optional path to a file containing cached spectral standard deviations.
+        ///
+        /// If not present and an expression uses `std[]`, they will be calculated first.
+        #[structopt(short, long)]
+        std_devs: Option<PathBuf>,
+    },
+    /// Resample each pixel spectrum onto the bands of another sensor.
+    Resample {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output path for the resampled data file.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional output path for a header describing the resampled data file.
+        #[structopt(long)]
+        output_header: Option<PathBuf>,
+        /// JSON file describing the target bands.
+        ///
+        /// This uses the band fields of the header format: `wavelength`, and optionally `fwhm`,
+        /// `wavelength_units` and `band_names`.
+        #[structopt(short, long)]
+        target: PathBuf,
+        /// Resampling method, either "gaussian" or "linear".
+        ///
+        /// Gaussian resampling requires the target to have FWHM.
+        #[structopt(long, default_value = "gaussian")]
+        method: ResampleMethod,
+    },
+    /// Write a calibrated copy of an image, using the gains, offsets and reflectance scale factor
+    /// declared in its header.
+    ///
+    /// Every other command already calibrates pixels as they are read, so this is only needed to
+    /// hand calibrated data to other tools.
+    Calibrate {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output path for the calibrated data file.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional output path for a header describing the calibrated data file.
+        #[structopt(long)]
+        output_header: Option<PathBuf>,
     },
-}
\ No newline at end of file
+    /// Calculate the mean and standard deviation of every band of every column.
+    ColumnStatistics {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output JSON file to store the column statistics in.
+        #[structopt(short, long)]
+        output: PathBuf,
+    },
+    /// Remove column-wise stripes by giving every column the global statistics of each band.
+    Destripe {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output path for the destriped data file.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional output path for a header describing the destriped data file.
+        #[structopt(long)]
+        output_header: Option<PathBuf>,
+        /// Optional path to a file containing cached column statistics.
+        ///
+        /// If not present, they will be calculated first.
+        #[structopt(short, long)]
+        stats: Option<PathBuf>,
+    },
+    /// Remove the dark level of each column, and optionally divide by a flat-field, line by line.
+    Correct {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output path for the corrected data file.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional output path for a header describing the corrected data file.
+        #[structopt(long)]
+        output_header: Option<PathBuf>,
+        /// The path to the header of the dark frame cube.
+        ///
+        /// It must have the same number of channels and pixels per line as the image.
+        #[structopt(short, long)]
+        dark: PathBuf,
+        /// Optional path to the header of the flat-field reference cube.
+        ///
+        /// It must have the same number of channels and pixels per line as the image.
+        #[structopt(short, long)]
+        flat: Option<PathBuf>,
+    },
+    /// Smooth or differentiate each pixel spectrum with a Savitzky-Golay filter.
+    SpectralFilter {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output path for the filtered data file.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional output path for a header describing the filtered data file.
+        #[structopt(long)]
+        output_header: Option<PathBuf>,
+        /// Number of bands in each fitting window.
+        #[structopt(short, long, default_value = "5")]
+        window: usize,
+        /// Degree of the fitted polynomial.
+        #[structopt(short = "p", long, default_value = "2")]
+        order: usize,
+        /// Derivative to take, with zero for smoothing.
+        ///
+        /// Derivatives are per nanometre if the header has wavelengths, and per band otherwise.
+        #[structopt(short, long, default_value = "0")]
+        derivative: usize,
+    },
+    /// Divide each pixel spectrum by its continuum, the upper convex hull across wavelength.
+    ContinuumRemove {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        /// It must include band wavelengths.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output path for the continuum removed data file.
+        ///
+        /// This has one band per band within the window, in order of wavelength, followed by the
+        /// band depth and band center if a feature is given.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional output path for a header describing the continuum removed data file.
+        #[structopt(long)]
+        output_header: Option<PathBuf>,
+        /// Optional wavelength range in nanometres to remove the continuum over.
+        ///
+        /// Defaults to the whole spectrum.
+        #[structopt(short, long, number_of_values = 2)]
+        window: Option<Vec<f64>>,
+        /// Optional wavelength range in nanometres of an absorption feature.
+        ///
+        /// Its depth, one minus the lowest continuum removed value, and the wavelength of that
+        /// value are appended to each output pixel.
+        #[structopt(short, long, number_of_values = 2)]
+        feature: Option<Vec<f64>>,
+    },
+    /// Apply a 2D filter to every band, streaming a rolling window of lines.
+    SpatialFilter {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output path for the filtered data file.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional output path for a header describing the filtered data file.
+        #[structopt(long)]
+        output_header: Option<PathBuf>,
+        /// Filter to apply, one of "smooth", "sharpen", "sobel", "median" or "gaussian".
+        #[structopt(short, long, required_unless = "kernel-file", conflicts_with = "kernel-file")]
+        kernel: Option<FilterKind>,
+        /// Width of the square filter window, which must be odd.
+        ///
+        /// Sobel always uses a 3x3 window.
+        #[structopt(short, long, default_value = "3")]
+        size: usize,
+        /// Standard deviation of the gaussian filter, in pixels.
+        ///
+        /// Defaults to a sixth of the window width.
+        #[structopt(long)]
+        sigma: Option<f64>,
+        /// JSON file containing a custom convolution kernel, as an array of rows.
+        ///
+        /// The kernel must be square, with an odd width.
+        #[structopt(long)]
+        kernel_file: Option<PathBuf>,
+        /// Output interleave, either "bip" or "bsq".
+        #[structopt(long, default_value = "bip")]
+        format: ImageFormat,
+    },
+    /// Average blocks of lines and pixels into a coarser image.
+    Bin {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output path for the binned data file.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional output path for a header describing the binned data file.
+        #[structopt(long)]
+        output_header: Option<PathBuf>,
+        /// Lines by pixels in each block, such as `2x4`.
+        ///
+        /// Lines and pixels left over at the bottom and right edges are dropped.
+        #[structopt(short, long)]
+        factor: GridSize,
+        /// Output interleave, either "bip" or "bsq".
+        #[structopt(long, default_value = "bip")]
+        format: ImageFormat,
+    },
+    /// Resize an image to an arbitrary number of lines and pixels.
+    Resize {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output path for the resized data file.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional output path for a header describing the resized data file.
+        #[structopt(long)]
+        output_header: Option<PathBuf>,
+        /// Lines by pixels of the output, such as `1000x1200`.
+        #[structopt(short, long)]
+        size: GridSize,
+        /// Interpolation, either "nearest" or "bilinear".
+        #[structopt(short, long, default_value = "bilinear")]
+        method: ResizeMethod,
+        /// Output interleave, either "bip" or "bsq".
+        #[structopt(long, default_value = "bip")]
+        format: ImageFormat,
+    },
+    /// Extract the spectra at a list of pixel coordinates.
+    Spectrum {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output JSON file to store the spectra in, as a spectral library.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional output path for a CSV copy of the spectra.
+        #[structopt(long)]
+        csv: Option<PathBuf>,
+        /// Coordinates to extract, either a JSON array of `[line, pixel]` pairs or a CSV file with
+        /// one `line,pixel` pair per row.
+        #[structopt(short, long)]
+        coordinates: PathBuf,
+        /// Average over a square window reaching this many lines and pixels around each
+        /// coordinate, rather than taking the single pixel.
+        #[structopt(short, long, default_value = "0")]
+        radius: usize,
+    },
+    /// Compute the mean, standard deviation, covariance and pixel count of regions of interest.
+    ///
+    /// Regions are given either as GeoJSON polygons in pixel coordinates, or as a label raster
+    /// with one region per distinct value.
+    RoiStatistics {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output JSON file to store the statistics of each region in, keyed by region name.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// GeoJSON polygons, with `x` along pixels and `y` along lines.
+        #[structopt(short, long, required_unless = "labels", conflicts_with = "labels")]
+        polygons: Option<PathBuf>,
+        /// Header of a label raster with the same lines and pixels as the image.
+        ///
+        /// Its first band labels each pixel, and NaN marks unlabelled pixels.
+        #[structopt(short, long)]
+        labels: Option<PathBuf>,
+        /// A label value to leave out, such as a background class.
+        #[structopt(long, requires = "labels")]
+        ignore: Option<f32>,
+    },
+    /// Calculate the minimum, maximum, histogram and approximate quantiles of every band.
+    BandStatistics {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output JSON file to store the statistics of each band in.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Number of histogram bins per band.
+        ///
+        /// The bins are equal width, and their range grows in a single pass to cover every value.
+        #[structopt(short, long, default_value = "256")]
+        bins: usize,
+        /// Quantiles to estimate, between zero and one.
+        #[structopt(short, long, use_delimiter = true, default_value = "0.02,0.25,0.5,0.75,0.98")]
+        quantiles: Vec<f64>,
+        /// Size of the quantile sketch of each band.
+        ///
+        /// Larger sketches use more memory and give more accurate quantiles; the rank error is
+        /// roughly 1.7 / size.
+        #[structopt(short = "k", long, default_value = "200")]
+        sketch_size: usize,
+        /// Seed for the random number generator used by the quantile sketches.
+        #[structopt(long, default_value = "0")]
+        seed: u64,
+    },
+    /// Calculate the correlation matrix of the bands, and optionally report redundant bands.
+    Correlations {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output JSON file to store the band correlations in.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional path to a file containing cached spectral means.
+        ///
+        /// If not present, means will be calculated first.
+        #[structopt(short, long)]
+        means: Option<PathBuf>,
+        /// Optional output JSON file for a report of the most correlated band pairs, and a subset
+        /// of bands with the redundant ones removed.
+        #[structopt(short, long)]
+        report: Option<PathBuf>,
+        /// Number of band pairs to list in the report.
+        #[structopt(long, default_value = "20")]
+        top: usize,
+        /// Largest absolute correlation allowed between bands of the suggested subset.
+        #[structopt(long, default_value = "0.95")]
+        threshold: f64,
+    },
+    /// Time operations on each backend, reporting throughput and time spent waiting on IO.
+    Bench {
+        /// The path to the header file.
+        ///
+        /// Header files must be JSON and follow the JSON header format used by the program.
+        #[structopt(long)]
+        header: PathBuf,
+        /// Output JSON file to store the benchmark results in.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Optional Markdown file to write the results table to.
+        ///
+        /// If not present, the table is printed.
+        #[structopt(long)]
+        table: Option<PathBuf>,
+        /// Operations to time: means, std or cov.
+        #[structopt(long, use_delimiter = true, default_value = "means,cov")]
+        ops: Vec<BenchOp>,
+        /// Backends to time, or all for every backend compiled in.
+        #[structopt(long, default_value = "all")]
+        backends: BackendList,
+        /// Number of timed passes of each operation on each backend.
+        #[structopt(long, default_value = "3")]
+        trials: usize,
+        /// Drop the image from the page cache before each pass, so that every pass reads it cold.
+        #[structopt(long)]
+        drop_caches: bool,
+    },
+    /// Write a seeded synthetic image with known means and covariance, for tests and demos.
+    Generate {
+        /// Output path for the data file.
+        #[structopt(short, long)]
+        output: PathBuf,
+        /// Output path for the header describing the data file.
+        #[structopt(long)]
+        output_header: PathBuf,
+        /// Lines by pixels of the image, such as `100x200`.
+        #[structopt(short, long)]
+        size: GridSize,
+        /// Optional JSON file describing the distribution of the pixels.
+        ///
+        /// Either `{"model": "gaussian", "means": [...], "covariance": [[...], ...]}`, or
+        /// `{"model": "mixture", "endmembers": [[...], ...], "noise": 0.1}`. If not present, a
+        /// Gaussian with correlated bands is used.
+        #[structopt(short, long)]
+        model: Option<PathBuf>,
+        /// Number of channels of the default model.
+        #[structopt(short, long, default_value = "5", conflicts_with = "model")]
+        channels: usize,
+        /// Optional output JSON file for the exact means and covariance of the model.
+        #[structopt(long)]
+        statistics: Option<PathBuf>,
+        /// Output interleave, either "bip" or "bsq". Values are written as 32-bit floats.
+        #[structopt(long, default_value = "bip")]
+        format: ImageFormat,
+        /// Seed for the random number generator, so the same image is written each time.
+        #[structopt(long, default_value = "0")]
+        seed: u64,
+    },
+}
@@ -0,0 +1,64 @@
+use ndarray::Array2;
+
+use super::*;
+use crate::io::auto::{choose, Probe};
+use crate::io::bip::Bip;
+use crate::opt::IoBackend;
+
+const GIB: u64 = 1 << 30;
+
+fn probe(file_size: u64, available_memory: Option<u64>) -> Probe {
+    Probe {
+        compiled: vec![IoBackend::Glommio, IoBackend::Syscall, IoBackend::Mapped],
+        io_uring: true,
+        direct_io: true,
+        file_size,
+        available_memory,
+    }
+}
+
+#[test]
+fn auto_maps_files_which_fit_in_cache() {
+    assert_eq!(IoBackend::Mapped, choose(&probe(GIB, Some(4 * GIB))).0);
+
+    // Writing commands run on the chosen backend too, so the mapped one has to write
+    let dims = ImageDims { channels: 2, lines: 3, pixels: 4 };
+    let pixels = Array2::from_shape_fn((12, 2), |(i, j)| (i * 2 + j) as f32);
+    let image = temp_image("auto-mapped", dims, &pixels);
+    let out = TempPath::new("auto-mapped-out");
+
+    let config = IoConfig::default();
+    let mut mapped: MappedBip<f32> = MappedBip::new(image.header.clone(), config).unwrap();
+    mapped.crop_map("Cropping", Some((1, 3)), Some((1, 2)), 1, &out, |pixels, write| {
+        write.column_mut(0).assign(&pixels.column(1));
+    }).unwrap();
+
+    assert_eq!(vec![11.0, 19.0], read_floats(&out));
+}
+
+#[test]
+fn auto_uses_direct_io_for_large_files() {
+    assert_eq!(IoBackend::Glommio, choose(&probe(8 * GIB, Some(4 * GIB))).0);
+    assert_eq!(IoBackend::Glommio, choose(&probe(GIB, None)).0);
+}
+
+#[test]
+fn auto_falls_back_without_direct_io() {
+    let no_direct = Probe { direct_io: false, ..probe(8 * GIB, Some(4 * GIB)) };
+    assert_eq!(IoBackend::Syscall, choose(&no_direct).0);
+
+    let no_uring = Probe { io_uring: false, ..probe(8 * GIB, Some(4 * GIB)) };
+    assert_eq!(IoBackend::Syscall, choose(&no_uring).0);
+}
+
+#[test]
+fn auto_only_picks_compiled_backends() {
+    let tokio_only = Probe { compiled: vec![IoBackend::Tokio], ..probe(GIB, Some(4 * GIB)) };
+    assert_eq!(IoBackend::Tokio, choose(&tokio_only).0);
+
+    let no_glommio = Probe {
+        compiled: vec![IoBackend::Syscall, IoBackend::Mapped],
+        ..probe(8 * GIB, Some(4 * GIB))
+    };
+    assert_eq!(IoBackend::Syscall, choose(&no_glommio).0);
+}
@@ -0,0 +1,442 @@
+//! Runs every enabled backend over randomly sized images of random data, checking each
+//! operation against a straightforward in-memory implementation.
+
+use ndarray::{Array1, Array2, Array3, Axis};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use super::*;
+use crate::algorithms::resize::Resampler;
+use crate::algorithms::spatial::{FilterKind, SpatialFilter};
+use crate::io::auto::compiled_backends;
+use crate::io::bip::Bip;
+#[cfg(feature = "tokio-backend")]
+use crate::io::tokio::bip::TokioBip;
+use crate::opt::IoBackend;
+
+const CASES: usize = 24;
+
+/// A random image, and the config to read it with.
+struct Case {
+    name: String,
+    image: TempImage,
+    pixels: Array2<f32>,
+    config: IoConfig,
+}
+
+impl Case {
+    fn random(rng: &mut StdRng, tag: &str, index: usize) -> Self {
+        let dims = ImageDims {
+            channels: rng.gen_range(1..=7),
+            lines: rng.gen_range(1..=23),
+            pixels: rng.gen_range(2..=41),
+        };
+
+        let n = dims.lines * dims.pixels;
+
+        // Batches larger than the image, dividing it exactly, and leaving a tail are all likely
+        let batch_size = match rng.gen_range(0..4) {
+            0 => n + rng.gen_range(0..10),
+            1 => dims.pixels,
+            _ => rng.gen_range(1..=n.min(64)),
+        };
+
+        // Offset from zero, so that the statistics are not trivially centered
+        let offset = rng.gen_range(-100.0..100.0);
+        let pixels = Array2::from_shape_fn((n, dims.channels), |_| {
+            offset + rng.gen_range(-10.0..10.0)
+        });
+
+        let name = format!(
+            "case {} ({} lines, {} pixels, {} channels, batch size {})",
+            index, dims.lines, dims.pixels, dims.channels, batch_size
+        );
+
+        Self {
+            image: temp_image(&format!("backends-{}-{}", tag, index), dims, &pixels),
+            pixels,
+            config: IoConfig { batch_size, ..IoConfig::default() },
+            name,
+        }
+    }
+
+    fn dims(&self) -> ImageDims {
+        self.image.header.dims.clone()
+    }
+
+    /// The pixels as lines by pixels by channels.
+    fn cube(&self) -> Array3<f32> {
+        let ImageDims { lines, pixels, channels } = self.dims();
+
+        self.pixels.clone().into_shape((lines, pixels, channels)).unwrap()
+    }
+
+    fn open(&self, backend: IoBackend) -> Box<dyn BasicImage<f32>> {
+        let header = self.image.header.clone();
+        let config = self.config.clone();
+
+        match backend {
+            #[cfg(feature = "glommio-backend")]
+            IoBackend::Glommio => Box::new(GlommioBip::new(header, config).unwrap()),
+            #[cfg(feature = "syscall-backend")]
+            IoBackend::Syscall => Box::new(SyscallBip::new(header, config).unwrap()),
+            #[cfg(feature = "mapped-backend")]
+            IoBackend::Mapped => Box::new(MappedBip::new(header, config).unwrap()),
+            #[cfg(feature = "tokio-backend")]
+            IoBackend::Tokio => Box::new(TokioBip::new(header, config).unwrap()),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The same random cases for every test, each written to its own temporary file.
+fn cases(tag: &'static str) -> impl Iterator<Item=Case> {
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+
+    (0..CASES).map(move |i| Case::random(&mut rng, tag, i))
+}
+
+fn reference_means(pixels: &Array2<f32>) -> Array1<f64> {
+    pixels.mapv(f64::from).mean_axis(Axis(0)).unwrap()
+}
+
+fn reference_std_devs(pixels: &Array2<f32>, means: &Array1<f64>) -> Array1<f64> {
+    let centered = pixels.mapv(f64::from) - means;
+
+    centered.mapv(|x| x * x).mean_axis(Axis(0)).unwrap().mapv(f64::sqrt)
+}
+
+fn reference_covariances(
+    pixels: &Array2<f32>,
+    means: Option<&Array1<f64>>,
+    std_devs: Option<&Array1<f64>>,
+) -> Array2<f64> {
+    let mut x = pixels.mapv(f64::from);
+
+    if let Some(means) = means {
+        x -= means;
+    }
+
+    if let Some(std_devs) = std_devs {
+        x /= std_devs;
+    }
+
+    x.t().dot(&x) / x.nrows() as f64
+}
+
+/// Checks a backend's output against the reference, to within a thousandth of the largest
+/// expected value, since the backends accumulate in single precision.
+fn assert_matches<'a, I>(case: &Case, what: &str, actual: I, expected: &[f64])
+    where I: IntoIterator<Item=&'a f32>
+{
+    let scale = expected.iter().fold(1.0f64, |m, e| m.max(e.abs()));
+
+    assert_close(&format!("{}: {}", case.name, what), actual, expected, 1e-3 * scale);
+}
+
+#[test]
+fn backends_match_reference_statistics() {
+    for case in cases("statistics") {
+        let means = reference_means(&case.pixels);
+        let std_devs = reference_std_devs(&case.pixels, &means);
+
+        for backend in compiled_backends() {
+            let what = |op: &str| format!("{:?} {}", backend, op);
+
+            // Every operation runs on the same image, so that each has to start from the top
+            let mut image = case.open(backend);
+
+            let actual_means = image.means().unwrap();
+            assert_matches(&case, &what("means"), &actual_means, means.as_slice().unwrap());
+
+            let actual_std_devs = image.std_deviations(&actual_means).unwrap();
+            assert_matches(&case, &what("std devs"), &actual_std_devs, std_devs.as_slice().unwrap());
+
+            let raw = image.covariance_matrix(None, None).unwrap();
+            let expected = reference_covariances(&case.pixels, None, None);
+            assert_matches(&case, &what("raw covariances"), &raw, expected.as_slice().unwrap());
+
+            let scaled = image.covariance_matrix(Some(&actual_means), Some(&actual_std_devs))
+                .unwrap();
+            let expected = reference_covariances(&case.pixels, Some(&means), Some(&std_devs));
+            assert_matches(&case, &what("correlations"), &scaled, expected.as_slice().unwrap());
+        }
+    }
+}
+
+#[test]
+fn backends_match_reference_pixel_reads() {
+    let mut rng = StdRng::seed_from_u64(7);
+
+    for case in cases("pixels") {
+        let ImageDims { lines, pixels, .. } = case.dims();
+
+        let line = rng.gen_range(0..lines);
+        let pixel = rng.gen_range(0..pixels);
+        let n = rng.gen_range(1..=(pixels - pixel));
+
+        let first = line * pixels + pixel;
+        let expected = case.pixels.slice(s![first..(first + n), ..]).mapv(f64::from);
+
+        for backend in compiled_backends() {
+            let actual = match backend {
+                #[cfg(feature = "glommio-backend")]
+                IoBackend::Glommio => {
+                    let mut bip: GlommioBip<String, f32> =
+                        GlommioBip::new(case.image.header.clone(), case.config.clone()).unwrap();
+                    bip.read_pixels(line, pixel, n).unwrap()
+                }
+                #[cfg(feature = "syscall-backend")]
+                IoBackend::Syscall => {
+                    let mut bip: SyscallBip<f32> =
+                        SyscallBip::new(case.image.header.clone(), case.config.clone()).unwrap();
+                    bip.read_pixels(line, pixel, n).unwrap()
+                }
+                #[cfg(feature = "mapped-backend")]
+                IoBackend::Mapped => {
+                    let mut bip: MappedBip<f32> =
+                        MappedBip::new(case.image.header.clone(), case.config.clone()).unwrap();
+                    bip.read_pixels(line, pixel, n).unwrap()
+                }
+                #[cfg(feature = "tokio-backend")]
+                IoBackend::Tokio => {
+                    let mut bip: TokioBip<f32> =
+                        TokioBip::new(case.image.header.clone(), case.config.clone()).unwrap();
+                    bip.read_pixels(line, pixel, n).unwrap()
+                }
+                _ => unreachable!(),
+            };
+
+            let what = format!("{:?} pixels ({}, {}) x {}", backend, line, pixel, n);
+            assert_matches(&case, &what, &actual, expected.as_standard_layout().as_slice().unwrap());
+        }
+    }
+}
+
+/// A random range of at least one index within `0..n`.
+fn random_range(rng: &mut StdRng, n: usize) -> (usize, usize) {
+    let start = rng.gen_range(0..n);
+
+    (start, rng.gen_range((start + 1)..=n))
+}
+
+#[test]
+fn backends_match_reference_crops() {
+    let mut rng = StdRng::seed_from_u64(11);
+
+    for case in cases("crops") {
+        let ImageDims { lines, pixels, .. } = case.dims();
+
+        let (start_line, end_line) = random_range(&mut rng, lines);
+        let (start_pixel, end_pixel) = random_range(&mut rng, pixels);
+
+        let expected: Vec<f64> = case.cube()
+            .slice(s![start_line..end_line, start_pixel..end_pixel, ..])
+            .iter()
+            .map(|x| f64::from(*x))
+            .collect();
+
+        for backend in compiled_backends() {
+            let out = TempPath::new("backends-crop");
+
+            case.open(backend)
+                .crop(
+                    Some((start_line as u64, end_line as u64)),
+                    Some((start_pixel as u64, end_pixel as u64)),
+                    &out,
+                )
+                .unwrap();
+
+            let what = format!(
+                "{:?} crop of lines {}..{}, pixels {}..{}",
+                backend, start_line, end_line, start_pixel, end_pixel
+            );
+            assert_matches(&case, &what, &read_floats(&out), &expected);
+        }
+    }
+}
+
+#[test]
+fn backends_match_reference_subsets() {
+    let mut rng = StdRng::seed_from_u64(17);
+
+    for case in cases("subsets") {
+        let ImageDims { lines, pixels, channels } = case.dims();
+
+        let (start_line, end_line) = random_range(&mut rng, lines);
+        let (start_pixel, end_pixel) = random_range(&mut rng, pixels);
+
+        // Bands may be repeated and out of order
+        let bands: Vec<usize> = (0..rng.gen_range(1..=channels + 2))
+            .map(|_| rng.gen_range(0..channels))
+            .collect();
+
+        let expected: Vec<f64> = case.cube()
+            .slice(s![start_line..end_line, start_pixel..end_pixel, ..])
+            .select(Axis(2), &bands)
+            .iter()
+            .map(|x| f64::from(*x))
+            .collect();
+
+        for backend in compiled_backends() {
+            let out = TempPath::new("backends-subset");
+
+            case.open(backend)
+                .subset(
+                    Some((start_line as u64, end_line as u64)),
+                    Some((start_pixel as u64, end_pixel as u64)),
+                    &bands,
+                    &out,
+                )
+                .unwrap();
+
+            let what = format!(
+                "{:?} subset of lines {}..{}, pixels {}..{}, bands {:?}",
+                backend, start_line, end_line, start_pixel, end_pixel, bands
+            );
+            assert_matches(&case, &what, &read_floats(&out), &expected);
+        }
+    }
+}
+
+#[test]
+fn backends_match_reference_transforms() {
+    let mut rng = StdRng::seed_from_u64(13);
+
+    for case in cases("transforms") {
+        let channels = case.dims().channels;
+        let outputs = rng.gen_range(1..=4);
+
+        let transform = Array2::from_shape_fn((outputs, channels), |_| rng.gen_range(-1.0..1.0));
+        let expected = case.pixels.mapv(f64::from).dot(&transform.t().mapv(f64::from));
+
+        for backend in compiled_backends() {
+            let out = TempPath::new("backends-transform");
+
+            case.open(backend).write_transformed(&transform, &out, None, None).unwrap();
+
+            let what = format!("{:?} transform to {} channels", backend, outputs);
+            assert_matches(&case, &what, &read_floats(&out), expected.as_slice().unwrap());
+        }
+    }
+}
+
+#[test]
+fn backends_match_reference_line_maps() {
+    let mut rng = StdRng::seed_from_u64(19);
+
+    for case in cases("lines") {
+        let ImageDims { pixels, channels, .. } = case.dims();
+
+        // One value per column and band, as the column corrections take them
+        let mut per_column = |lo: f32, hi: f32| {
+            Array2::from_shape_fn((pixels, channels), |_| rng.gen_range(lo..hi))
+        };
+
+        let dark = per_column(-5.0, 5.0);
+        let gain = per_column(0.5, 2.0);
+        let offset = per_column(-5.0, 5.0);
+
+        let cube = case.cube().mapv(f64::from);
+        let corrected = (&cube - &dark.mapv(f64::from)) / &gain.mapv(f64::from);
+        let destriped = &cube * &gain.mapv(f64::from) + &offset.mapv(f64::from);
+
+        for backend in compiled_backends() {
+            let out = TempPath::new("backends-correct");
+
+            case.open(backend).write_column_corrected(&dark, Some(&gain), &out).unwrap();
+
+            let what = format!("{:?} column correction", backend);
+            assert_matches(&case, &what, &read_floats(&out), corrected.as_slice().unwrap());
+
+            let out = TempPath::new("backends-destripe");
+
+            case.open(backend).write_destriped(&gain, &offset, &out).unwrap();
+
+            let what = format!("{:?} destripe", backend);
+            assert_matches(&case, &what, &read_floats(&out), destriped.as_slice().unwrap());
+        }
+    }
+}
+
+/// Box mean over the `size` by `size` window around every pixel, taking pixels beyond the edges
+/// from the nearest edge.
+fn reference_smooth(cube: &Array3<f64>, size: usize) -> Array3<f64> {
+    let (lines, pixels, _) = cube.dim();
+    let radius = size as isize / 2;
+
+    let clamp = |x: isize, n: usize| x.clamp(0, n as isize - 1) as usize;
+
+    Array3::from_shape_fn(cube.dim(), |(l, p, c)| {
+        let mut sum = 0.0;
+
+        for dl in -radius..=radius {
+            for dp in -radius..=radius {
+                sum += cube[[clamp(l as isize + dl, lines), clamp(p as isize + dp, pixels), c]];
+            }
+        }
+
+        sum / (size * size) as f64
+    })
+}
+
+/// Means of `factor.0` by `factor.1` blocks, dropping whatever is left at the edges.
+fn reference_bin(cube: &Array3<f64>, factor: (usize, usize)) -> Array3<f64> {
+    let (lines, pixels, channels) = cube.dim();
+    let shape = (lines / factor.0, pixels / factor.1, channels);
+
+    Array3::from_shape_fn(shape, |(l, p, c)| {
+        let block = cube.slice(s![
+            (l * factor.0)..((l + 1) * factor.0),
+            (p * factor.1)..((p + 1) * factor.1),
+            c
+        ]);
+
+        block.sum() / block.len() as f64
+    })
+}
+
+#[test]
+fn backends_match_reference_fold_writers() {
+    let mut rng = StdRng::seed_from_u64(23);
+
+    for case in cases("folds") {
+        let ImageDims { lines, pixels, .. } = case.dims();
+
+        let size = [1, 3, 5][rng.gen_range(0..3)];
+        let filter = SpatialFilter::new(FilterKind::Smooth, size).unwrap();
+
+        let factor = (rng.gen_range(1..=lines), rng.gen_range(1..=pixels));
+        let resampler = Resampler::bin(lines, pixels, factor).unwrap();
+
+        let cube = case.cube().mapv(f64::from);
+        let smoothed = reference_smooth(&cube, size);
+        let binned = reference_bin(&cube, factor);
+
+        for backend in compiled_backends() {
+            let out = TempPath::new("backends-spatial");
+
+            case.open(backend).write_spatially_filtered(&filter, ImageFormat::Bip, &out).unwrap();
+
+            let what = format!("{:?} {}x{} smooth", backend, size, size);
+            assert_matches(&case, &what, &read_floats(&out), smoothed.as_slice().unwrap());
+
+            let out = TempPath::new("backends-bin");
+
+            case.open(backend).write_resampled(&resampler, ImageFormat::Bip, &out).unwrap();
+
+            let what = format!("{:?} {}x{} bin", backend, factor.0, factor.1);
+            assert_matches(&case, &what, &read_floats(&out), binned.as_slice().unwrap());
+        }
+    }
+}
+
+#[test]
+fn io_config_rejects_zero_sizes() {
+    assert!(IoConfig::default().validate().is_ok());
+    assert!(IoConfig { batch_size: 0, ..IoConfig::default() }.validate().is_err());
+    assert!(IoConfig { buffer_size: 0, ..IoConfig::default() }.validate().is_err());
+
+    #[cfg(feature = "glommio-backend")]
+    assert_eq!(IoConfig::default().locked_memory(), 2 * 524_288 * 16);
+}
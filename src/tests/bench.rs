@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use std::path::Path;
+
+use ndarray::{Array2, ArrayViewMut2};
+
+use crate::error::VanadiumResult;
+use crate::image_formats::bip::BipDims;
+use crate::io::bench::{BenchOp, BenchResult, markdown_table, Trial};
+use crate::io::bip::Bip;
+use crate::io::timed::{ComputeTimer, TimedBip};
+use crate::opt::IoBackend;
+
+use super::*;
+
+fn trial(wall: f64, compute: f64) -> Trial {
+    Trial { wall: Duration::from_secs_f64(wall), compute: Duration::from_secs_f64(compute) }
+}
+
+#[test]
+fn bench_summarizes_trials() {
+    let trials = [trial(1.0, 0.25), trial(3.0, 0.75)];
+    let result = BenchResult::new(IoBackend::Syscall, BenchOp::Means, 4 << 30, &trials);
+
+    assert_eq!("vanadium (syscall)", result.tool);
+    assert_eq!(2, result.trials);
+    assert!((result.mean - 2.0).abs() < 1e-9);
+    assert!((result.std_dev - 1.0).abs() < 1e-9);
+    assert!((result.throughput - 2.0).abs() < 1e-9);
+    assert!((result.compute - 0.5).abs() < 1e-9);
+    assert!((result.io_wait - 1.5).abs() < 1e-9);
+}
+
+#[test]
+fn bench_table_aligns_with_header() {
+    let results = vec![
+        BenchResult::new(IoBackend::Glommio, BenchOp::Cov, 1 << 30, &[trial(12.5, 2.0)]),
+        BenchResult::new(IoBackend::Mapped, BenchOp::Means, 1 << 30, &[trial(0.5, 0.1)]),
+    ];
+
+    let table = markdown_table(&results);
+    let lines: Vec<&str> = table.lines().collect();
+
+    assert_eq!(4, lines.len());
+    assert!(lines[2].starts_with("| vanadium (io-uring) | cov       |"));
+    assert!(lines[2].contains("12.500 s ± 0.000 s"));
+
+    let widths: Vec<usize> = lines.iter().map(|l| l.chars().count()).collect();
+    assert!(widths.iter().all(|w| *w == widths[0]));
+}
+
+#[test]
+fn bench_times_compute_within_pass() {
+    let dims = ImageDims { channels: 4, lines: 50, pixels: 60 };
+    let pixels = Array2::from_shape_fn((3000, 4), |(i, c)| (i * 4 + c) as f32);
+    let image = temp_image("bench-timed", dims, &pixels);
+
+    let timer = ComputeTimer::default();
+    let bip: SyscallBip<f32> = SyscallBip::new(image.header.clone(), IoConfig::default()).unwrap();
+    let mut bip = TimedBip::new(bip, timer.clone());
+
+    let start = std::time::Instant::now();
+    bip.means().unwrap();
+    let wall = start.elapsed();
+
+    assert!(timer.get() > Duration::from_secs(0));
+    assert!(timer.get() <= wall);
+}
+
+#[test]
+fn bench_parses_ops() {
+    assert_eq!(BenchOp::Cov, "cov".parse().unwrap());
+    assert!("median".parse::<BenchOp>().is_err());
+}
+
+/// Counts the passes an operation makes over the image it is given.
+struct CountingBip<C> {
+    inner: C,
+    passes: u64,
+}
+
+impl<C> Bip<f32> for CountingBip<C> where C: Bip<f32> {
+    fn fold_batched<F, A>(&mut self, name: &str, accumulator: A, f: F) -> VanadiumResult<A>
+        where F: FnMut(&mut Array2<f32>, &mut A)
+    {
+        self.passes += 1;
+        self.inner.fold_batched(name, accumulator, f)
+    }
+
+    fn dims(&self) -> &BipDims<f32> {
+        self.inner.dims()
+    }
+
+    fn map_and_write_batched<F>(
+        &mut self,
+        name: &str,
+        out: &dyn AsRef<Path>,
+        n_output_channels: usize,
+        f: F,
+    ) -> VanadiumResult<()>
+        where F: FnMut(&mut ArrayViewMut2<f32>, &mut Array2<f32>)
+    {
+        self.passes += 1;
+        self.inner.map_and_write_batched(name, out, n_output_channels, f)
+    }
+
+    fn crop_map<F>(
+        &mut self,
+        name: &str,
+        rows: Option<(u64, u64)>,
+        cols: Option<(u64, u64)>,
+        n_output_channels: usize,
+        out: &dyn AsRef<Path>,
+        f: F,
+    ) -> VanadiumResult<()>
+        where F: FnMut(&mut ArrayViewMut2<f32>, &mut Array2<f32>)
+    {
+        self.passes += 1;
+        self.inner.crop_map(name, rows, cols, n_output_channels, out, f)
+    }
+
+    fn read_pixels(&mut self, line: usize, pixel: usize, n: usize) -> VanadiumResult<Array2<f32>> {
+        self.inner.read_pixels(line, pixel, n)
+    }
+}
+
+#[test]
+fn bench_ops_take_the_passes_they_report() {
+    let dims = ImageDims { channels: 2, lines: 4, pixels: 5 };
+    let pixels = Array2::from_shape_fn((20, 2), |(i, c)| 100.0 + (i * 2 + c) as f32);
+    let image = temp_image("bench-passes", dims, &pixels);
+
+    for op in [BenchOp::Means, BenchOp::Std, BenchOp::Cov] {
+        let inner: SyscallBip<f32> = SyscallBip::new(image.header.clone(), IoConfig::default())
+            .unwrap();
+        let mut bip = CountingBip { inner, passes: 0 };
+
+        op.run(&mut bip).unwrap();
+
+        assert_eq!(op.passes(), bip.passes, "{:?}", op);
+    }
+}
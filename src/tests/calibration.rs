@@ -0,0 +1,39 @@
+use ndarray::arr2;
+
+use crate::headers::BandInfo;
+use crate::io::calibrated::Calibration;
+
+#[test]
+fn check_calibration() {
+    let bands = BandInfo {
+        data_gain_values: Some(vec![2.0, 0.5]),
+        data_offset_values: Some(vec![10.0, 0.0]),
+        reflectance_scale_factor: Some(10.0),
+        ..BandInfo::NONE
+    };
+
+    let calibration: Calibration<f32> = Calibration::from_bands(&bands, 2).unwrap();
+
+    let mut pixels = arr2(&[[0.0f32, 20.0], [5.0, 40.0]]);
+
+    calibration.apply(&mut pixels);
+
+    assert_eq!(arr2(&[[1.0f32, 1.0], [2.0, 2.0]]), pixels);
+
+    assert!(!bands.without_calibration().is_calibrated());
+}
+
+#[test]
+fn check_scale_only() {
+    let bands = BandInfo { reflectance_scale_factor: Some(10000.0), ..BandInfo::NONE };
+
+    let calibration: Calibration<f32> = Calibration::from_bands(&bands, 3).unwrap();
+
+    let mut pixels = arr2(&[[5000.0f32, 10000.0, 2500.0]]);
+
+    calibration.apply(&mut pixels);
+
+    assert_eq!(arr2(&[[0.5f32, 1.0, 0.25]]), pixels);
+
+    assert!(Calibration::<f32>::from_bands(&BandInfo::NONE, 3).is_none());
+}
@@ -0,0 +1,65 @@
+use ndarray::{arr2, Array2};
+
+use crate::algorithms::continuum::ContinuumRemoval;
+
+const WAVELENGTHS: [f64; 5] = [400.0, 500.0, 600.0, 700.0, 800.0];
+
+#[test]
+fn check_continuum_removal() {
+    let removal = ContinuumRemoval::new(&WAVELENGTHS, None, Some((550.0, 650.0))).unwrap();
+
+    assert_eq!(7, removal.n_outputs());
+
+    let pixels = arr2(&[[0.5f32, 0.6, 0.3, 0.6, 0.5], [0.2, 0.4, 0.6, 0.4, 0.2]]);
+    let mut out = Array2::zeros((2, 7));
+
+    removal.remove(&pixels, &mut out);
+
+    let expected = [[1.0, 1.0, 0.5, 1.0, 1.0, 0.5, 600.0], [1.0, 1.0, 1.0, 1.0, 1.0, 0.0, 600.0]];
+
+    for (o, e) in out.iter().zip(expected.iter().flatten()) {
+        assert!((o - e).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn check_window() {
+    // bands out of wavelength order are sorted, and the window drops the first and last
+    let wavelengths = [800.0, 400.0, 600.0, 500.0, 700.0];
+
+    let removal = ContinuumRemoval::new(&wavelengths, Some((450.0, 750.0)), None).unwrap();
+
+    assert_eq!(&[3, 2, 4], removal.bands());
+
+    let pixels = arr2(&[[9.0f32, 9.0, 0.5, 1.0, 2.0]]);
+    let mut out = Array2::zeros((1, 3));
+
+    removal.remove(&pixels, &mut out);
+
+    assert!((out[[0, 1]] - 1.0 / 3.0).abs() < 1e-6);
+
+    assert!(ContinuumRemoval::new(&wavelengths, Some((450.0, 550.0)), None).is_err());
+    assert!(ContinuumRemoval::new(&wavelengths, None, Some((410.0, 450.0))).is_err());
+}
+
+#[test]
+fn check_empty_and_nan_pixels() {
+    let removal = ContinuumRemoval::new(&WAVELENGTHS, None, Some((550.0, 650.0))).unwrap();
+
+    let pixels = arr2(&[[0.0f32; 5], [0.5, 0.6, f32::NAN, 0.6, 0.5]]);
+    let mut out = Array2::zeros((2, 7));
+
+    removal.remove(&pixels, &mut out);
+
+    // an empty pixel has no continuum, and so no absorption
+    for o in out.row(0) {
+        assert!(o.is_finite());
+    }
+
+    assert!((out[[0, 5]] - 0.0).abs() < 1e-6);
+
+    // the only band in the feature is NaN, so the feature is unmeasured
+    assert!(out[[1, 2]].is_nan());
+    assert!(out[[1, 5]].is_nan());
+    assert!(out[[1, 6]].is_nan());
+}
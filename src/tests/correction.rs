@@ -0,0 +1,104 @@
+use std::marker::PhantomData;
+
+use ndarray::{arr2, Array2};
+
+use crate::algorithms::correction::{ColumnStatistics, flat_field_gain};
+use crate::headers::ImageDims;
+use crate::image_formats::bip::BipDims;
+
+#[test]
+fn check_column_means() {
+    let dims: BipDims<f32> = BipDims {
+        dims: ImageDims { channels: 2, lines: 2, pixels: 3 },
+        phantom: PhantomData,
+    };
+
+    let mut acc = (Array2::zeros((3, 2)), 0);
+
+    // batches which straddle the line boundary
+    BipDims::accumulate_column_means(&mut arr2(&[[1.0, 10.0], [2.0, 20.0]]), &mut acc);
+    BipDims::accumulate_column_means(&mut arr2(&[[3.0, 30.0], [3.0, 50.0], [4.0, 60.0]]), &mut acc);
+    BipDims::accumulate_column_means(&mut arr2(&[[5.0, 70.0]]), &mut acc);
+
+    let (mut means, seen) = acc;
+
+    dims.normalize_column_means_accumulator(&mut means);
+
+    assert_eq!(6, seen);
+    assert_eq!(arr2(&[[2.0, 30.0], [3.0, 40.0], [4.0, 50.0]]), means);
+}
+
+#[test]
+fn check_flat_field() {
+    let dark = arr2(&[[1.0f32, 0.0], [1.0, 0.0]]);
+    let flat = arr2(&[[4.0f32, 2.0], [2.0, 6.0]]);
+
+    let gain = flat_field_gain(&dark, &flat);
+
+    assert_eq!(arr2(&[[1.5f32, 0.5], [0.5, 1.5]]), gain);
+
+    let mut line = arr2(&[[7.0f32, 1.0], [2.0, 3.0]]);
+    let mut out = Array2::zeros((2, 2));
+
+    BipDims::map_column_correction(&mut line.view_mut(), &dark, Some(&gain), &mut out);
+
+    assert_eq!(arr2(&[[4.0f32, 2.0], [2.0, 2.0]]), out);
+}
+
+#[test]
+fn check_flat_field_dead_column() {
+    // the second column reads the dark level in the first band, and the second band is dead
+    let dark = arr2(&[[1.0f32, 2.0], [1.0, 2.0]]);
+    let flat = arr2(&[[3.0f32, 2.0], [1.0, 2.0]]);
+
+    let gain = flat_field_gain(&dark, &flat);
+
+    assert_eq!(arr2(&[[2.0f32, 1.0], [1.0, 1.0]]), gain);
+
+    let mut line = arr2(&[[5.0f32, 4.0], [3.0, 2.0]]);
+    let mut out = Array2::zeros((2, 2));
+
+    BipDims::map_column_correction(&mut line.view_mut(), &dark, Some(&gain), &mut out);
+
+    assert!(out.iter().all(|x| x.is_finite()));
+    assert_eq!(arr2(&[[2.0f32, 2.0], [2.0, 0.0]]), out);
+}
+
+#[test]
+fn check_destripe() {
+    let dims: BipDims<f32> = BipDims {
+        dims: ImageDims { channels: 1, lines: 2, pixels: 2 },
+        phantom: PhantomData,
+    };
+
+    // the second column reads twice as bright as the first, with twice the spread
+    let mut pixels = arr2(&[[1.0f32], [2.0], [3.0], [6.0]]);
+
+    let means = arr2(&[[2.0f32], [4.0]]);
+    let mut acc = (Array2::zeros((2, 1)), 0);
+
+    BipDims::accumulate_column_standard_deviations(&mut pixels, &means, &mut acc);
+
+    let (mut std_devs, _) = acc;
+    dims.normalize_column_standard_deviations_accumulator(&mut std_devs);
+
+    assert_eq!(arr2(&[[1.0f32], [2.0]]), std_devs);
+
+    let stats = ColumnStatistics { means, std_devs };
+
+    let (global_means, global_std_devs) = stats.global();
+
+    assert_eq!(3.0, global_means[0]);
+    assert!((global_std_devs[0] - 3.5f32.sqrt()).abs() < 1e-6);
+
+    let (gains, offsets) = stats.destripe_coefficients();
+
+    let mut line = arr2(&[[1.0f32], [2.0]]);
+    let mut out = Array2::zeros((2, 1));
+
+    BipDims::map_column_gain_offset(&mut line.view_mut(), &gains, &offsets, &mut out);
+
+    // both pixels sit one column standard deviation below their column mean
+    assert!((out[[0, 0]] - out[[1, 0]]).abs() < 1e-6);
+    assert!((out[[0, 0]] - (3.0 - 3.5f32.sqrt())).abs() < 1e-6);
+}
@@ -0,0 +1,56 @@
+use ndarray::arr2;
+
+use crate::algorithms::correlation::{BandPair, correlation_matrix, CorrelationReport};
+
+#[test]
+fn check_correlation_matrix() {
+    let covariances = arr2(&[
+        [4.0, 2.0, -1.0, 0.0],
+        [2.0, 1.0, -0.25, 0.0],
+        [-1.0, -0.25, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 0.0],
+    ]);
+
+    let correlations = correlation_matrix(&covariances);
+
+    assert_eq!(correlations, arr2(&[
+        [1.0, 1.0, -0.5, 0.0],
+        [1.0, 1.0, -0.25, 0.0],
+        [-0.5, -0.25, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]));
+}
+
+#[test]
+fn check_report() {
+    let correlations = arr2(&[
+        [1.0, 0.99, 0.96, 0.1],
+        [0.99, 1.0, -0.97, 0.1],
+        [0.96, -0.97, 1.0, 0.2],
+        [0.1, 0.1, 0.2, 1.0],
+    ]);
+
+    let report = CorrelationReport::new(&correlations, 2, 0.95);
+
+    assert_eq!(report.pairs, vec![
+        BandPair { bands: (0, 1), correlation: 0.99 },
+        BandPair { bands: (1, 2), correlation: -0.97 },
+    ]);
+
+    // Band 1 is slightly more redundant than band 0, then band 2 more than band 0
+    assert_eq!(report.dropped, vec![1, 2]);
+    assert_eq!(report.subset, vec![0, 3]);
+}
+
+#[test]
+fn check_report_ties_keep_lower_band() {
+    let correlations = arr2(&[
+        [1.0, 0.99],
+        [0.99, 1.0],
+    ]);
+
+    let report = CorrelationReport::new(&correlations, 10, 0.95);
+
+    assert_eq!(report.subset, vec![0]);
+    assert_eq!(CorrelationReport::new(&correlations, 10, 0.995).subset, vec![0, 1]);
+}
@@ -1,130 +1,27 @@
-use std::fs::File;
-use std::mem::MaybeUninit;
-use std::sync::Once;
-
 use approx::assert_relative_eq;
-use ndarray::{Array2};
-
-use super::*;
-use crate::io::tokio::bip::TokioBip;
-
-static mut GLO_VAL: MaybeUninit<Array2<f32>> = MaybeUninit::uninit();
-static mut MAP_VAL: MaybeUninit<Array2<f32>> = MaybeUninit::uninit();
-static mut SYS_VAL: MaybeUninit<Array2<f32>> = MaybeUninit::uninit();
-static mut TOK_VAL: MaybeUninit<Array2<f32>> = MaybeUninit::uninit();
-
-fn glommio_init() {
-    static INIT: Once = Once::new();
-
-    INIT.call_once(|| {
-        let means = serde_json::from_reader(File::open("data/small/means.json").unwrap()).unwrap();
-        let sd = serde_json::from_reader(File::open("data/small/std-devs.json").unwrap()).unwrap();
-
-        let mut bip: GlommioBip<&str, f32> = GlommioBip::new(TINY_HEADER.clone()).unwrap();
-
-        unsafe {
-            GLO_VAL = MaybeUninit::new(bip.covariance_matrix(Some(&means), Some(&sd)).unwrap());
-        }
-    });
-}
-
-fn mapped_init() {
-    static INIT: Once = Once::new();
-
-    INIT.call_once(|| {
-        let means = serde_json::from_reader(File::open("data/small/means.json").unwrap()).unwrap();
-        let sd = serde_json::from_reader(File::open("data/small/std-devs.json").unwrap()).unwrap();
-
-        let mut bip: MappedBip<f32> = MappedBip::new(TINY_HEADER.clone()).unwrap();
-
-        unsafe {
-            MAP_VAL = MaybeUninit::new(bip.covariance_matrix(Some(&means), Some(&sd)).unwrap());
-        }
-    });
-}
-
-fn sys_init() {
-    static INIT: Once = Once::new();
-
-    INIT.call_once(|| {
-        let means = serde_json::from_reader(File::open("data/small/means.json").unwrap()).unwrap();
-        let sd = serde_json::from_reader(File::open("data/small/std-devs.json").unwrap()).unwrap();
-
-        let mut bip: SyscallBip<f32> = SyscallBip::new(TINY_HEADER.clone()).unwrap();
+use ndarray::Array2;
 
-        unsafe {
-            SYS_VAL = MaybeUninit::new(bip.covariance_matrix(Some(&means), Some(&sd)).unwrap());
-        }
-    });
-}
-
-fn tok_init() {
-    static INIT: Once = Once::new();
-
-    INIT.call_once(|| {
-        let means = serde_json::from_reader(File::open("data/small/means.json").unwrap()).unwrap();
-        let sd = serde_json::from_reader(File::open("data/small/std-devs.json").unwrap()).unwrap();
-
-        let mut bip: TokioBip<f32> = TokioBip::new(TINY_HEADER.clone()).unwrap();
-
-        unsafe {
-            TOK_VAL = MaybeUninit::new(bip.covariance_matrix(Some(&means), Some(&sd)).unwrap());
-        }
-    });
-}
+use crate::headers::ImageDims;
+use crate::image_formats::bip::BipDims;
 
 #[test]
-fn cov_check_eq_sys_tok() {
-    tok_init();
-    sys_init();
+fn cov_check_accumulator_matches_product() {
+    let pixels = Array2::from_shape_fn((37, 6), |(i, c)| ((i * 7 + c * 13) % 11) as f32 - 4.0);
 
-    unsafe {
-        assert_relative_eq!(
-            TOK_VAL.as_ptr().as_ref().unwrap().as_slice().unwrap(),
-            SYS_VAL.as_ptr().as_ref().unwrap().as_slice().unwrap(),
-            epsilon = f32::EPSILON
-        );
-    }
-}
+    let dims = BipDims::<f32> {
+        dims: ImageDims { channels: 6, lines: 37, pixels: 1 },
+        phantom: Default::default(),
+    };
 
-#[test]
-fn cov_check_eq_sys_gl() {
-    glommio_init();
-    sys_init();
+    let mut acc = Array2::zeros((6, 6));
 
-    unsafe {
-        assert_relative_eq!(
-            GLO_VAL.as_ptr().as_ref().unwrap().as_slice().unwrap(),
-            SYS_VAL.as_ptr().as_ref().unwrap().as_slice().unwrap(),
-            epsilon = f32::EPSILON
-        );
+    for batch in [pixels.slice(s![..32, ..]), pixels.slice(s![32.., ..])].iter() {
+        BipDims::accumulate_covariances(&mut batch.to_owned(), None, None, &mut acc);
     }
-}
-
-#[test]
-fn cov_check_eq_map_gl() {
-    mapped_init();
-    glommio_init();
 
-    unsafe {
-        assert_relative_eq!(
-            MAP_VAL.as_ptr().as_ref().unwrap().as_slice().unwrap(),
-            GLO_VAL.as_ptr().as_ref().unwrap().as_slice().unwrap(),
-            epsilon = f32::EPSILON
-        );
-    }
-}
+    dims.normalize_covariances_accumulator(&mut acc);
 
-#[test]
-fn cov_check_eq_sys_map() {
-    sys_init();
-    mapped_init();
+    let expected = pixels.t().dot(&pixels) / 37.0;
 
-    unsafe {
-        assert_relative_eq!(
-            SYS_VAL.as_ptr().as_ref().unwrap().as_slice().unwrap(),
-            MAP_VAL.as_ptr().as_ref().unwrap().as_slice().unwrap(),
-            epsilon = f32::EPSILON
-        );
-    }
+    assert_relative_eq!(acc.as_slice().unwrap(), expected.as_slice().unwrap(), epsilon = 1e-5);
 }
@@ -0,0 +1,72 @@
+use ndarray::{arr1, arr2, Array1, Array2};
+
+use crate::algorithms::endmembers::{NfindrAccumulator, orthogonal_direction, PpiAccumulator};
+use crate::algorithms::pca::Projection;
+
+fn identity(n: usize) -> Projection<f32> {
+    Projection {
+        means: Array1::zeros(n),
+        transform: Array2::eye(n),
+    }
+}
+
+// a triangle's corners, with interior points mixed in
+fn triangle() -> Array2<f32> {
+    arr2(&[
+        [0.2, 0.2],
+        [0.0, 0.0],
+        [0.3, 0.1],
+        [1.0, 0.0],
+        [0.1, 0.3],
+        [0.0, 1.0],
+        [0.25, 0.25],
+    ])
+}
+
+#[test]
+fn check_orthogonal_direction() {
+    let basis = vec![arr1(&[1.0f32, 0.0, 0.0]), arr1(&[1.0, 1.0, 0.0])];
+
+    let f = orthogonal_direction(arr1(&[0.3, 0.4, 0.5]), &basis);
+
+    assert!(f[0].abs() < 1e-6);
+    assert!(f[1].abs() < 1e-6);
+    assert!((f[2] - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn check_nfindr_finds_corners() {
+    let pixels = triangle();
+    let projection = identity(2);
+
+    let start = pixels.select(ndarray::Axis(0), &[0, 2, 6]);
+    let mut acc = NfindrAccumulator::new(start.clone(), vec![0, 2, 6], start).unwrap();
+
+    for _ in 0..3 {
+        acc.start_pass();
+        acc.add(&pixels, &projection);
+    }
+
+    let library = acc.into_library(7);
+
+    let mut found: Vec<usize> = library.coordinates.iter().map(|(_, p)| *p).collect();
+    found.sort_unstable();
+
+    assert_eq!(vec![1, 3, 5], found);
+}
+
+#[test]
+fn check_ppi_prefers_corners() {
+    let pixels = triangle();
+    let projection = identity(2);
+
+    let skewers = arr2(&[[1.0, 0.0], [0.0, 1.0], [0.70710677, 0.70710677]]);
+
+    let mut acc = PpiAccumulator::new(skewers);
+    acc.add(&pixels, &projection);
+
+    let library = acc.into_library(1, 7);
+
+    // the origin is the minimum along all three skewers
+    assert_eq!(vec![(0, 1)], library.coordinates);
+}
@@ -0,0 +1,94 @@
+use ndarray::arr2;
+
+use crate::algorithms::expr::{BandRef, Expr, ExprContext};
+
+fn channels(channels: usize) -> ExprContext<'static> {
+    ExprContext { channels, ..ExprContext::default() }
+}
+
+#[test]
+fn check_ndvi() {
+    let wavelengths = [450.0, 670.0, 800.0];
+
+    let ndvi = Expr::parse("(b[800nm]-b[670nm])/(b[800nm]+b[0.67um])")
+        .unwrap()
+        .resolve(&ExprContext { channels: 3, wavelengths: Some(&wavelengths), ..ExprContext::default() })
+        .unwrap();
+
+    let pixels = arr2(&[[0.1f32, 0.2, 0.6], [0.1, 0.3, 0.3]]);
+
+    let v = ndvi.eval(&pixels);
+
+    assert!((v[0] - 0.5).abs() < 1e-6);
+    assert!(v[1].abs() < 1e-6);
+}
+
+#[test]
+fn check_precedence() {
+    let e = Expr::parse("1 + 2 * b1 ^ 2 - -b[0]").unwrap().resolve(&channels(2)).unwrap();
+
+    let v = e.eval(&arr2(&[[1.0f32, 3.0]]));
+
+    assert_eq!(20.0, v[0]);
+}
+
+#[test]
+fn check_band_refs() {
+    assert_eq!(Expr::Band(BandRef::Index(12)), Expr::parse("b12").unwrap());
+    assert_eq!(Expr::Band(BandRef::Index(12)), Expr::parse("b[12]").unwrap());
+    assert_eq!(Expr::Band(BandRef::Wavelength(800.0)), Expr::parse("b[0.8um]").unwrap());
+}
+
+#[test]
+fn check_invalid() {
+    assert!(Expr::parse("b[1").is_err());
+    assert!(Expr::parse("b1 +").is_err());
+    assert!(Expr::parse("c1").is_err());
+    assert!(Expr::parse("b5").unwrap().resolve(&channels(3)).is_err());
+    assert!(Expr::parse("b[800nm]").unwrap().resolve(&channels(3)).is_err());
+}
+
+#[test]
+fn check_conditionals() {
+    let e = Expr::parse("if(b0 > 0.5 && !(b1 == 2), max(b0, b1, 3), min(abs(-b1), sqrt(b0)))")
+        .unwrap()
+        .resolve(&channels(2))
+        .unwrap();
+
+    let v = e.eval(&arr2(&[[1.0f32, 4.0], [0.25, 4.0], [1.0, 2.0]]));
+
+    assert_eq!(4.0, v[0]);
+    assert_eq!(0.5, v[1]);
+    assert_eq!(1.0, v[2]);
+}
+
+#[test]
+fn check_statistics() {
+    let means = [1.0, 2.0];
+    let std_devs = [0.5, 4.0];
+
+    let ctx = ExprContext {
+        channels: 2,
+        means: Some(&means),
+        std_devs: Some(&std_devs),
+        ..ExprContext::default()
+    };
+
+    let e = Expr::parse("(b1 - mean[b1]) / std[b[1]]").unwrap().resolve(&ctx).unwrap();
+
+    assert_eq!(0.5, e.eval(&arr2(&[[0.0f32, 4.0]]))[0]);
+
+    assert!(Expr::parse("mean[b0]").unwrap().resolve(&channels(2)).is_err());
+}
+
+#[test]
+fn check_named() {
+    let (name, e) = Expr::parse_named("out1=b1*0.5+b0").unwrap();
+
+    assert_eq!("out1", name);
+    assert_eq!(Expr::parse("b1*0.5+b0").unwrap(), e);
+
+    assert!(Expr::parse_named("b0 == b1").is_err());
+    assert!(Expr::parse_named("1x=b0").is_err());
+    assert!(Expr::parse("log(b0, b1)").is_err());
+}
@@ -0,0 +1,41 @@
+use crate::algorithms::filter::savitzky_golay;
+
+#[test]
+fn check_smoothing_coefficients() {
+    let positions: Vec<f64> = (0..7).map(|i| i as f64).collect();
+
+    let m = savitzky_golay(&positions, 5, 2, 0).unwrap();
+
+    // the classic 5 point quadratic smoothing weights
+    let expected = [-3.0, 12.0, 17.0, 12.0, -3.0];
+
+    for (j, e) in expected.iter().enumerate() {
+        assert!((m[[3, j + 1]] - e / 35.0).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn check_polynomial_derivatives() {
+    // uneven spacing, and edges where the window is shifted inwards
+    let positions = [400.0, 410.0, 425.0, 430.0, 450.0, 455.0, 470.0];
+    let spectrum: Vec<f64> = positions.iter().map(|x| 0.5 * x * x - 3.0 * x + 2.0).collect();
+
+    let smooth = savitzky_golay(&positions, 4, 2, 0).unwrap().dot(&ndarray::arr1(&spectrum));
+    let first = savitzky_golay(&positions, 4, 2, 1).unwrap().dot(&ndarray::arr1(&spectrum));
+    let second = savitzky_golay(&positions, 4, 2, 2).unwrap().dot(&ndarray::arr1(&spectrum));
+
+    for (i, x) in positions.iter().enumerate() {
+        assert!((smooth[i] - spectrum[i]).abs() < 1e-6);
+        assert!((first[i] - (x - 3.0)).abs() < 1e-6);
+        assert!((second[i] - 1.0).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn check_invalid_filter() {
+    let positions = [0.0, 1.0, 2.0];
+
+    assert!(savitzky_golay(&positions, 5, 2, 0).is_err());
+    assert!(savitzky_golay(&positions, 3, 3, 0).is_err());
+    assert!(savitzky_golay(&positions, 3, 1, 2).is_err());
+}
@@ -0,0 +1,44 @@
+use crate::headers::{BandInfo, Header};
+
+const JSON: &str = r#"{
+    "channels": 3,
+    "lines": 2,
+    "pixels": 2,
+    "format": "Bip",
+    "path": "data",
+    "wavelength": [0.45, 0.67, 0.8],
+    "fwhm": [0.01, 0.01, 0.02],
+    "wavelength_units": "um",
+    "band_names": ["blue", "red", "nir"],
+    "bbl": [1, 0, 1]
+}"#;
+
+#[test]
+fn check_band_info() {
+    let header: Header<String> = serde_json::from_str(JSON).unwrap();
+
+    header.validate().unwrap();
+
+    assert_eq!(Some(vec![450.0, 670.0, 800.0]), header.bands.wavelength_nm());
+
+    let subset = header.bands.select(&[2, 0]);
+
+    assert_eq!(Some(vec!["nir".to_owned(), "blue".to_owned()]), subset.band_names);
+    assert_eq!(Some(vec![0.02, 0.01]), subset.fwhm);
+    assert_eq!(Some(vec![1, 1]), subset.bbl);
+    assert_eq!(Some("um".to_owned()), subset.wavelength_units);
+}
+
+#[test]
+fn check_band_info_lengths() {
+    let mut header: Header<String> = serde_json::from_str(JSON).unwrap();
+
+    header.dims.channels = 4;
+
+    assert!(header.validate().is_err());
+
+    let missing = BandInfo { fwhm: Some(vec![1.0]), ..BandInfo::NONE };
+
+    assert!(missing.validate(1).is_ok());
+    assert!(missing.validate(2).is_err());
+}
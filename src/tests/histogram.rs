@@ -0,0 +1,109 @@
+use ndarray::arr2;
+
+use crate::algorithms::histogram::{
+    accumulate_distributions, BandDistribution, Histogram, KllSketch,
+};
+
+#[test]
+fn check_histogram_start() {
+    let mut histogram = Histogram::new(4);
+
+    histogram.add(2.0);
+    histogram.add(2.0);
+    assert_eq!(histogram.bin_width, 0.0);
+    assert_eq!(histogram.counts, vec![2, 0, 0, 0]);
+
+    histogram.add(0.0);
+    assert_eq!(histogram.start, 0.0);
+    assert_eq!(histogram.end(), 2.0);
+    assert_eq!(histogram.counts, vec![1, 0, 0, 2]);
+
+    histogram.add(1.2);
+    assert_eq!(histogram.counts, vec![1, 0, 1, 2]);
+}
+
+#[test]
+fn check_histogram_growth() {
+    let mut histogram = Histogram::new(4);
+
+    for x in &[0.0, 4.0, 1.0, 3.5] {
+        histogram.add(*x);
+    }
+
+    assert_eq!(histogram.counts, vec![1, 1, 0, 2]);
+
+    // Doubling upwards merges pairs of bins
+    histogram.add(7.0);
+    assert_eq!((histogram.start, histogram.bin_width), (0.0, 2.0));
+    assert_eq!(histogram.counts, vec![2, 2, 0, 1]);
+
+    // Doubling downwards keeps the upper edge
+    histogram.add(-1.0);
+    assert_eq!((histogram.start, histogram.bin_width), (-8.0, 4.0));
+    assert_eq!(histogram.counts, vec![0, 1, 4, 1]);
+}
+
+#[test]
+fn check_histogram_covers_values() {
+    let mut histogram = Histogram::new(16);
+
+    let values: Vec<f64> = (0..1000).map(|i| ((i * 7919) % 1000) as f64 * 0.37 - 50.0).collect();
+
+    for x in &values {
+        histogram.add(*x);
+    }
+
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+    assert!(histogram.start <= min);
+    assert!(histogram.end() >= max);
+    assert_eq!(histogram.counts.iter().sum::<u64>(), 1000);
+}
+
+#[test]
+fn check_sketch_quantiles() {
+    let mut sketch = KllSketch::new(200, 1);
+
+    let n = 100_000;
+
+    for i in 0..n {
+        sketch.add(((i * 48271) % n) as f64);
+    }
+
+    assert_eq!(sketch.count(), n as u64);
+
+    for q in &[0.01, 0.25, 0.5, 0.75, 0.99] {
+        let estimate = sketch.quantile(*q).unwrap();
+        assert!((estimate / n as f64 - q).abs() < 0.02, "q {}: {}", q, estimate);
+    }
+
+    assert!(KllSketch::new(10, 0).quantile(0.5).is_none());
+}
+
+#[test]
+fn check_band_distributions() {
+    let pixels = arr2(&[
+        [1.0f32, 10.0],
+        [f32::NAN, 20.0],
+        [3.0, f32::INFINITY],
+        [2.0, 40.0],
+    ]);
+
+    let mut acc = vec![BandDistribution::new(8, 50, 0), BandDistribution::new(8, 50, 1)];
+
+    accumulate_distributions(&pixels, &mut acc);
+
+    let summaries: Vec<_> = acc.iter().map(|d| d.summary(&[0.0, 0.5, 1.0])).collect();
+
+    assert_eq!(summaries[0].count, 3);
+    assert_eq!((summaries[0].min, summaries[0].max), (Some(1.0), Some(3.0)));
+    assert_eq!(summaries[0].quantiles, vec![Some(1.0), Some(2.0), Some(3.0)]);
+
+    assert_eq!(summaries[1].count, 3);
+    assert_eq!((summaries[1].min, summaries[1].max), (Some(10.0), Some(40.0)));
+    assert_eq!(summaries[1].histogram.counts.iter().sum::<u64>(), 3);
+
+    let empty = BandDistribution::new(8, 50, 0).summary(&[0.5]);
+    assert_eq!((empty.count, empty.min, empty.quantiles[0]), (0, None, None));
+}
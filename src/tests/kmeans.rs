@@ -0,0 +1,127 @@
+use ndarray::{arr2, Array2};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::algorithms::kmeans::{
+    ClusterAccumulator, isodata_step, IsodataParams, KMeansParams, kmeans_plus_plus,
+    nearest_clusters,
+};
+
+use super::*;
+
+fn two_blobs() -> Array2<f32> {
+    arr2(&[
+        [0.0, 0.0],
+        [0.1, 0.0],
+        [0.0, 0.1],
+        [10.0, 10.0],
+        [10.1, 10.0],
+        [10.0, 10.1],
+    ])
+}
+
+#[test]
+fn check_nearest_clusters() {
+    let centroids = arr2(&[[10.0, 10.0], [0.0, 0.0]]);
+
+    assert_eq!(vec![1, 1, 1, 0, 0, 0], nearest_clusters(&two_blobs(), &centroids));
+}
+
+#[test]
+fn check_kmeans_plus_plus_picks_both_blobs() {
+    let pixels = two_blobs();
+    let mut rng = StdRng::seed_from_u64(7);
+
+    let seeds = kmeans_plus_plus(&pixels, 2, &mut rng);
+
+    assert_eq!(2, seeds.len());
+    assert_ne!(seeds[0] < 3, seeds[1] < 3);
+}
+
+#[test]
+fn check_cluster_centroids() {
+    let pixels = two_blobs();
+    let mut acc = ClusterAccumulator::new(2, 2);
+
+    acc.add(&pixels, &[0, 0, 0, 1, 1, 1]);
+
+    let centroids = acc.centroids(&Array2::zeros((2, 2)));
+
+    assert_eq!(vec![3, 3], acc.counts);
+    assert!((centroids[[0, 0]] - 0.1 / 3.0).abs() < 1e-6);
+    assert!((centroids[[1, 1]] - 30.1 / 3.0).abs() < 1e-5);
+}
+
+#[test]
+fn check_isodata_merge() {
+    let pixels = two_blobs();
+    let mut acc = ClusterAccumulator::new(2, 2);
+
+    acc.add(&pixels, &[0, 0, 0, 1, 1, 1]);
+
+    let centroids = acc.centroids(&Array2::zeros((2, 2)));
+
+    let params = IsodataParams {
+        min_cluster_size: None,
+        split_std_dev: None,
+        merge_distance: Some(100.0),
+        min_clusters: 1,
+        max_clusters: 4,
+    };
+
+    let merged = isodata_step(&centroids, &acc, &params).unwrap();
+
+    assert_eq!(1, merged.nrows());
+    assert!((merged[[0, 0]] - 30.2 / 6.0).abs() < 1e-5);
+}
+
+#[test]
+fn check_isodata_keeps_a_cluster() {
+    let pixels = two_blobs();
+    let mut acc = ClusterAccumulator::new(2, 2);
+
+    acc.add(&pixels, &[0, 0, 0, 1, 1, 1]);
+
+    let centroids = acc.centroids(&Array2::zeros((2, 2)));
+
+    // every cluster is too small, but discarding stops at one
+    let params = IsodataParams {
+        min_cluster_size: Some(10),
+        split_std_dev: None,
+        merge_distance: None,
+        min_clusters: 0,
+        max_clusters: 4,
+    };
+
+    let kept = isodata_step(&centroids, &acc, &params).unwrap();
+
+    assert_eq!(1, kept.nrows());
+}
+
+#[test]
+fn kmeans_rejects_a_sample_smaller_than_the_clusters() {
+    let dims = ImageDims { channels: 2, lines: 1, pixels: 2 };
+    let pixels = Array2::from_shape_fn((2, 2), |(i, c)| (i * 2 + c) as f32);
+    let image = temp_image("kmeans-sample", dims, &pixels);
+
+    let mut bip: SyscallBip<f32> = SyscallBip::new(image.header.clone(), IoConfig::default())
+        .unwrap();
+
+    let params = KMeansParams {
+        clusters: 3,
+        max_iterations: 10,
+        tolerance: 0.0,
+        sample_size: 100,
+        seed: 0,
+        projection: None,
+        isodata: IsodataParams {
+            min_cluster_size: None,
+            split_std_dev: None,
+            merge_distance: None,
+            min_clusters: 1,
+            max_clusters: 6,
+        },
+    };
+
+    assert!(bip.kmeans(&params).is_err());
+}
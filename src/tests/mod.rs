@@ -1,44 +1,165 @@
-use crate::headers::{Header, ImageDims, ImageFormat};
-use crate::io::BasicImage;
-use crate::io::bip::{GlommioBip, SyscallBip};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ndarray::Array2;
+
+use crate::headers::{BandInfo, Header, ImageDims, ImageFormat};
+use crate::io::{BasicImage, IoConfig};
+#[cfg(feature = "glommio-backend")]
+use crate::io::bip::GlommioBip;
+#[cfg(feature = "syscall-backend")]
+use crate::io::bip::SyscallBip;
+#[cfg(feature = "mapped-backend")]
 use crate::io::mapped::bip::MappedBip;
 use crate::util::{make_raw, make_raw_mut};
 
-const TINY_HEADER: Header<&str> = Header {
-    dims: ImageDims {
-        channels: 5,
-        lines: 1000,
-        pixels: 1000,
-    },
-    format: ImageFormat::Bip,
-    path: "data/tiny/bip",
-};
-
-const CROP_HEADER: Header<&str> = Header {
-    dims: ImageDims {
-        channels: 5,
-        lines: 21954,
-        pixels: 28740,
-    },
-    format: ImageFormat::Bip,
-    path: "/data/undergrad-research/bench-data/small-bip",
-};
+/// A path in the temporary directory, unique to this process and call, whose file is removed
+/// when it is dropped.
+struct TempPath(PathBuf);
 
-#[cfg(test)]
-#[cfg_attr(miri, ignore)]
-mod means;
+impl TempPath {
+    fn new(name: &str) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+
+        Self(std::env::temp_dir().join(format!("vanadium-{}-{}-{}", process::id(), n, name)))
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// A temporary BIP image, removed along with its header when dropped.
+struct TempImage {
+    header: Header<String>,
+    _path: TempPath,
+}
+
+/// Writes pixels, one per row, to a temporary BIP image.
+fn temp_image(name: &str, dims: ImageDims, pixels: &Array2<f32>) -> TempImage {
+    let path = TempPath::new(name);
+    let pixels = pixels.as_standard_layout();
+
+    fs::write(&path, unsafe { make_raw(pixels.as_slice().unwrap()) }).unwrap();
+
+    let header = Header {
+        dims,
+        format: ImageFormat::Bip,
+        path: path.0.to_str().unwrap().to_owned(),
+        bands: BandInfo::NONE,
+        map_info: None,
+    };
+
+    TempImage { header, _path: path }
+}
+
+/// Asserts that `actual` has as many values as `expected`, each within `epsilon` of the one
+/// expected.
+fn assert_close<'a, 'b, A, E, X, Y>(what: &str, actual: A, expected: E, epsilon: f64)
+    where A: IntoIterator<Item=&'a X>,
+          E: IntoIterator<Item=&'b Y>,
+          X: Copy + Into<f64> + 'a,
+          Y: Copy + Into<f64> + 'b
+{
+    let actual: Vec<f64> = actual.into_iter().map(|&a| a.into()).collect();
+    let expected: Vec<f64> = expected.into_iter().map(|&e| e.into()).collect();
+
+    assert_eq!(actual.len(), expected.len(), "{} has the wrong length", what);
+
+    for (a, e) in actual.iter().zip(&expected) {
+        assert!((a - e).abs() <= epsilon, "{} is {}, expected {} within {}", what, a, e, epsilon);
+    }
+}
+
+/// Reads a file of native endian floats.
+fn read_floats(path: &dyn AsRef<Path>) -> Vec<f32> {
+    fs::read(path).unwrap()
+        .chunks(4)
+        .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
 
 #[cfg(test)]
 #[cfg_attr(miri, ignore)]
-mod std_devs;
+mod backends;
 
 #[cfg(test)]
-#[cfg_attr(miri, ignore)]
 mod covariances;
 
 #[cfg(test)]
-#[cfg_attr(miri, ignore)]
-mod crop;
+mod kmeans;
+
+#[cfg(test)]
+mod endmembers;
+
+#[cfg(test)]
+mod unmixing;
+
+#[cfg(test)]
+mod expr;
+
+#[cfg(test)]
+mod subset;
+
+#[cfg(test)]
+mod headers;
+
+#[cfg(test)]
+mod transform;
+
+#[cfg(test)]
+mod resample;
+
+#[cfg(test)]
+mod calibration;
+
+#[cfg(test)]
+mod correction;
+
+#[cfg(test)]
+mod filter;
+
+#[cfg(test)]
+mod continuum;
+
+#[cfg(test)]
+mod spatial;
+
+#[cfg(test)]
+mod resize;
+
+#[cfg(test)]
+mod spectrum;
+
+#[cfg(test)]
+mod roi;
+
+#[cfg(test)]
+mod histogram;
+
+#[cfg(test)]
+mod correlation;
+
+#[cfg(test)]
+mod auto;
+
+#[cfg(test)]
+mod bench;
+
+#[cfg(test)]
+mod synthetic;
 
 #[test]
 fn test_raw() {
@@ -67,4 +188,4 @@ fn test_raw() {
 
         assert_eq!(&r[4..8], (7f32).to_ne_bytes());
     }
-}
\ No newline at end of file
+}
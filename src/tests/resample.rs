@@ -0,0 +1,34 @@
+use crate::algorithms::resample::{resampling_matrix, ResampleMethod};
+
+const SOURCE: [f64; 4] = [400.0, 500.0, 600.0, 700.0];
+
+#[test]
+fn check_linear() {
+    let m = resampling_matrix(ResampleMethod::Linear, &SOURCE, &[500.0, 625.0], None).unwrap();
+
+    assert_eq!(&[0.0, 1.0, 0.0, 0.0], m.row(0).as_slice().unwrap());
+    assert_eq!(&[0.0, 0.0, 0.75, 0.25], m.row(1).as_slice().unwrap());
+}
+
+#[test]
+fn check_gaussian() {
+    let fwhm = [10.0, 200.0];
+
+    let m = resampling_matrix(ResampleMethod::Gaussian, &SOURCE, &[600.0, 550.0], Some(&fwhm))
+        .unwrap();
+
+    // a narrow response picks out the band at its center
+    assert!((m[[0, 2]] - 1.0).abs() < 1e-9);
+
+    // a wide one is symmetric about its center
+    assert!((m[[1, 1]] - m[[1, 2]]).abs() < 1e-9);
+    assert!((m.row(1).sum() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn check_outside() {
+    assert!(resampling_matrix(ResampleMethod::Linear, &SOURCE, &[350.0], None).is_err());
+    assert!(resampling_matrix(ResampleMethod::Linear, &SOURCE, &[750.0], None).is_err());
+    assert!(resampling_matrix(ResampleMethod::Gaussian, &SOURCE, &[500.0], None).is_err());
+    assert!(resampling_matrix(ResampleMethod::Gaussian, &SOURCE, &[5000.0], Some(&[1.0])).is_err());
+}
@@ -0,0 +1,116 @@
+use ndarray::{arr2, Array2};
+
+use crate::algorithms::lines::BipWriter;
+use crate::algorithms::resize::{LineResampler, Resampler, ResizeMethod};
+
+/// Streams a single band image through a resampler in uneven batches, returning the output lines.
+fn run(image: &Array2<f32>, resampler: &Resampler) -> Array2<f32> {
+    let (lines, pixels) = image.dim();
+
+    let mut window = LineResampler::new(resampler, pixels, 1, BipWriter::new(Vec::new()));
+
+    let flat = image.clone().into_shape((lines * pixels, 1)).unwrap();
+
+    for batch in flat.exact_chunks((3, 1)) {
+        window.add(&batch);
+    }
+
+    let tail = (lines * pixels) / 3 * 3;
+    window.add(&flat.slice(s![tail.., ..]));
+
+    let bytes = window.finish().unwrap().into_inner();
+
+    let values = bytes.chunks(4)
+        .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+
+    Array2::from_shape_vec(resampler.size(), values).unwrap()
+}
+
+fn image() -> Array2<f32> {
+    arr2(&[
+        [1.0f32, 2.0, 3.0, 4.0, 5.0],
+        [6.0, 7.0, 8.0, 9.0, 10.0],
+        [11.0, 12.0, 13.0, 14.0, 15.0],
+        [16.0, 17.0, 18.0, 19.0, 20.0],
+        [21.0, 22.0, 23.0, 24.0, 25.0],
+    ])
+}
+
+#[test]
+fn check_bin() {
+    let resampler = Resampler::bin(5, 5, (2, 2)).unwrap();
+
+    assert_eq!(resampler.size(), (2, 2));
+    assert_eq!(resampler.scale(), (2.0, 2.0));
+
+    let out = run(&image(), &resampler);
+
+    assert_eq!(out, arr2(&[[4.0, 6.0], [14.0, 16.0]]));
+}
+
+#[test]
+fn check_bin_uneven_factor() {
+    let resampler = Resampler::bin(5, 5, (1, 4)).unwrap();
+
+    assert_eq!(resampler.size(), (5, 1));
+
+    let out = run(&image(), &resampler);
+
+    assert_eq!(out.column(0).to_vec(), vec![2.5, 7.5, 12.5, 17.5, 22.5]);
+}
+
+#[test]
+fn check_invalid_bin_factor() {
+    assert!(Resampler::bin(5, 5, (0, 2)).is_err());
+    assert!(Resampler::bin(5, 5, (2, 6)).is_err());
+}
+
+#[test]
+fn check_nearest() {
+    let resampler = Resampler::resize(ResizeMethod::Nearest, 5, 5, (10, 2)).unwrap();
+
+    let out = run(&image(), &resampler);
+
+    assert_eq!(out.dim(), (10, 2));
+    assert_eq!(out.row(0).to_vec(), vec![2.0, 4.0]);
+    assert_eq!(out.row(1).to_vec(), vec![2.0, 4.0]);
+    assert_eq!(out.row(9).to_vec(), vec![22.0, 24.0]);
+}
+
+#[test]
+fn check_bilinear() {
+    let resampler = Resampler::resize(ResizeMethod::Bilinear, 5, 5, (3, 2)).unwrap();
+
+    let out = run(&image(), &resampler);
+
+    // Output centers fall on input positions 1/3, 2 and 11/3 along lines and 0.75, 3.25 along
+    // pixels, so each value is 1 + 5 * line + pixel.
+    for (i, l) in [1.0 / 3.0, 2.0, 11.0 / 3.0].iter().enumerate() {
+        for (j, p) in [0.75, 3.25].iter().enumerate() {
+            assert!((out[[i, j]] - (1.0 + 5.0 * l + p)).abs() < 1e-5);
+        }
+    }
+}
+
+#[test]
+fn check_upsample_clamps_edges() {
+    let resampler = Resampler::resize(ResizeMethod::Bilinear, 5, 5, (10, 10)).unwrap();
+
+    let out = run(&image(), &resampler);
+
+    assert!((out[[0, 0]] - 1.0).abs() < 1e-6);
+    assert!((out[[9, 9]] - 25.0).abs() < 1e-6);
+    assert_eq!(resampler.scale(), (0.5, 0.5));
+}
+
+#[test]
+fn check_incomplete_image() {
+    let resampler = Resampler::bin(5, 5, (1, 1)).unwrap();
+
+    let mut window = LineResampler::new(&resampler, 5, 1, BipWriter::new(Vec::new()));
+
+    window.add(&Array2::<f32>::zeros((7, 1)));
+
+    assert!(window.finish().is_err());
+}
@@ -0,0 +1,123 @@
+use ndarray::{arr1, arr2, Array2, Axis};
+
+use super::*;
+use crate::algorithms::roi::{polygons_from_geojson, Polygon, RegionAccumulator, Regions};
+
+/// A 3 line by 4 pixel image with 2 channels.
+fn pixels() -> Array2<f64> {
+    Array2::from_shape_fn((12, 2), |(i, c)| (i * i) as f64 + 3.0 * c as f64 + 1e6)
+}
+
+/// Feeds the image to an accumulator in uneven batches.
+fn accumulate<'a>(regions: &'a Regions) -> RegionAccumulator<'a, f64> {
+    let image = pixels();
+    let mut acc = RegionAccumulator::new(regions, 4, 2);
+
+    acc.add(&image.slice(s![..5, ..]));
+    acc.add(&image.slice(s![5.., ..]));
+
+    acc
+}
+
+#[test]
+fn check_contains() {
+    let square = vec![(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)];
+    let hole = vec![(1.0, 1.0), (3.0, 1.0), (3.0, 3.0), (1.0, 3.0)];
+
+    let polygon = Polygon { name: "a".to_owned(), rings: vec![square, hole] };
+
+    assert!(polygon.contains(0.5, 0.5));
+    assert!(polygon.contains(3.5, 2.0));
+    assert!(!polygon.contains(2.0, 2.0));
+    assert!(!polygon.contains(4.5, 0.5));
+}
+
+#[test]
+fn check_geojson() {
+    let collection = r#"{
+        "type": "FeatureCollection",
+        "features": [
+            {
+                "type": "Feature",
+                "properties": {"name": "field"},
+                "geometry": {"type": "Polygon", "coordinates": [[[0, 0], [2, 0], [2, 1], [0, 0]]]}
+            },
+            {
+                "type": "Feature",
+                "properties": {},
+                "geometry": {
+                    "type": "MultiPolygon",
+                    "coordinates": [[[[0, 0], [1, 0], [1, 1]]], [[[2, 2], [3, 2], [3, 3]]]]
+                }
+            }
+        ]
+    }"#;
+
+    let polygons = polygons_from_geojson(collection).unwrap();
+
+    assert_eq!(polygons.len(), 2);
+    assert_eq!(polygons[0].name, "field");
+    assert_eq!(polygons[1].name, "polygon_1");
+    assert_eq!(polygons[1].rings.len(), 2);
+
+    let bare = r#"{"type": "Polygon", "coordinates": [[[0, 0], [1, 0], [1, 1]]]}"#;
+    assert_eq!(polygons_from_geojson(bare).unwrap()[0].rings[0][1], (1.0, 0.0));
+
+    assert!(polygons_from_geojson(r#"{"type": "Point", "coordinates": [0, 0]}"#).is_err());
+    assert!(polygons_from_geojson(r#"{"type": "Polygon", "coordinates": [[[0, 0], [1, 0]]]}"#)
+        .is_err());
+}
+
+#[test]
+fn check_label_statistics() {
+    let labels = vec![0.0, 1.0, 1.0, 2.0, 1.0, f32::NAN, 2.0, 1.0, 0.0, 1.0, 2.0, 2.0];
+
+    let regions = Regions::Labels { labels: labels.clone(), ignore: Some(0.0) };
+    let summaries = accumulate(&regions).summaries();
+
+    assert_eq!(summaries.keys().collect::<Vec<_>>(), vec!["1", "2"]);
+
+    let image = pixels();
+    let rows: Vec<_> = (0..12).filter(|i| labels[*i] == 1.0).collect();
+    let region = image.select(Axis(0), &rows);
+
+    let mean = region.mean_axis(Axis(0)).unwrap();
+    let centered = &region - &mean;
+    let covariance = centered.t().dot(&centered) / rows.len() as f64;
+
+    let summary = &summaries["1"];
+
+    assert_eq!(summary.count, 5);
+    assert_close("mean", &summary.mean, &mean, 1e-9);
+    assert_close("covariance", &summary.covariance, &covariance, 1e-6);
+    assert_close("std dev", &summary.std_dev, &covariance.diag().mapv(f64::sqrt), 1e-9);
+}
+
+#[test]
+fn check_polygon_statistics() {
+    // Covers the centers of pixels 1 and 2 of lines 1 and 2, and overlaps a single pixel region
+    let block = vec![(1.0, 1.0), (3.0, 1.0), (3.0, 3.0), (1.0, 3.0)];
+    let single = vec![(2.2, 2.2), (2.8, 2.2), (2.8, 2.8), (2.2, 2.8)];
+
+    let regions = Regions::Polygons(vec![
+        Polygon { name: "block".to_owned(), rings: vec![block] },
+        Polygon { name: "single".to_owned(), rings: vec![single] },
+    ]);
+
+    let summaries = accumulate(&regions).summaries();
+
+    let image = pixels();
+
+    assert_eq!(summaries["block"].count, 4);
+    assert_close(
+        "block mean",
+        &summaries["block"].mean,
+        &image.select(Axis(0), &[5, 6, 9, 10]).mean_axis(Axis(0)).unwrap(),
+        1e-9,
+    );
+
+    assert_eq!(summaries["single"].count, 1);
+    assert_eq!(summaries["single"].mean, image.row(10));
+    assert_eq!(summaries["single"].std_dev, arr1(&[0.0, 0.0]));
+    assert_eq!(summaries["single"].covariance, arr2(&[[0.0, 0.0], [0.0, 0.0]]));
+}
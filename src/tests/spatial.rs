@@ -0,0 +1,132 @@
+use ndarray::{arr2, Array2};
+
+use crate::algorithms::lines::BipWriter;
+use crate::algorithms::spatial::{FilterKind, LineWindow, SpatialFilter};
+
+/// Streams a single band image through a filter in uneven batches, returning the output lines.
+fn run(image: &Array2<f32>, filter: &SpatialFilter<f32>) -> Array2<f32> {
+    let (lines, pixels) = image.dim();
+
+    let mut window = LineWindow::new(pixels, 1, filter.radius(), BipWriter::new(Vec::new()));
+
+    let flat = image.clone().into_shape((lines * pixels, 1)).unwrap();
+
+    for batch in flat.exact_chunks((5, 1)) {
+        window.add(&batch, filter);
+    }
+
+    let tail = (lines * pixels) / 5 * 5;
+    window.add(&flat.slice(s![tail.., ..]), filter);
+
+    let bytes = window.finish(filter).unwrap().into_inner();
+
+    let values = bytes.chunks(4)
+        .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+
+    Array2::from_shape_vec((lines, pixels), values).unwrap()
+}
+
+#[test]
+fn check_smooth() {
+    let image = arr2(&[
+        [1.0f32, 2.0, 3.0, 4.0],
+        [5.0, 6.0, 7.0, 8.0],
+        [9.0, 10.0, 11.0, 12.0],
+        [13.0, 14.0, 15.0, 16.0],
+    ]);
+
+    let out = run(&image, &SpatialFilter::new(FilterKind::Smooth, 3).unwrap());
+
+    // the image is linear, so the box mean is exact away from the edges
+    assert!((out[[1, 1]] - 6.0).abs() < 1e-5);
+    assert!((out[[2, 2]] - 11.0).abs() < 1e-5);
+
+    // replicated edges: rows 0, 0, 1 and columns 0, 0, 1
+    assert!((out[[0, 0]] - (1.0 + 1.0 + 2.0 + 1.0 + 1.0 + 2.0 + 5.0 + 5.0 + 6.0) / 9.0).abs() < 1e-5);
+}
+
+#[test]
+fn check_median_and_sobel() {
+    let image = arr2(&[
+        [0.0f32, 0.0, 0.0],
+        [0.0, 100.0, 0.0],
+        [0.0, 0.0, 0.0],
+    ]);
+
+    let median = run(&image, &SpatialFilter::new(FilterKind::Median, 3).unwrap());
+
+    assert_eq!(Array2::<f32>::zeros((3, 3)), median);
+
+    let flat = Array2::from_elem((3, 5), 7.0f32);
+    let sobel = run(&flat, &SpatialFilter::new(FilterKind::Sobel, 3).unwrap());
+
+    assert_eq!(Array2::<f32>::zeros((3, 5)), sobel);
+
+    assert!(SpatialFilter::<f32>::new(FilterKind::Smooth, 4).is_err());
+}
+
+#[test]
+fn check_custom_kernel() {
+    let image = arr2(&[
+        [1.0f32, 2.0, 3.0],
+        [4.0, 5.0, 6.0],
+        [7.0, 8.0, 9.0],
+    ]);
+
+    // picks out the pixel to the right, clamped at the edge
+    let shift = vec![vec![0.0f32, 0.0, 0.0], vec![0.0, 0.0, 1.0], vec![0.0, 0.0, 0.0]];
+    let out = run(&image, &SpatialFilter::from_kernel(&shift).unwrap());
+
+    assert_eq!(arr2(&[[2.0f32, 3.0, 3.0], [5.0, 6.0, 6.0], [8.0, 9.0, 9.0]]), out);
+
+    assert!(SpatialFilter::<f32>::from_kernel(&[]).is_err());
+    assert!(SpatialFilter::from_kernel(&[vec![1.0f32, 0.0], vec![0.0, 1.0]]).is_err());
+    assert!(SpatialFilter::from_kernel(&[vec![1.0f32], vec![0.0], vec![1.0]]).is_err());
+    assert!(SpatialFilter::from_kernel(&[vec![f32::NAN]]).is_err());
+}
+
+#[test]
+fn check_gaussian() {
+    let image = arr2(&[
+        [1.0f32, 2.0, 3.0],
+        [4.0, 5.0, 6.0],
+        [7.0, 8.0, 9.0],
+    ]);
+
+    let filter = SpatialFilter::<f32>::gaussian(5, 1.0).unwrap();
+
+    let kernel = match &filter {
+        SpatialFilter::Convolve(kernel) => kernel.clone(),
+        _ => panic!("gaussian should be a convolution"),
+    };
+
+    // normalized, symmetric, and peaked at the center
+    assert!((kernel.sum() - 1.0).abs() < 1e-6);
+    assert_eq!(kernel, kernel.t());
+    assert_eq!(kernel[[0, 2]], kernel[[2, 0]]);
+    assert!(kernel[[2, 2]] > kernel[[1, 2]] && kernel[[1, 2]] > kernel[[0, 2]]);
+
+    // a plane through the center is unchanged there
+    let out = run(&image, &filter);
+    assert!((out[[1, 1]] - 5.0).abs() < 1e-5);
+
+    assert!(SpatialFilter::<f32>::new(FilterKind::Gaussian, 3).is_ok());
+    assert!(SpatialFilter::<f32>::gaussian(4, 1.0).is_err());
+    assert!(SpatialFilter::<f32>::gaussian(3, 0.0).is_err());
+    assert!("gaussian".parse::<FilterKind>().is_ok());
+}
+
+#[test]
+fn check_median_with_nan() {
+    let image = arr2(&[
+        [1.0f32, 2.0, 3.0],
+        [4.0, f32::NAN, 6.0],
+        [7.0, 8.0, 9.0],
+    ]);
+
+    let median = run(&image, &SpatialFilter::new(FilterKind::Median, 3).unwrap());
+
+    // NaN sorts above every number, so the center's median is the next value up
+    assert!((median[[1, 1]] - 6.0).abs() < 1e-6);
+}
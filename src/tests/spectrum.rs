@@ -0,0 +1,92 @@
+use ndarray::{arr1, arr2, Array2};
+
+use super::*;
+use crate::algorithms::endmembers::{parse_coordinates, SpectralLibrary};
+use crate::io::bip::Bip;
+
+/// A 3 line by 4 pixel image with 2 channels, where channel `c` of pixel `(l, p)` holds
+/// `10 * l + p + 100 * c`.
+fn test_image(name: &str) -> TempImage {
+    let dims = ImageDims { channels: 2, lines: 3, pixels: 4 };
+    let pixels = Array2::from_shape_fn((12, 2), |(i, c)| (10 * (i / 4) + i % 4 + 100 * c) as f32);
+
+    temp_image(&format!("spectrum-{}", name), dims, &pixels)
+}
+
+#[test]
+fn check_syscall_read_pixels() {
+    let image = test_image("syscall");
+    let mut bip: SyscallBip<f32> = SyscallBip::new(image.header.clone(), IoConfig::default())
+        .unwrap();
+
+    let pixels = bip.read_pixels(1, 2, 2).unwrap();
+
+    assert_eq!(pixels, arr2(&[[12.0, 112.0], [13.0, 113.0]]));
+    assert_eq!(bip.spectrum(2, 0, 0).unwrap(), arr1(&[20.0, 120.0]));
+}
+
+#[test]
+fn check_mapped_read_pixels() {
+    let image = test_image("mapped");
+    let mut bip: MappedBip<f32> = MappedBip::new(image.header.clone(), IoConfig::default())
+        .unwrap();
+
+    let pixels = bip.read_pixels(0, 3, 1).unwrap();
+
+    assert_eq!(pixels, arr2(&[[3.0, 103.0]]));
+}
+
+#[test]
+fn check_out_of_bounds() {
+    let image = test_image("bounds");
+    let mut bip: SyscallBip<f32> = SyscallBip::new(image.header.clone(), IoConfig::default())
+        .unwrap();
+
+    assert!(bip.read_pixels(3, 0, 1).is_err());
+    assert!(bip.read_pixels(0, 3, 2).is_err());
+    assert!(bip.spectrum(0, 4, 1).is_err());
+}
+
+#[test]
+fn check_window_mean() {
+    let image = test_image("window");
+    let mut bip: SyscallBip<f32> = SyscallBip::new(image.header.clone(), IoConfig::default())
+        .unwrap();
+
+    // The window around the corner is clipped to lines 0-1 and pixels 0-1.
+    assert_eq!(bip.spectrum(0, 0, 1).unwrap(), arr1(&[5.5, 105.5]));
+    assert_eq!(bip.spectrum(1, 1, 1).unwrap(), arr1(&[11.0, 111.0]));
+}
+
+#[test]
+fn check_parse_json_coordinates() {
+    let pairs = parse_coordinates("[[1, 2], [3, 4]]").unwrap();
+    let named = parse_coordinates(r#"[{"line": 1, "pixel": 2}]"#).unwrap();
+
+    assert_eq!(pairs, vec![(1, 2), (3, 4)]);
+    assert_eq!(named, vec![(1, 2)]);
+    assert!(parse_coordinates("[[1]]").is_err());
+}
+
+#[test]
+fn check_parse_csv_coordinates() {
+    assert_eq!(parse_coordinates("line,pixel\n1,2\n\n3, 4\n").unwrap(), vec![(1, 2), (3, 4)]);
+    assert_eq!(parse_coordinates("5,6").unwrap(), vec![(5, 6)]);
+    assert!(parse_coordinates("1,2\nx,y").is_err());
+    assert!(parse_coordinates("1,2\n3").is_err());
+}
+
+#[test]
+fn check_csv_wavelength_columns() {
+    let library = SpectralLibrary {
+        names: vec!["a".to_owned()],
+        coordinates: vec![(0, 1)],
+        spectra: Array2::from_elem((1, 2), 0.5f32),
+        wavelengths: Some(vec![400.0, 500.0]),
+    };
+
+    let mut csv = Vec::new();
+    library.write_csv(&mut csv).unwrap();
+
+    assert_eq!(String::from_utf8(csv).unwrap(), "name,line,pixel,400,500\na,0,1,0.5,0.5\n");
+}
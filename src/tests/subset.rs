@@ -0,0 +1,41 @@
+use ndarray::Array2;
+
+use super::*;
+use crate::opt::BandList;
+
+#[test]
+fn check_band_list() {
+    let bands: BandList = "0-2,5,8-9".parse().unwrap();
+
+    assert_eq!(vec![0, 1, 2, 5, 8, 9], bands.0);
+
+    let reordered: BandList = "3,0-1".parse().unwrap();
+
+    assert_eq!(vec![3, 0, 1], reordered.0);
+}
+
+#[test]
+fn check_invalid_band_list() {
+    assert!("".parse::<BandList>().is_err());
+    assert!("5-2".parse::<BandList>().is_err());
+    assert!("1-a".parse::<BandList>().is_err());
+    assert!("1,,2".parse::<BandList>().is_err());
+}
+
+#[test]
+fn check_subset() {
+    let dims = ImageDims { channels: 3, lines: 3, pixels: 2 };
+    let pixels = Array2::from_shape_fn((6, 3), |(i, j)| (i * 3 + j) as f32);
+    let image = temp_image("subset", dims, &pixels);
+    let out = TempPath::new("subset-out");
+
+    let mut bip: SyscallBip<f32> = SyscallBip::new(image.header.clone(), IoConfig::default())
+        .unwrap();
+
+    // the last two lines of the second column, with bands reordered
+    bip.subset(Some((1, 3)), Some((1, 2)), &[2, 0], &out).unwrap();
+
+    assert_eq!(vec![11.0, 9.0, 17.0, 15.0], read_floats(&out));
+
+    assert!(bip.subset(None, None, &[], &out).is_err());
+}
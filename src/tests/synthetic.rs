@@ -0,0 +1,121 @@
+use ndarray::{Array1, Array2, Axis};
+
+use super::*;
+use crate::algorithms::synthetic::{CubeGenerator, SyntheticModel};
+
+fn mixture() -> SyntheticModel {
+    SyntheticModel::Mixture {
+        endmembers: vec![
+            vec![1.0, 2.0, 3.0, 4.0],
+            vec![4.0, 3.0, 2.0, 1.0],
+            vec![0.0, 5.0, 0.0, 5.0],
+        ],
+        noise: 0.1,
+    }
+}
+
+fn sample_statistics(pixels: &Array2<f64>) -> (Array1<f64>, Array2<f64>) {
+    let means = pixels.mean_axis(Axis(0)).unwrap();
+    let centered = pixels - &means;
+    let covariance = centered.t().dot(&centered) / pixels.nrows() as f64;
+
+    (means, covariance)
+}
+
+#[test]
+fn synthetic_gaussian_matches_model() {
+    let model = SyntheticModel::default_gaussian(4);
+    let pixels = CubeGenerator::new(&model, 3).unwrap().pixels::<f64>(50_000);
+
+    let (means, covariance) = sample_statistics(&pixels);
+
+    assert_close("means", &means, &model.means(), 0.05);
+    assert_close("covariance", &covariance, &model.covariance(), 0.15);
+}
+
+#[test]
+fn synthetic_mixture_matches_model() {
+    let model = mixture();
+    let pixels = CubeGenerator::new(&model, 5).unwrap().pixels::<f64>(50_000);
+
+    let (means, covariance) = sample_statistics(&pixels);
+
+    assert_close("means", &means, &model.means(), 0.03);
+    assert_close("covariance", &covariance, &model.covariance(), 0.05);
+}
+
+#[test]
+fn synthetic_mixture_of_one_is_noise() {
+    let model = SyntheticModel::Mixture { endmembers: vec![vec![1.0, 2.0]], noise: 0.5 };
+
+    assert_close("covariance", &model.covariance(), &[0.25, 0.0, 0.0, 0.25], 1e-12);
+}
+
+#[test]
+fn synthetic_cube_statistics_through_backend() {
+    let model = SyntheticModel::default_gaussian(3);
+    let path = TempPath::new("synthetic-cube");
+
+    CubeGenerator::new(&model, 11).unwrap()
+        .write_cube::<f32>(ImageFormat::Bip, &path, 120, 97)
+        .unwrap();
+
+    let header = Header {
+        dims: ImageDims { channels: 3, lines: 120, pixels: 97 },
+        format: ImageFormat::Bip,
+        path: path.0.clone(),
+        bands: BandInfo::NONE,
+        map_info: None,
+    };
+
+    let mut bip: SyscallBip<f32> = SyscallBip::new(header, IoConfig::default()).unwrap();
+
+    let means = bip.means().unwrap();
+    let covariance = bip.covariance_matrix(Some(&means), None).unwrap();
+
+    assert_close("means", &means, &model.means(), 0.1);
+    assert_close("covariance", &covariance, &model.covariance(), 0.3);
+}
+
+#[test]
+fn synthetic_cube_is_deterministic_in_either_interleave() {
+    let model = mixture();
+    let (lines, pixels, channels) = (6, 5, 4);
+
+    let bip = TempPath::new("synthetic-bip");
+    let bip_again = TempPath::new("synthetic-bip-again");
+    let bsq = TempPath::new("synthetic-bsq");
+
+    let outputs = [(&bip, ImageFormat::Bip), (&bip_again, ImageFormat::Bip), (&bsq, ImageFormat::Bsq)];
+
+    for (path, format) in outputs {
+        CubeGenerator::new(&model, 7).unwrap()
+            .write_cube::<f32>(format, path, lines, pixels)
+            .unwrap();
+    }
+
+    let (bip, bip_again, bsq) = (read_floats(&bip), read_floats(&bip_again), read_floats(&bsq));
+
+    assert_eq!(bip, bip_again);
+
+    for i in 0..(lines * pixels) {
+        for c in 0..channels {
+            assert_eq!(bip[i * channels + c], bsq[c * lines * pixels + i]);
+        }
+    }
+}
+
+#[test]
+fn synthetic_rejects_invalid_models() {
+    let ragged = SyntheticModel::Gaussian { means: vec![0.0, 0.0], covariance: vec![vec![1.0]] };
+    assert!(CubeGenerator::new(&ragged, 0).is_err());
+
+    let singular = SyntheticModel::Gaussian {
+        means: vec![0.0, 0.0],
+        covariance: vec![vec![1.0, 1.0], vec![1.0, 1.0]],
+    };
+    assert!(CubeGenerator::new(&singular, 0).is_err());
+
+    let negative = SyntheticModel::Mixture { endmembers: vec![vec![1.0]], noise: -1.0 };
+    assert!(negative.validate().is_err());
+}
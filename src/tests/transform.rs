@@ -0,0 +1,18 @@
+use ndarray::{arr2, Array2};
+
+use crate::image_formats::bip::BipDims;
+
+#[test]
+fn check_transform_orientation() {
+    // one row per output channel, one column per input channel
+    let transform = arr2(&[[1.0f32, 0.0, 0.0], [1.0, 1.0, 1.0]]);
+
+    let mut pixels = arr2(&[[1.0f32, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+
+    // the batch is one pixel short, as at the end of an image
+    let mut out = Array2::zeros((3, 2));
+
+    BipDims::map_transform(&mut pixels.view_mut(), &transform, &mut out, None, None);
+
+    assert_eq!(arr2(&[[1.0f32, 6.0], [4.0, 15.0], [0.0, 0.0]]), out);
+}
@@ -0,0 +1,53 @@
+use ndarray::{arr2, Array2};
+
+use crate::algorithms::unmixing::{nnls, Unmixer, UnmixingMethod};
+
+fn unmix(method: UnmixingMethod, pixels: Array2<f32>) -> Array2<f32> {
+    let endmembers = arr2(&[[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+    let unmixer = Unmixer::new(method, endmembers).unwrap();
+
+    let mut out = Array2::zeros((pixels.nrows(), 3));
+    unmixer.unmix(&pixels, &mut out);
+
+    out
+}
+
+#[test]
+fn check_nnls() {
+    // unconstrained minimum is at (2, -1)
+    let gram = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+
+    let x = nnls(&gram, &[2.0, -1.0]);
+
+    assert!((x[0] - 2.0).abs() < 1e-9);
+    assert_eq!(0.0, x[1]);
+}
+
+#[test]
+fn check_exact_mixture() {
+    let pixels = arr2(&[[0.3, 0.7, 0.0]]);
+
+    for method in [UnmixingMethod::Ucls, UnmixingMethod::Scls, UnmixingMethod::Fcls].iter() {
+        let out = unmix(*method, pixels.clone());
+
+        assert!((out[[0, 0]] - 0.3).abs() < 1e-4, "{:?}: {}", method, out);
+        assert!((out[[0, 1]] - 0.7).abs() < 1e-4, "{:?}: {}", method, out);
+        assert!(out[[0, 2]].abs() < 1e-4, "{:?}: {}", method, out);
+    }
+}
+
+#[test]
+fn check_constraints() {
+    let pixels = arr2(&[[-0.2, 1.4, 0.0]]);
+
+    let ucls = unmix(UnmixingMethod::Ucls, pixels.clone());
+    assert!((ucls[[0, 0]] + 0.2).abs() < 1e-5);
+    assert!((ucls[[0, 1]] - 1.4).abs() < 1e-5);
+
+    let scls = unmix(UnmixingMethod::Scls, pixels.clone());
+    assert!((scls[[0, 0]] + scls[[0, 1]] - 1.0).abs() < 1e-5);
+
+    let fcls = unmix(UnmixingMethod::Fcls, pixels);
+    assert!(fcls[[0, 0]].abs() < 1e-4);
+    assert!((fcls[[0, 1]] - 1.0).abs() < 1e-4);
+}